@@ -0,0 +1,44 @@
+//! `encrypt` and `decrypt` under each cipher suite, from short chat messages
+//! up to 100 MB. Run with `cargo bench --bench encryption`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::{Mode, SUITES};
+
+const SIZES: [usize; 4] = [100, 10_000, 1_000_000, 100_000_000];
+
+fn encryption(c: &mut Criterion) {
+    for cipher_suite in SUITES {
+        let (mut sender, mut reciever) = common::sessions(Mode::Classic, cipher_suite);
+
+        let mut group = c.benchmark_group(format!("encryption/{cipher_suite}"));
+
+        for size in SIZES {
+            let plaintext = "x".repeat(size);
+            let ciphertext = sender.encrypt(&plaintext).unwrap();
+
+            group.throughput(Throughput::Bytes(size as u64));
+            // large messages take long enough that fewer samples still settle
+            group.sample_size(if size >= 1_000_000 { 10 } else { 100 });
+
+            group.bench_with_input(
+                BenchmarkId::new("encrypt", size),
+                &plaintext,
+                |b, plaintext| b.iter(|| sender.encrypt(plaintext).unwrap()),
+            );
+            group.bench_with_input(
+                BenchmarkId::new("decrypt", size),
+                &ciphertext,
+                |b, ciphertext| b.iter(|| reciever.decrypt(ciphertext).unwrap()),
+            );
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, encryption);
+criterion_main!(benches);
@@ -0,0 +1,120 @@
+//! Each step of a handshake in every mode, with the identity keys made
+//! beforehand. Run with `cargo bench --bench handshake`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use e2eoffline::{CipherSuite, E2EOfflineBuilder, E2EOfflinePakeBuilder, Identity};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::{Handshake, Mode};
+
+fn steps(c: &mut Criterion) {
+    let reciever_identity = Identity::generate();
+    let sender_identity = Identity::generate();
+
+    for mode in Mode::ALL {
+        let mut group = c.benchmark_group(format!("handshake/{}", mode.name()));
+
+        let hello = common::hello(&mut common::reciever(&reciever_identity, mode), mode);
+        let handshake = || {
+            common::handshake(
+                &reciever_identity,
+                &sender_identity,
+                mode,
+                CipherSuite::default(),
+            )
+        };
+
+        group.bench_function("hello", |b| {
+            b.iter_batched(
+                || common::reciever(&reciever_identity, mode),
+                |mut reciever| common::hello(&mut reciever, mode),
+                BatchSize::SmallInput,
+            )
+        });
+
+        group.bench_function("set_hello", |b| {
+            b.iter_batched(
+                || E2EOfflineBuilder::new_sender_with_identity(&sender_identity),
+                |mut sender| {
+                    common::set_hello(&mut sender, mode, &hello);
+                    sender
+                },
+                BatchSize::SmallInput,
+            )
+        });
+
+        group.bench_function("send", |b| {
+            b.iter_batched(
+                || {
+                    let mut sender = E2EOfflineBuilder::new_sender_with_identity(&sender_identity);
+                    common::set_hello(&mut sender, mode, &hello);
+                    sender
+                },
+                |mut sender| sender.send().unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+
+        group.bench_function("recieve", |b| {
+            b.iter_batched(
+                handshake,
+                |mut handshake| {
+                    handshake.reciever.recieve(&handshake.exchange).unwrap();
+                    handshake
+                },
+                BatchSize::SmallInput,
+            )
+        });
+
+        group.bench_function("build", |b| {
+            b.iter_batched(
+                || {
+                    let Handshake {
+                        mut reciever,
+                        exchange,
+                        ..
+                    } = handshake();
+                    reciever.recieve(&exchange).unwrap();
+                    reciever
+                },
+                |reciever| reciever.build().unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+
+        group.bench_function("full", |b| {
+            b.iter(|| {
+                let Handshake {
+                    mut reciever,
+                    sender,
+                    exchange,
+                } = handshake();
+                reciever.recieve(&exchange).unwrap();
+
+                (sender.build().unwrap(), reciever.build().unwrap())
+            })
+        });
+
+        group.finish();
+    }
+}
+
+fn pake(c: &mut Criterion) {
+    c.bench_function("handshake/pake/full", |b| {
+        b.iter(|| {
+            let mut sender = E2EOfflinePakeBuilder::new_sender("123-456");
+            let mut reciever = E2EOfflinePakeBuilder::new_reciever("123-456");
+
+            reciever.recieve(&sender.send().unwrap()).unwrap();
+            sender.recieve(&reciever.send().unwrap()).unwrap();
+            reciever.recieve(&sender.send().unwrap()).unwrap();
+
+            (sender.build().unwrap(), reciever.build().unwrap())
+        })
+    });
+}
+
+criterion_group!(benches, steps, pake);
+criterion_main!(benches);
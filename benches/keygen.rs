@@ -0,0 +1,88 @@
+//! How long it takes to make keys, which is most of the wait behind the
+//! webapp's `is_doing_work` flag. Run with `cargo bench --bench keygen`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use e2eoffline::{E2EOffline, E2EOfflineBuilder, HandshakeMode, Identity};
+use rand::rngs::OsRng;
+use rsa::RsaPrivateKey;
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::Mode;
+
+// identities are 3072-bit; the other sizes are here for comparison
+fn rsa_keygen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rsa_keygen");
+    group.sample_size(10);
+
+    for bits in [2048, 3072, 4096] {
+        group.bench_with_input(BenchmarkId::from_parameter(bits), &bits, |b, &bits| {
+            b.iter(|| RsaPrivateKey::new(&mut OsRng, bits).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+// what the webapp and cmdline actually call, identity key included
+fn new_builder(c: &mut Criterion) {
+    let mut group = c.benchmark_group("new_builder");
+    group.sample_size(20);
+
+    group.bench_function("sender", |b| b.iter(E2EOfflineBuilder::new_sender));
+    group.bench_function("reciever/classic", |b| {
+        b.iter(|| E2EOfflineBuilder::new_reciever_with_mode(HandshakeMode::Classic))
+    });
+    group.bench_function("reciever/hybrid", |b| {
+        b.iter(|| E2EOfflineBuilder::new_reciever_with_mode(HandshakeMode::Hybrid))
+    });
+
+    group.finish();
+}
+
+// the per-handshake keys alone, with an existing identity
+fn ephemeral_keys(c: &mut Criterion) {
+    let identity = Identity::generate();
+    let mut group = c.benchmark_group("ephemeral_keys");
+
+    for mode in [Mode::Classic, Mode::Hybrid] {
+        group.bench_function(mode.name(), |b| {
+            b.iter(|| common::reciever(&identity, mode))
+        });
+    }
+
+    for one_time_prekeys in [1, 10, 100] {
+        group.bench_with_input(
+            BenchmarkId::new("prekey_bundle", one_time_prekeys),
+            &one_time_prekeys,
+            |b, &one_time_prekeys| {
+                b.iter_batched(
+                    || common::reciever(&identity, Mode::Classic),
+                    |mut reciever| {
+                        reciever
+                            .get_prekey_bundle_encoded(one_time_prekeys)
+                            .unwrap()
+                    },
+                    criterion::BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn passphrase(c: &mut Criterion) {
+    let mut group = c.benchmark_group("passphrase");
+    group.sample_size(10);
+
+    group.bench_function("argon2id", |b| {
+        b.iter(|| E2EOffline::from_passphrase("correct horse battery staple").unwrap())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, rsa_keygen, new_builder, ephemeral_keys, passphrase);
+criterion_main!(benches);
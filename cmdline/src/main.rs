@@ -1,6 +1,13 @@
-use std::io::stdin;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, stdin, Write},
+    path::{Path, PathBuf},
+};
 
-use e2eoffline::{E2EOffline, E2EOfflineBuilder};
+use e2eoffline::{
+    CipherSuite, ContactBook, E2EOffline, E2EOfflineBuilder, E2EOfflinePakeBuilder, HandshakeMode,
+    Identity, SessionExport, Trust, Verification,
+};
 
 macro_rules! readline {
     ($buffer:ident) => {
@@ -12,68 +19,200 @@ macro_rules! readline {
 fn main() -> anyhow::Result<()> {
     let mut buffer = String::new();
 
-    let mut e2e = loop {
-        println!("s to send, r to recieve, k to use an existing key");
+    let use_passphrase = std::env::args().any(|arg| arg == "--passphrase");
 
+    // only used when sending, as the sender picks the cipher suite, and for a
+    // raw key, which doesn't carry one
+    let cipher_suite = match std::env::args().skip_while(|arg| arg != "--cipher").nth(1) {
+        Some(name) => name.parse()?,
+        None => CipherSuite::default(),
+    };
+
+    let mut e2e = if use_passphrase {
+        println!("Passphrase? (agreed on with the other user in person)");
         readline!(buffer);
-        let r = buffer.trim().to_lowercase();
 
-        match r.as_str() {
-            "k" => {
-                println!("Shared Key?");
-                readline!(buffer);
+        E2EOffline::from_passphrase(buffer.trim_end_matches(['\r', '\n']))
+    } else {
+        let identity = match std::env::args()
+            .skip_while(|arg| arg != "--identity")
+            .nth(1)
+        {
+            Some(path) => load_identity(Path::new(&path))?,
+            None => Identity::generate(),
+        };
 
-                break E2EOffline::from_key_base64(&buffer);
-            }
-            "r" => {
-                println!("Recieving!");
-                let mut reciever = E2EOfflineBuilder::new_reciever();
-                println!("Your public key is {}", reciever.get_pubkey_encoded()?,);
-                println!("Sender public key? (preferably exchanged with them in person)");
-                readline!(buffer);
+        loop {
+            println!("s to send, r to recieve, h to recieve with a post-quantum hybrid key, b to send to a prekey bundle, p to recieve with a prekey bundle, c to pair with a short code, j to join a short code pairing, l to resume a saved handshake, i to import a session, k to use a raw key");
 
-                reciever.set_other_public_key_encoded(&buffer)?;
+            readline!(buffer);
+            let r = buffer.trim().to_lowercase();
 
-                println!("Sender key exchange text?");
+            match r.as_str() {
+                "c" => {
+                    let code = E2EOfflinePakeBuilder::generate_code();
+                    println!("Your code is {code}. Read it to the other user over the phone");
 
-                readline!(buffer);
+                    let mut sender = E2EOfflinePakeBuilder::new_sender(&code);
+                    println!(
+                        "Your pairing text is {}. Send it to the other user",
+                        sender.send()?
+                    );
 
-                reciever.recieve(&buffer)?;
+                    println!("Other user's pairing text?");
+                    readline!(buffer);
 
-                println!(
-                    "Your key is {}, do not send it to anyone",
-                    reciever.get_shared_key()?
-                );
+                    sender.recieve(&buffer)?;
 
-                break reciever.build();
-            }
-            "s" => {
-                println!("Sending!");
-                let mut sender = E2EOfflineBuilder::new_sender();
-                println!("Your public key is {}.", sender.get_pubkey_encoded()?);
+                    println!(
+                        "Your confirmation text is {}. Send it to the other user",
+                        sender.send()?
+                    );
 
-                println!("Reciever public key? (preferably exchanged with them in person)");
-                readline!(buffer);
+                    break sender.build();
+                }
+                "j" => {
+                    println!("Code?");
+                    readline!(buffer);
 
-                sender.set_other_public_key_encoded(&buffer)?;
+                    let mut reciever = E2EOfflinePakeBuilder::new_reciever(&buffer);
 
-                let token = sender.send()?;
+                    println!("Other user's pairing text?");
+                    readline!(buffer);
 
-                println!("Your key exchange text is {token}. Send it to the other user");
-                println!(
-                    "Your key is {}, do not send it to anyone",
-                    sender.get_shared_key()?
-                );
+                    reciever.recieve(&buffer)?;
 
-                break sender.build();
-            }
+                    println!(
+                        "Your pairing text is {}. Send it to the other user",
+                        reciever.send()?
+                    );
+
+                    println!("Other user's confirmation text?");
+                    readline!(buffer);
+
+                    reciever.recieve(&buffer)?;
+
+                    break reciever.build();
+                }
+                "l" => {
+                    println!("Saved handshake file?");
+                    readline!(buffer);
+                    let path = PathBuf::from(buffer.trim());
+
+                    println!("Saved handshake passphrase?");
+                    readline!(buffer);
+
+                    let state = SessionExport::from_encoded(&fs::read_to_string(path)?)?;
+                    let reciever = E2EOfflineBuilder::import_state(
+                        &state,
+                        buffer.trim_end_matches(['\r', '\n']),
+                    )?;
+
+                    match finish_reciever(reciever)? {
+                        Some(e2e) => break Ok(e2e),
+                        None => return Ok(()),
+                    }
+                }
+                "i" => {
+                    println!("Session export?");
+                    readline!(buffer);
+                    let export = SessionExport::from_encoded(&buffer)?;
+
+                    println!("Session export passphrase?");
+                    readline!(buffer);
+
+                    break E2EOffline::import_session(
+                        &export,
+                        buffer.trim_end_matches(['\r', '\n']),
+                    );
+                }
+                "k" => {
+                    println!("Raw key?");
+                    readline!(buffer);
+
+                    break E2EOffline::from_key_base64(&buffer).map(|mut e2e| {
+                        e2e.set_cipher_suite(cipher_suite);
+                        e2e
+                    });
+                }
+                "r" | "h" => {
+                    println!("Recieving!");
+                    let reciever = E2EOfflineBuilder::new_reciever_with_identity(
+                        &identity,
+                        match r.as_str() {
+                            "h" => HandshakeMode::Hybrid,
+                            _ => HandshakeMode::Classic,
+                        },
+                    );
+                    println!("Your hello is {}", reciever.get_hello_encoded()?,);
+
+                    match finish_reciever(reciever)? {
+                        Some(e2e) => break Ok(e2e),
+                        None => return Ok(()),
+                    }
+                }
+                "p" => {
+                    println!("Recieving with prekeys!");
+                    let mut reciever = E2EOfflineBuilder::new_reciever_with_identity(
+                        &identity,
+                        HandshakeMode::Classic,
+                    );
+                    println!(
+                        "Your prekey bundle is {}",
+                        reciever.get_prekey_bundle_encoded(10)?
+                    );
+
+                    match finish_reciever(reciever)? {
+                        Some(e2e) => break Ok(e2e),
+                        None => return Ok(()),
+                    }
+                }
+                "b" => {
+                    println!("Sending to a prekey bundle!");
+                    let mut sender = E2EOfflineBuilder::new_sender_with_identity(&identity);
+                    sender.set_cipher_suite(cipher_suite);
+                    println!("Your public key is {}.", sender.get_pubkey_encoded()?);
 
-            _ => println!("Bad choice"),
+                    println!("Reciever prekey bundle?");
+                    readline!(buffer);
+
+                    sender.set_prekey_bundle_encoded(&buffer)?;
+
+                    check_contact(&sender)?;
+
+                    let token = sender.send()?;
+
+                    println!("Your key exchange text is {token}. Send it to the other user along with your first message");
+                    break sender.build();
+                }
+                "s" => {
+                    println!("Sending!");
+                    let mut sender = E2EOfflineBuilder::new_sender_with_identity(&identity);
+                    sender.set_cipher_suite(cipher_suite);
+                    println!("Your public key is {}.", sender.get_pubkey_encoded()?);
+
+                    println!("Reciever hello? (preferably exchanged with them in person)");
+                    readline!(buffer);
+
+                    sender.set_other_public_key_encoded(&buffer)?;
+
+                    check_contact(&sender)?;
+
+                    let token = sender.send()?;
+
+                    println!("Your key exchange text is {token}. Send it to the other user");
+                    break sender.build();
+                }
+
+                _ => println!("Bad choice"),
+            }
         }
     }?;
 
+    println!("Messages are encrypted with {}", e2e.cipher_suite());
+
     loop {
-        println!("e for encrypt, d for decrypt, q for quit");
+        println!("e for encrypt, d for decrypt, r to switch to a new key, x to export this session, k to show the raw key, q for quit");
         readline!(buffer);
 
         match buffer.trim().to_lowercase().as_str() {
@@ -88,7 +227,42 @@ fn main() -> anyhow::Result<()> {
                 println!("Ciphertext?");
 
                 readline!(buffer);
-                println!("{}", e2e.decrypt(&buffer)?);
+                if E2EOffline::is_rekey_message(&buffer) {
+                    e2e.apply_rekey(&buffer)?;
+                    println!("The other user switched to a new key");
+                } else {
+                    println!("{}", e2e.decrypt(&buffer)?.expose_secret());
+                }
+            }
+
+            "r" => {
+                println!(
+                    "Your rekey text is {}. Send it to the other user",
+                    e2e.rekey()?
+                );
+            }
+
+            "x" => {
+                println!("Passphrase to protect the export with?");
+                readline!(buffer);
+
+                println!(
+                    "Your session export is {}. Import it with i, or in the webapp",
+                    e2e.export_session(buffer.trim_end_matches(['\r', '\n']))?
+                        .to_encoded()
+                );
+            }
+
+            "k" => {
+                println!("Anyone with the raw key can read and forge your messages. Show it anyway? (yes/no)");
+                readline!(buffer);
+
+                if buffer.trim().to_lowercase() == "yes" {
+                    println!(
+                        "Your raw key is {}, do not send it to anyone",
+                        e2e.dangerously_export_raw_key().expose_secret()
+                    );
+                }
             }
 
             "q" => break,
@@ -99,3 +273,143 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+// finishes a reciever's handshake, or saves it to finish later if the sender
+// hasn't replied yet, in which case there is no key
+fn finish_reciever(mut reciever: E2EOfflineBuilder) -> anyhow::Result<Option<E2EOffline>> {
+    let mut buffer = String::new();
+
+    println!("Sender public key? (preferably exchanged with them in person, w to save this handshake and finish it later)");
+    readline!(buffer);
+
+    if buffer.trim().to_lowercase() == "w" {
+        println!("File to save the handshake to?");
+        readline!(buffer);
+        let path = PathBuf::from(buffer.trim());
+
+        println!("Passphrase to protect it with?");
+        readline!(buffer);
+
+        fs::write(
+            &path,
+            reciever
+                .export_state(buffer.trim_end_matches(['\r', '\n']))?
+                .to_encoded(),
+        )?;
+        println!("Saved to {}, resume it with l", path.display());
+
+        return Ok(None);
+    }
+
+    reciever.set_other_public_key_encoded(&buffer)?;
+
+    check_contact(&reciever)?;
+
+    println!("Sender key exchange text?");
+
+    readline!(buffer);
+
+    reciever.recieve(&buffer)?;
+
+    Ok(Some(reciever.build()?))
+}
+
+// loads the identity saved at path, or makes a new one there if there isn't
+// one yet, so the same public key is used every run
+fn load_identity(path: &Path) -> anyhow::Result<Identity> {
+    let mut buffer = String::new();
+
+    let identity = match fs::read_to_string(path) {
+        Ok(pem) => {
+            println!("Identity passphrase?");
+            readline!(buffer);
+
+            Identity::from_private_key_pem(&pem, buffer.trim_end_matches(['\r', '\n']))?
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            println!(
+                "No identity at {}, making a new one. Passphrase to protect it with?",
+                path.display()
+            );
+            readline!(buffer);
+
+            let identity = Identity::generate();
+            let pem = identity.to_private_key_pem(buffer.trim_end_matches(['\r', '\n']))?;
+
+            // never over a key file that turned up in the meantime
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)?
+                .write_all(pem.as_bytes())?;
+
+            identity
+        }
+        Err(e) => Err(e)?,
+    };
+
+    println!(
+        "Your identity's fingerprint is {}",
+        identity.get_fingerprint()?
+    );
+
+    Ok(identity)
+}
+
+fn contacts_path() -> PathBuf {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(".e2eoffline_contacts")
+}
+
+// checks the other user's key against the contact book, trusting it if this is
+// the first time we've seen them
+fn check_contact(builder: &E2EOfflineBuilder) -> anyhow::Result<()> {
+    let mut buffer = String::new();
+
+    let path = contacts_path();
+    let mut contacts = match fs::read_to_string(&path) {
+        Ok(encoded) => ContactBook::from_encoded(&encoded)?,
+        // anything else could be a book full of pinned keys, so it isn't
+        // written over
+        Err(e) if e.kind() == io::ErrorKind::NotFound => ContactBook::new(),
+        Err(e) => Err(e)?,
+    };
+
+    println!("Contact name?");
+    readline!(buffer);
+    let name = buffer.trim().to_string();
+
+    let verification = match contacts.check(
+        &name,
+        &builder.get_other_fingerprint()?,
+        builder.get_other_expiry(),
+    )? {
+        Trust::FirstSeen => {
+            println!("First time talking to {name}, their key has been remembered");
+            Verification::Unverified
+        }
+        Trust::Known(verification) => {
+            println!("{name}'s key matches the one on file ({verification})");
+            verification
+        }
+    };
+
+    if verification == Verification::Unverified {
+        println!(
+            "Your fingerprint is {}, theirs is {}. v if you've compared them in person, anything else to skip",
+            builder.get_fingerprint()?,
+            builder.get_other_fingerprint()?
+        );
+        readline!(buffer);
+
+        if buffer.trim().to_lowercase() == "v" {
+            contacts.mark_verified(&name)?;
+        }
+    }
+
+    fs::write(path, contacts.to_encoded())?;
+
+    Ok(())
+}
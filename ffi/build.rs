@@ -0,0 +1,17 @@
+// generates the C header into OUT_DIR, where tests/header.rs compares it with
+// the checked-in include/e2eoffline.h, so C users don't need cbindgen
+fn main() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let header = format!("{}/e2eoffline.h", std::env::var("OUT_DIR").unwrap());
+
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml")).unwrap())
+        .generate()
+        .expect("unable to generate the C header")
+        .write_to_file(&header);
+
+    println!("cargo:rustc-env=E2EOFFLINE_GENERATED_HEADER={header}");
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
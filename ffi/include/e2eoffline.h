@@ -0,0 +1,163 @@
+#ifndef E2EOFFLINE_H
+#define E2EOFFLINE_H
+
+/* Generated by cbindgen from ffi/src/lib.rs, don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// How the reciever wants the shared key to be agreed on.
+typedef enum E2EOfflineHandshakeMode {
+  E2E_OFFLINE_HANDSHAKE_MODE_CLASSIC,
+  E2E_OFFLINE_HANDSHAKE_MODE_HYBRID,
+} E2EOfflineHandshakeMode;
+
+// What a function did. Apart from the first three and the last two, each
+// code matches the part of the library the error came from. `Panic` means
+// the library hit a bug; the handle that was passed in shouldn't be used
+// again.
+typedef enum E2EOfflineStatus {
+  E2E_OFFLINE_STATUS_OK = 0,
+  E2E_OFFLINE_STATUS_NULL_POINTER,
+  E2E_OFFLINE_STATUS_INVALID_UTF8,
+  E2E_OFFLINE_STATUS_CIPHER,
+  E2E_OFFLINE_STATUS_CONTACT,
+  E2E_OFFLINE_STATUS_HELLO,
+  E2E_OFFLINE_STATUS_HYBRID,
+  E2E_OFFLINE_STATUS_IDENTITY,
+  E2E_OFFLINE_STATUS_KEY_FILE,
+  E2E_OFFLINE_STATUS_PAKE,
+  E2E_OFFLINE_STATUS_PASSPHRASE,
+  E2E_OFFLINE_STATUS_PREKEY,
+  E2E_OFFLINE_STATUS_RECIEVE,
+  E2E_OFFLINE_STATUS_REKEY,
+  E2E_OFFLINE_STATUS_SESSION,
+  E2E_OFFLINE_STATUS_STATE,
+  E2E_OFFLINE_STATUS_ENCODING,
+  E2E_OFFLINE_STATUS_CRYPTO,
+  E2E_OFFLINE_STATUS_OTHER,
+  E2E_OFFLINE_STATUS_PANIC,
+} E2EOfflineStatus;
+
+// A conversation under a shared key, from [`e2eoffline_builder_build`].
+typedef struct E2EOffline E2EOffline;
+
+// A handshake in progress, from [`e2eoffline_builder_new_sender`] or
+// [`e2eoffline_builder_new_reciever`].
+typedef struct E2EOfflineBuilder E2EOfflineBuilder;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Describes the last error on this thread, or returns NULL if there hasn't
+// been one. The string stays valid until another call on this thread fails.
+const char *e2eoffline_last_error(void);
+
+// Frees a string handed out by this library. It is zeroed first, as it may
+// be a decrypted message. NULL is ignored.
+//
+// # Safety
+//
+// `text` must be NULL or a string from this library that hasn't been freed.
+void e2eoffline_string_free(char *text);
+
+// Starts a sender handshake with a freshly generated key. Returns NULL if
+// that fails.
+struct E2EOfflineBuilder *e2eoffline_builder_new_sender(void);
+
+// Starts a reciever handshake with a freshly generated key. Returns NULL if
+// that fails.
+struct E2EOfflineBuilder *e2eoffline_builder_new_reciever(enum E2EOfflineHandshakeMode mode);
+
+// Frees a builder. NULL is ignored.
+//
+// # Safety
+//
+// `builder` must be NULL or a builder that hasn't been freed or built.
+void e2eoffline_builder_free(struct E2EOfflineBuilder *builder);
+
+// Writes this side's public key to `out`. The sender gives it to the
+// reciever.
+//
+// # Safety
+//
+// `builder` must be a live builder and `out` must be writable.
+enum E2EOfflineStatus e2eoffline_builder_get_pubkey(const struct E2EOfflineBuilder *builder,
+                                                    char **out);
+
+// Writes the reciever's signed hello to `out`, which the reciever gives to
+// the sender.
+//
+// # Safety
+//
+// `builder` must be a live builder and `out` must be writable.
+enum E2EOfflineStatus e2eoffline_builder_get_hello(const struct E2EOfflineBuilder *builder,
+                                                   char **out);
+
+// Sets the other side's key: the reciever's hello for a sender, or the
+// sender's public key for a reciever.
+//
+// # Safety
+//
+// `builder` must be a live builder and `pubkey` a NUL-terminated string.
+enum E2EOfflineStatus e2eoffline_builder_set_other_public_key(struct E2EOfflineBuilder *builder,
+                                                              const char *pubkey);
+
+// Writes the sender's key exchange text to `out`, for the reciever's
+// [`e2eoffline_builder_recieve`].
+//
+// # Safety
+//
+// `builder` must be a live builder and `out` must be writable.
+enum E2EOfflineStatus e2eoffline_builder_send(struct E2EOfflineBuilder *builder, char **out);
+
+// Takes in the sender's key exchange text.
+//
+// # Safety
+//
+// `builder` must be a live builder and `exchange` a NUL-terminated string.
+enum E2EOfflineStatus e2eoffline_builder_recieve(struct E2EOfflineBuilder *builder,
+                                                 const char *exchange);
+
+// Finishes the handshake, writing the conversation to `out`. The builder is
+// used up either way, so it must not be freed or used again.
+//
+// # Safety
+//
+// `builder` must be a live builder and `out` must be writable.
+enum E2EOfflineStatus e2eoffline_builder_build(struct E2EOfflineBuilder *builder,
+                                               struct E2EOffline **out);
+
+// Frees a conversation. NULL is ignored.
+//
+// # Safety
+//
+// `e2e` must be NULL or a conversation that hasn't been freed.
+void e2eoffline_free(struct E2EOffline *e2e);
+
+// Encrypts a message, writing the ciphertext to `out`.
+//
+// # Safety
+//
+// `e2e` must be a live conversation, `plaintext` a NUL-terminated string and
+// `out` writable.
+enum E2EOfflineStatus e2eoffline_encrypt(struct E2EOffline *e2e, const char *plaintext, char **out);
+
+// Decrypts a message, writing the plaintext to `out`.
+//
+// # Safety
+//
+// `e2e` must be a live conversation, `ciphertext` a NUL-terminated string
+// and `out` writable.
+enum E2EOfflineStatus e2eoffline_decrypt(struct E2EOffline *e2e,
+                                         const char *ciphertext,
+                                         char **out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* E2EOFFLINE_H */
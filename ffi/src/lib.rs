@@ -0,0 +1,421 @@
+//! C bindings for e2eoffline. See `include/e2eoffline.h`, which cbindgen
+//! generates from this file; `cargo test` checks that it is up to date.
+//!
+//! Every function returns an [`E2EOfflineStatus`]; on failure,
+//! [`e2eoffline_last_error`] describes what went wrong. Strings handed out
+//! through `char **out` belong to the caller and are freed with
+//! [`e2eoffline_string_free`].
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use e2eoffline::{Error, HandshakeMode};
+use zeroize::Zeroize;
+
+/// A handshake in progress, from [`e2eoffline_builder_new_sender`] or
+/// [`e2eoffline_builder_new_reciever`].
+pub struct E2EOfflineBuilder {
+    inner: e2eoffline::E2EOfflineBuilder,
+}
+
+/// A conversation under a shared key, from [`e2eoffline_builder_build`].
+pub struct E2EOffline {
+    inner: e2eoffline::E2EOffline,
+}
+
+/// What a function did. Apart from the first three and the last two, each
+/// code matches the part of the library the error came from. `Panic` means
+/// the library hit a bug; the handle that was passed in shouldn't be used
+/// again.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum E2EOfflineStatus {
+    Ok = 0,
+    NullPointer,
+    InvalidUtf8,
+    Cipher,
+    Contact,
+    Hello,
+    Hybrid,
+    Identity,
+    KeyFile,
+    Pake,
+    Passphrase,
+    Prekey,
+    Recieve,
+    Rekey,
+    Session,
+    State,
+    Encoding,
+    Crypto,
+    Other,
+    Panic,
+}
+
+/// How the reciever wants the shared key to be agreed on.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum E2EOfflineHandshakeMode {
+    Classic,
+    Hybrid,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+impl From<&Error> for E2EOfflineStatus {
+    fn from(e: &Error) -> Self {
+        match e {
+            Error::Cipher(_) => E2EOfflineStatus::Cipher,
+            Error::Contact(_) => E2EOfflineStatus::Contact,
+            Error::Hello(_) => E2EOfflineStatus::Hello,
+            Error::Hybrid(_) => E2EOfflineStatus::Hybrid,
+            Error::Identity(_) => E2EOfflineStatus::Identity,
+            Error::KeyFile(_) => E2EOfflineStatus::KeyFile,
+            Error::Pake(_) => E2EOfflineStatus::Pake,
+            Error::Passphrase(_) => E2EOfflineStatus::Passphrase,
+            Error::Prekey(_) => E2EOfflineStatus::Prekey,
+            Error::Recieve(_) => E2EOfflineStatus::Recieve,
+            Error::Rekey(_) => E2EOfflineStatus::Rekey,
+            Error::Session(_) => E2EOfflineStatus::Session,
+            Error::State(_) => E2EOfflineStatus::State,
+            Error::Encoding(_) => E2EOfflineStatus::Encoding,
+            Error::Crypto(_) => E2EOfflineStatus::Crypto,
+            _ => E2EOfflineStatus::Other,
+        }
+    }
+}
+
+fn fail(status: E2EOfflineStatus, message: &str) -> E2EOfflineStatus {
+    LAST_ERROR.with(|last| {
+        last.replace(CString::new(message.replace('\0', "")).ok());
+    });
+    status
+}
+
+fn status<T>(
+    result: e2eoffline::Result<T>,
+    ok: impl FnOnce(T) -> E2EOfflineStatus,
+) -> E2EOfflineStatus {
+    match result {
+        Ok(value) => ok(value),
+        Err(e) => fail(E2EOfflineStatus::from(&e), &e.to_string()),
+    }
+}
+
+// a panic mustn't unwind into C, so every entry point runs inside this,
+// which reports one as `Panic` (or NULL, from a constructor)
+fn catch<T>(on_panic: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("unknown panic");
+        fail(
+            E2EOfflineStatus::Panic,
+            &format!("e2eoffline panicked: {message}"),
+        );
+        on_panic
+    })
+}
+
+unsafe fn input<'a>(text: *const c_char) -> Result<&'a str, E2EOfflineStatus> {
+    if text.is_null() {
+        return Err(fail(
+            E2EOfflineStatus::NullPointer,
+            "a string argument was NULL",
+        ));
+    }
+
+    CStr::from_ptr(text).to_str().map_err(|_| {
+        fail(
+            E2EOfflineStatus::InvalidUtf8,
+            "a string argument isn't UTF-8",
+        )
+    })
+}
+
+unsafe fn output(out: *mut *mut c_char, text: String) -> E2EOfflineStatus {
+    match CString::new(text) {
+        Ok(text) => {
+            *out = text.into_raw();
+            E2EOfflineStatus::Ok
+        }
+        Err(e) => {
+            e.into_vec().zeroize();
+            fail(E2EOfflineStatus::Encoding, "the text contains a NUL byte")
+        }
+    }
+}
+
+macro_rules! non_null {
+    ($($pointer:expr),*) => {
+        if $($pointer.is_null())||* {
+            return fail(E2EOfflineStatus::NullPointer, "a pointer argument was NULL");
+        }
+    };
+}
+
+macro_rules! try_input {
+    ($text:expr) => {
+        match input($text) {
+            Ok(text) => text,
+            Err(status) => return status,
+        }
+    };
+}
+
+/// Describes the last error on this thread, or returns NULL if there hasn't
+/// been one. The string stays valid until another call on this thread fails.
+#[no_mangle]
+pub extern "C" fn e2eoffline_last_error() -> *const c_char {
+    catch(ptr::null(), || {
+        LAST_ERROR.with(|last| {
+            last.borrow()
+                .as_ref()
+                .map_or(ptr::null(), |message| message.as_ptr())
+        })
+    })
+}
+
+/// Frees a string handed out by this library. It is zeroed first, as it may
+/// be a decrypted message. NULL is ignored.
+///
+/// # Safety
+///
+/// `text` must be NULL or a string from this library that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn e2eoffline_string_free(text: *mut c_char) {
+    catch((), || {
+        if !text.is_null() {
+            CString::from_raw(text).into_bytes().zeroize();
+        }
+    })
+}
+
+/// Starts a sender handshake with a freshly generated key. Returns NULL if
+/// that fails.
+#[no_mangle]
+pub extern "C" fn e2eoffline_builder_new_sender() -> *mut E2EOfflineBuilder {
+    catch(ptr::null_mut(), || {
+        Box::into_raw(Box::new(E2EOfflineBuilder {
+            inner: e2eoffline::E2EOfflineBuilder::new_sender(),
+        }))
+    })
+}
+
+/// Starts a reciever handshake with a freshly generated key. Returns NULL if
+/// that fails.
+#[no_mangle]
+pub extern "C" fn e2eoffline_builder_new_reciever(
+    mode: E2EOfflineHandshakeMode,
+) -> *mut E2EOfflineBuilder {
+    catch(ptr::null_mut(), || {
+        let mode = match mode {
+            E2EOfflineHandshakeMode::Classic => HandshakeMode::Classic,
+            E2EOfflineHandshakeMode::Hybrid => HandshakeMode::Hybrid,
+        };
+
+        Box::into_raw(Box::new(E2EOfflineBuilder {
+            inner: e2eoffline::E2EOfflineBuilder::new_reciever_with_mode(mode),
+        }))
+    })
+}
+
+/// Frees a builder. NULL is ignored.
+///
+/// # Safety
+///
+/// `builder` must be NULL or a builder that hasn't been freed or built.
+#[no_mangle]
+pub unsafe extern "C" fn e2eoffline_builder_free(builder: *mut E2EOfflineBuilder) {
+    catch((), || {
+        if !builder.is_null() {
+            drop(Box::from_raw(builder));
+        }
+    })
+}
+
+/// Writes this side's public key to `out`. The sender gives it to the
+/// reciever.
+///
+/// # Safety
+///
+/// `builder` must be a live builder and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn e2eoffline_builder_get_pubkey(
+    builder: *const E2EOfflineBuilder,
+    out: *mut *mut c_char,
+) -> E2EOfflineStatus {
+    catch(E2EOfflineStatus::Panic, || {
+        non_null!(builder, out);
+
+        status((*builder).inner.get_pubkey_encoded(), |pubkey| {
+            output(out, pubkey)
+        })
+    })
+}
+
+/// Writes the reciever's signed hello to `out`, which the reciever gives to
+/// the sender.
+///
+/// # Safety
+///
+/// `builder` must be a live builder and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn e2eoffline_builder_get_hello(
+    builder: *const E2EOfflineBuilder,
+    out: *mut *mut c_char,
+) -> E2EOfflineStatus {
+    catch(E2EOfflineStatus::Panic, || {
+        non_null!(builder, out);
+
+        status((*builder).inner.get_hello_encoded(), |hello| {
+            output(out, hello)
+        })
+    })
+}
+
+/// Sets the other side's key: the reciever's hello for a sender, or the
+/// sender's public key for a reciever.
+///
+/// # Safety
+///
+/// `builder` must be a live builder and `pubkey` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn e2eoffline_builder_set_other_public_key(
+    builder: *mut E2EOfflineBuilder,
+    pubkey: *const c_char,
+) -> E2EOfflineStatus {
+    catch(E2EOfflineStatus::Panic, || {
+        non_null!(builder);
+        let pubkey = try_input!(pubkey);
+
+        status(
+            (*builder).inner.set_other_public_key_encoded(pubkey),
+            |_| E2EOfflineStatus::Ok,
+        )
+    })
+}
+
+/// Writes the sender's key exchange text to `out`, for the reciever's
+/// [`e2eoffline_builder_recieve`].
+///
+/// # Safety
+///
+/// `builder` must be a live builder and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn e2eoffline_builder_send(
+    builder: *mut E2EOfflineBuilder,
+    out: *mut *mut c_char,
+) -> E2EOfflineStatus {
+    catch(E2EOfflineStatus::Panic, || {
+        non_null!(builder, out);
+
+        status((*builder).inner.send(), |exchange| output(out, exchange))
+    })
+}
+
+/// Takes in the sender's key exchange text.
+///
+/// # Safety
+///
+/// `builder` must be a live builder and `exchange` a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn e2eoffline_builder_recieve(
+    builder: *mut E2EOfflineBuilder,
+    exchange: *const c_char,
+) -> E2EOfflineStatus {
+    catch(E2EOfflineStatus::Panic, || {
+        non_null!(builder);
+        let exchange = try_input!(exchange);
+
+        status((*builder).inner.recieve(exchange), |_| E2EOfflineStatus::Ok)
+    })
+}
+
+/// Finishes the handshake, writing the conversation to `out`. The builder is
+/// used up either way, so it must not be freed or used again.
+///
+/// # Safety
+///
+/// `builder` must be a live builder and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn e2eoffline_builder_build(
+    builder: *mut E2EOfflineBuilder,
+    out: *mut *mut E2EOffline,
+) -> E2EOfflineStatus {
+    catch(E2EOfflineStatus::Panic, || {
+        non_null!(builder, out);
+        let builder = Box::from_raw(builder);
+
+        status(builder.inner.build(), |inner| {
+            *out = Box::into_raw(Box::new(E2EOffline { inner }));
+            E2EOfflineStatus::Ok
+        })
+    })
+}
+
+/// Frees a conversation. NULL is ignored.
+///
+/// # Safety
+///
+/// `e2e` must be NULL or a conversation that hasn't been freed.
+#[no_mangle]
+pub unsafe extern "C" fn e2eoffline_free(e2e: *mut E2EOffline) {
+    catch((), || {
+        if !e2e.is_null() {
+            drop(Box::from_raw(e2e));
+        }
+    })
+}
+
+/// Encrypts a message, writing the ciphertext to `out`.
+///
+/// # Safety
+///
+/// `e2e` must be a live conversation, `plaintext` a NUL-terminated string and
+/// `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn e2eoffline_encrypt(
+    e2e: *mut E2EOffline,
+    plaintext: *const c_char,
+    out: *mut *mut c_char,
+) -> E2EOfflineStatus {
+    catch(E2EOfflineStatus::Panic, || {
+        non_null!(e2e, out);
+        let plaintext = try_input!(plaintext);
+
+        status((*e2e).inner.encrypt(plaintext), |ciphertext| {
+            output(out, ciphertext)
+        })
+    })
+}
+
+/// Decrypts a message, writing the plaintext to `out`.
+///
+/// # Safety
+///
+/// `e2e` must be a live conversation, `ciphertext` a NUL-terminated string
+/// and `out` writable.
+#[no_mangle]
+pub unsafe extern "C" fn e2eoffline_decrypt(
+    e2e: *mut E2EOffline,
+    ciphertext: *const c_char,
+    out: *mut *mut c_char,
+) -> E2EOfflineStatus {
+    catch(E2EOfflineStatus::Panic, || {
+        non_null!(e2e, out);
+        let ciphertext = try_input!(ciphertext);
+
+        status((*e2e).inner.decrypt(ciphertext), |plaintext| {
+            output(out, plaintext.expose_secret().to_string())
+        })
+    })
+}
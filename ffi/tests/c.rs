@@ -0,0 +1,40 @@
+use std::{path::PathBuf, process::Command};
+
+// compiles tests/c/handshake.c against the cdylib cargo built alongside this
+// test, then runs it
+#[test]
+fn c_program_runs_a_conversation() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // target/<profile>/deps/c-<hash> -> target/<profile>
+    let lib_dir = std::env::current_exe()
+        .unwrap()
+        .parent()
+        .and_then(|deps| deps.parent())
+        .unwrap()
+        .to_path_buf();
+    let program = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("handshake");
+
+    let compiled = Command::new(std::env::var("CC").unwrap_or("cc".to_string()))
+        .arg(manifest_dir.join("tests/c/handshake.c"))
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-le2eoffline_ffi")
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .expect("a C compiler is needed for this test");
+    assert!(compiled.success());
+
+    let output = Command::new(&program).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+}
@@ -0,0 +1,98 @@
+// A whole conversation through the C API, in both handshake modes, plus the
+// errors a C caller is most likely to run into. Exits non-zero on failure.
+
+#include <stdio.h>
+#include <string.h>
+
+#include "e2eoffline.h"
+
+#define CHECK(call)                                                          \
+  do {                                                                       \
+    E2EOfflineStatus status = (call);                                        \
+    if (status != E2E_OFFLINE_STATUS_OK) {                                   \
+      fprintf(stderr, "%s:%d: %s failed with %d: %s\n", __FILE__, __LINE__, \
+              #call, status, e2eoffline_last_error());                       \
+      return 1;                                                              \
+    }                                                                        \
+  } while (0)
+
+#define EXPECT(condition)                                                    \
+  do {                                                                       \
+    if (!(condition)) {                                                      \
+      fprintf(stderr, "%s:%d: expected %s\n", __FILE__, __LINE__,            \
+              #condition);                                                   \
+      return 1;                                                              \
+    }                                                                        \
+  } while (0)
+
+static int conversation(E2EOfflineHandshakeMode mode) {
+  E2EOfflineBuilder *sender = e2eoffline_builder_new_sender();
+  E2EOfflineBuilder *reciever = e2eoffline_builder_new_reciever(mode);
+  char *hello, *pubkey, *exchange, *ciphertext, *plaintext;
+  E2EOffline *sender_e2e, *reciever_e2e;
+
+  CHECK(e2eoffline_builder_get_hello(reciever, &hello));
+  CHECK(e2eoffline_builder_set_other_public_key(sender, hello));
+  CHECK(e2eoffline_builder_get_pubkey(sender, &pubkey));
+  CHECK(e2eoffline_builder_set_other_public_key(reciever, pubkey));
+
+  CHECK(e2eoffline_builder_send(sender, &exchange));
+  CHECK(e2eoffline_builder_recieve(reciever, exchange));
+
+  CHECK(e2eoffline_builder_build(sender, &sender_e2e));
+  CHECK(e2eoffline_builder_build(reciever, &reciever_e2e));
+
+  CHECK(e2eoffline_encrypt(sender_e2e, "attack at dawn", &ciphertext));
+  CHECK(e2eoffline_decrypt(reciever_e2e, ciphertext, &plaintext));
+  EXPECT(strcmp(plaintext, "attack at dawn") == 0);
+  e2eoffline_string_free(plaintext);
+  e2eoffline_string_free(ciphertext);
+
+  CHECK(e2eoffline_encrypt(reciever_e2e, "and back", &ciphertext));
+  CHECK(e2eoffline_decrypt(sender_e2e, ciphertext, &plaintext));
+  EXPECT(strcmp(plaintext, "and back") == 0);
+  e2eoffline_string_free(plaintext);
+
+  // a tampered ciphertext is refused, and the error says why
+  ciphertext[strlen(ciphertext) - 2] ^= 1;
+  EXPECT(e2eoffline_decrypt(sender_e2e, ciphertext, &plaintext) != E2E_OFFLINE_STATUS_OK);
+  EXPECT(e2eoffline_last_error() != NULL);
+  e2eoffline_string_free(ciphertext);
+
+  e2eoffline_string_free(hello);
+  e2eoffline_string_free(pubkey);
+  e2eoffline_string_free(exchange);
+  e2eoffline_free(sender_e2e);
+  e2eoffline_free(reciever_e2e);
+
+  return 0;
+}
+
+static int errors(void) {
+  E2EOfflineBuilder *sender = e2eoffline_builder_new_sender();
+  char *out = NULL;
+
+  EXPECT(e2eoffline_builder_set_other_public_key(sender, "not a hello") == E2E_OFFLINE_STATUS_HELLO);
+  EXPECT(strlen(e2eoffline_last_error()) > 0);
+  EXPECT(e2eoffline_builder_set_other_public_key(sender, NULL) == E2E_OFFLINE_STATUS_NULL_POINTER);
+  EXPECT(e2eoffline_builder_set_other_public_key(sender, "\xff") == E2E_OFFLINE_STATUS_INVALID_UTF8);
+  EXPECT(e2eoffline_builder_get_hello(sender, &out) == E2E_OFFLINE_STATUS_HELLO);
+  EXPECT(e2eoffline_builder_send(sender, &out) == E2E_OFFLINE_STATUS_IDENTITY);
+  EXPECT(out == NULL);
+
+  e2eoffline_builder_free(sender);
+  e2eoffline_builder_free(NULL);
+  e2eoffline_string_free(NULL);
+
+  return 0;
+}
+
+int main(void) {
+  if (conversation(E2E_OFFLINE_HANDSHAKE_MODE_CLASSIC) ||
+      conversation(E2E_OFFLINE_HANDSHAKE_MODE_HYBRID) || errors()) {
+    return 1;
+  }
+
+  puts("ok");
+  return 0;
+}
@@ -0,0 +1,14 @@
+use std::{fs, path::PathBuf};
+
+// build.rs regenerates the header on every build, but only into OUT_DIR
+#[test]
+fn checked_in_header_is_up_to_date() {
+    let generated = env!("E2EOFFLINE_GENERATED_HEADER");
+    let checked_in = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("include/e2eoffline.h");
+
+    assert!(
+        fs::read_to_string(generated).unwrap() == fs::read_to_string(&checked_in).unwrap(),
+        "include/e2eoffline.h is out of date, update it with `cp {generated} {}`",
+        checked_in.display()
+    );
+}
@@ -0,0 +1,426 @@
+//! What each fuzz target runs, one function per parser plus [`handshake`],
+//! which fuzzes whole conversations. They're kept out of `fuzz_targets/` so
+//! that `cargo test` can replay the checked-in corpus through them too (see
+//! `tests/fuzz_corpus.rs`).
+//!
+//! None of them should ever panic, whatever the input.
+
+use std::sync::{Mutex, OnceLock};
+
+use arbitrary::{Arbitrary, Unstructured};
+use e2eoffline::{
+    CipherSuite, ContactBook, E2EOffline, E2EOfflineBuilder, E2EOfflinePakeBuilder, HandshakeMode,
+    Identity, SessionExport,
+};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+const KEY: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+const PASSPHRASE: &str = "fuzz";
+const PAKE_CODE: &str = "123-456";
+
+// RSA keygen is far too slow to do for every input
+fn reciever_identity() -> &'static Identity {
+    static IDENTITY: OnceLock<Identity> = OnceLock::new();
+    IDENTITY.get_or_init(|| Identity::generate_with_rng(&mut ChaCha20Rng::seed_from_u64(1)))
+}
+
+fn sender_identity() -> &'static Identity {
+    static IDENTITY: OnceLock<Identity> = OnceLock::new();
+    IDENTITY.get_or_init(|| Identity::generate_with_rng(&mut ChaCha20Rng::seed_from_u64(2)))
+}
+
+fn rng(seed: u64) -> ChaCha20Rng {
+    ChaCha20Rng::seed_from_u64(seed)
+}
+
+fn new_reciever(mode: HandshakeMode) -> E2EOfflineBuilder<ChaCha20Rng> {
+    E2EOfflineBuilder::new_reciever_with_rng(reciever_identity(), mode, rng(3))
+}
+
+fn new_sender() -> E2EOfflineBuilder<ChaCha20Rng> {
+    E2EOfflineBuilder::new_sender_with_rng(sender_identity(), rng(4))
+}
+
+/// A reciever's hello, as given to a sender.
+pub fn hello(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let mut sender = new_sender();
+    if sender.set_other_public_key_encoded(text).is_ok() {
+        let _ = sender.send();
+    }
+}
+
+/// A sender's public key, in any of the encodings a reciever accepts.
+pub fn pubkey(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let _ = new_reciever(HandshakeMode::Classic).set_other_public_key_encoded(text);
+}
+
+pub fn prekey_bundle(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let mut sender = new_sender();
+    if sender.set_prekey_bundle_encoded(text).is_ok() {
+        let _ = sender.send();
+    }
+}
+
+/// A sender's key exchange text, given to recievers in each mode, and to one
+/// that hasn't been given the sender's public key yet.
+pub fn recieve(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let sender_pubkey = sender_identity().get_pubkey_encoded().unwrap();
+
+    let mut classic = new_reciever(HandshakeMode::Classic);
+    let mut hybrid = new_reciever(HandshakeMode::Hybrid);
+    let mut prekey = new_reciever(HandshakeMode::Classic);
+    prekey.get_prekey_bundle_encoded(2).unwrap();
+
+    for reciever in [&mut classic, &mut hybrid, &mut prekey] {
+        reciever
+            .set_other_public_key_encoded(&sender_pubkey)
+            .unwrap();
+    }
+
+    for mut reciever in [
+        classic,
+        hybrid,
+        prekey,
+        new_reciever(HandshakeMode::Classic),
+    ] {
+        if reciever.recieve(text).is_ok() {
+            let _ = reciever.build();
+        }
+    }
+}
+
+pub fn key_base64(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    if let Ok(mut e2e) = E2EOffline::from_key_base64_with_rng(text, rng(5)) {
+        let _ = e2e.encrypt("fuzz");
+    }
+}
+
+/// A ciphertext, given to a raw key session and to a passphrase session.
+pub fn decrypt(data: &[u8]) {
+    // Argon2id is too slow to derive the passphrase key for every input
+    static PASSPHRASE_SESSION: OnceLock<Mutex<E2EOffline<ChaCha20Rng>>> = OnceLock::new();
+
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let _ = E2EOffline::from_key_base64_with_rng(KEY, rng(5))
+        .unwrap()
+        .decrypt(text);
+
+    let _ = PASSPHRASE_SESSION
+        .get_or_init(|| {
+            Mutex::new(E2EOffline::from_passphrase_with_rng(PASSPHRASE, rng(6)).unwrap())
+        })
+        .lock()
+        .unwrap()
+        .decrypt(text);
+}
+
+/// A rekey message, given to a session with no identity key and to one from a
+/// handshake, which checks the signature.
+pub fn rekey(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let _ = E2EOffline::from_key_base64_with_rng(KEY, rng(5))
+        .unwrap()
+        .apply_rekey(text);
+
+    let (_, mut reciever) = run_handshake(HandshakeMode::Classic);
+    let _ = reciever.apply_rekey(text);
+}
+
+/// A saved handshake. The state is encrypted, so this mostly fuzzes the outer
+/// layer; the corpus holds states saved with [`PASSPHRASE`].
+pub fn state(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    if let Ok(state) = SessionExport::from_encoded(text) {
+        if let Ok(mut builder) = E2EOfflineBuilder::import_state(&state, PASSPHRASE) {
+            let _ = builder.get_hello_encoded();
+            let _ = builder.send();
+        }
+    }
+}
+
+/// A session export, which like [`state`] is encrypted with [`PASSPHRASE`].
+pub fn session(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    if let Ok(export) = SessionExport::from_encoded(text) {
+        if let Ok(mut e2e) = E2EOffline::import_session(&export, PASSPHRASE) {
+            let _ = e2e.encrypt("fuzz");
+        }
+    }
+}
+
+pub fn contacts(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    if let Ok(contacts) = ContactBook::from_encoded(text) {
+        let _ = ContactBook::from_encoded(&contacts.to_encoded());
+    }
+}
+
+pub fn revocation(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let _ = new_sender().add_revocation_certificate_encoded(text);
+    let _ = ContactBook::new().add_revocation_certificate_encoded(text);
+}
+
+/// A private key file, in any of the formats [`Identity`] loads.
+pub fn private_key(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let _ = Identity::from_private_key_pem(text, PASSPHRASE);
+}
+
+/// A PAKE message, given to both sides.
+pub fn pake(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let mut reciever = E2EOfflinePakeBuilder::new_reciever(PAKE_CODE);
+    if reciever.recieve(text).is_ok() && reciever.send().is_ok() {
+        // the same text again, as the sender's confirmation
+        let _ = reciever.recieve(text);
+        let _ = reciever.build();
+    }
+
+    let mut sender = E2EOfflinePakeBuilder::new_sender(PAKE_CODE);
+    let _ = sender.send();
+    if sender.recieve(text).is_ok() {
+        let _ = sender.build();
+    }
+}
+
+fn run_handshake(mode: HandshakeMode) -> (E2EOffline<ChaCha20Rng>, E2EOffline<ChaCha20Rng>) {
+    let mut reciever = new_reciever(mode);
+    let mut sender = new_sender();
+
+    sender
+        .set_other_public_key_encoded(&reciever.get_hello_encoded().unwrap())
+        .unwrap();
+    reciever
+        .set_other_public_key_encoded(&sender.get_pubkey_encoded().unwrap())
+        .unwrap();
+    reciever.recieve(&sender.send().unwrap()).unwrap();
+
+    (sender.build().unwrap(), reciever.build().unwrap())
+}
+
+#[derive(Arbitrary, Debug)]
+enum Mode {
+    Classic,
+    Hybrid,
+    Prekey { one_time_prekeys: u8 },
+}
+
+#[derive(Arbitrary, Debug)]
+enum Suite {
+    Aes256Gcm,
+    XChaCha20Poly1305,
+    Aes256GcmSiv,
+}
+
+/// What happens to a piece of text on its way to the other side.
+#[derive(Arbitrary, Debug)]
+enum Tamper {
+    Deliver,
+    Drop,
+    Truncate(u16),
+    Replace(u16, char),
+    Insert(u16, char),
+    Swap(String),
+}
+
+impl Tamper {
+    fn apply(&self, text: &str) -> Option<String> {
+        // positions wrap around, landing on a character boundary
+        let at = |i: u16| {
+            text.char_indices()
+                .map(|(i, _)| i)
+                .chain([text.len()])
+                .nth(i as usize % (text.chars().count() + 1))
+                .unwrap()
+        };
+
+        match self {
+            Tamper::Deliver => Some(text.to_string()),
+            Tamper::Drop => None,
+            Tamper::Truncate(i) => Some(text[..at(*i)].to_string()),
+            Tamper::Replace(i, c) => {
+                let i = at(*i);
+                let rest = text[i..].chars().skip(1).collect::<String>();
+                Some(format!("{}{c}{rest}", &text[..i]))
+            }
+            Tamper::Insert(i, c) => {
+                let i = at(*i);
+                Some(format!("{}{c}{}", &text[..i], &text[i..]))
+            }
+            Tamper::Swap(other) => Some(other.clone()),
+        }
+    }
+}
+
+#[derive(Arbitrary, Debug)]
+struct Message {
+    from_sender: bool,
+    plaintext: String,
+    tamper: Tamper,
+}
+
+#[derive(Arbitrary, Debug)]
+struct Conversation {
+    seed: u64,
+    mode: Mode,
+    suite: Suite,
+    hello: Tamper,
+    sender_pubkey: Tamper,
+    exchange: Tamper,
+    messages: Vec<Message>,
+    rekey: Option<(bool, Tamper)>,
+}
+
+/// A whole conversation, where anything sent between the two sides may be
+/// dropped or tampered with. When nothing is, every message has to arrive
+/// intact.
+pub fn handshake(data: &[u8]) {
+    let Ok(conversation) = Conversation::arbitrary_take_rest(Unstructured::new(data)) else {
+        return;
+    };
+
+    let mode = match conversation.mode {
+        Mode::Hybrid => HandshakeMode::Hybrid,
+        _ => HandshakeMode::Classic,
+    };
+
+    let mut reciever =
+        E2EOfflineBuilder::new_reciever_with_rng(reciever_identity(), mode, rng(conversation.seed));
+    let mut sender = E2EOfflineBuilder::new_sender_with_rng(
+        sender_identity(),
+        rng(conversation.seed.wrapping_add(1)),
+    );
+
+    // false once anything has gone missing or been changed
+    let mut intact = true;
+
+    match conversation.mode {
+        Mode::Prekey { one_time_prekeys } => {
+            let bundle = reciever
+                .get_prekey_bundle_encoded(one_time_prekeys as usize % 4)
+                .unwrap();
+
+            if let Some(bundle) = deliver(&bundle, &conversation.hello, &mut intact) {
+                let _ = sender.set_prekey_bundle_encoded(&bundle);
+            }
+        }
+        _ => {
+            let hello = reciever.get_hello_encoded().unwrap();
+
+            if let Some(hello) = deliver(&hello, &conversation.hello, &mut intact) {
+                let _ = sender.set_other_public_key_encoded(&hello);
+            }
+        }
+    }
+
+    let sender_pubkey = sender.get_pubkey_encoded().unwrap();
+    if let Some(sender_pubkey) = deliver(&sender_pubkey, &conversation.sender_pubkey, &mut intact) {
+        let _ = reciever.set_other_public_key_encoded(&sender_pubkey);
+    }
+
+    sender.set_cipher_suite(match conversation.suite {
+        Suite::Aes256Gcm => CipherSuite::Aes256Gcm,
+        Suite::XChaCha20Poly1305 => CipherSuite::XChaCha20Poly1305,
+        Suite::Aes256GcmSiv => CipherSuite::Aes256GcmSiv,
+    });
+
+    let exchange = sender.send();
+    assert!(exchange.is_ok() || !intact);
+    let Ok(exchange) = exchange else {
+        return;
+    };
+
+    if let Some(exchange) = deliver(&exchange, &conversation.exchange, &mut intact) {
+        let recieved = reciever.recieve(&exchange);
+        assert!(recieved.is_ok() || !intact);
+    }
+
+    let (Ok(mut sender), Ok(mut reciever)) = (sender.build(), reciever.build()) else {
+        assert!(!intact);
+        return;
+    };
+
+    for message in &conversation.messages {
+        let (from, to) = match message.from_sender {
+            true => (&mut sender, &mut reciever),
+            false => (&mut reciever, &mut sender),
+        };
+
+        let ciphertext = from.encrypt(&message.plaintext).unwrap();
+
+        let mut message_intact = intact;
+        if let Some(delivered) = deliver(&ciphertext, &message.tamper, &mut message_intact) {
+            let decrypted = to.decrypt(&delivered);
+
+            if message_intact {
+                assert_eq!(decrypted.unwrap().expose_secret(), message.plaintext);
+            }
+        }
+    }
+
+    if let Some((from_sender, tamper)) = &conversation.rekey {
+        let (from, to) = match from_sender {
+            true => (&mut sender, &mut reciever),
+            false => (&mut reciever, &mut sender),
+        };
+
+        let rekey = from.rekey().unwrap();
+        if let Some(delivered) = deliver(&rekey, tamper, &mut intact) {
+            let applied = to.apply_rekey(&delivered);
+            assert!(applied.is_ok() || !intact);
+        }
+    }
+}
+
+// passes text on as the tamper says, noting whether it arrived unchanged
+fn deliver(text: &str, tamper: &Tamper, intact: &mut bool) -> Option<String> {
+    let delivered = tamper.apply(text);
+    *intact &= delivered.as_deref() == Some(text);
+
+    delivered
+}
@@ -0,0 +1,285 @@
+//! A JavaScript API for e2eoffline, for web pages and Node tools that want
+//! the crypto without the webapp. Build it with `wasm-pack` (see
+//! `package.json`).
+//!
+//! Errors are thrown as `Error`s whose `name` says which part of the library
+//! they came from, such as `"HelloError"`; see `E2EOfflineErrorName` in the
+//! TypeScript definitions.
+
+use e2eoffline::{Error, HandshakeMode};
+use js_sys::{Function, Promise};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+#[wasm_bindgen(typescript_custom_section)]
+const ERROR_TYPES: &'static str = r#"
+/** The `name` of every error thrown by this package. */
+export type E2EOfflineErrorName =
+  | "E2EOfflineError"
+  | "CipherError"
+  | "ContactError"
+  | "HelloError"
+  | "HybridError"
+  | "IdentityError"
+  | "KeyFileError"
+  | "PakeError"
+  | "PassphraseError"
+  | "PrekeyError"
+  | "RecieveError"
+  | "RekeyError"
+  | "SessionError"
+  | "StateError"
+  | "EncodingError"
+  | "CryptoError";
+
+/** An error thrown by this package. */
+export interface E2EOfflineError extends Error {
+  name: E2EOfflineErrorName;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    // both browsers and Node have a global setTimeout
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(callback: &Function, milliseconds: i32);
+}
+
+// gives the event loop a turn before a long computation, so a page can show
+// that something is happening before it blocks. The computation itself still
+// runs on the calling thread; nothing here moves it to a worker
+async fn yield_to_event_loop() {
+    let promise = Promise::new(&mut |resolve, _| set_timeout(&resolve, 0));
+    JsFuture::from(promise).await.unwrap_throw();
+}
+
+pub enum BindingError {
+    Library(Error),
+    AlreadyBuilt,
+}
+
+impl From<Error> for BindingError {
+    fn from(e: Error) -> Self {
+        BindingError::Library(e)
+    }
+}
+
+impl From<BindingError> for JsValue {
+    fn from(e: BindingError) -> Self {
+        let e = match e {
+            BindingError::Library(e) => e,
+            BindingError::AlreadyBuilt => {
+                return js_error("E2EOfflineError", "the handshake has already been built")
+            }
+        };
+
+        let name = match e {
+            Error::Cipher(_) => "CipherError",
+            Error::Contact(_) => "ContactError",
+            Error::Hello(_) => "HelloError",
+            Error::Hybrid(_) => "HybridError",
+            Error::Identity(_) => "IdentityError",
+            Error::KeyFile(_) => "KeyFileError",
+            Error::Pake(_) => "PakeError",
+            Error::Passphrase(_) => "PassphraseError",
+            Error::Prekey(_) => "PrekeyError",
+            Error::Recieve(_) => "RecieveError",
+            Error::Rekey(_) => "RekeyError",
+            Error::Session(_) => "SessionError",
+            Error::State(_) => "StateError",
+            Error::Encoding(_) => "EncodingError",
+            Error::Crypto(_) => "CryptoError",
+            _ => "E2EOfflineError",
+        };
+
+        js_error(name, &e.to_string())
+    }
+}
+
+fn js_error(name: &str, message: &str) -> JsValue {
+    let error = js_sys::Error::new(message);
+    error.set_name(name);
+    error.into()
+}
+
+type Result<T> = std::result::Result<T, BindingError>;
+
+/// A handshake in progress. `build()` turns it into an `E2EOffline`, after
+/// which it can't be used again.
+#[wasm_bindgen(js_name = E2EOfflineBuilder)]
+pub struct Builder {
+    inner: Option<e2eoffline::E2EOfflineBuilder>,
+}
+
+impl Builder {
+    fn inner(&self) -> Result<&e2eoffline::E2EOfflineBuilder> {
+        self.inner.as_ref().ok_or(BindingError::AlreadyBuilt)
+    }
+
+    fn inner_mut(&mut self) -> Result<&mut e2eoffline::E2EOfflineBuilder> {
+        self.inner.as_mut().ok_or(BindingError::AlreadyBuilt)
+    }
+}
+
+#[wasm_bindgen(js_class = E2EOfflineBuilder)]
+impl Builder {
+    /// Starts a sender handshake with a freshly generated key.
+    ///
+    /// The promise only lets the page redraw before key generation starts.
+    /// Generating the key still blocks the calling thread for several
+    /// seconds, so call this from a Web Worker to keep a page responsive.
+    #[wasm_bindgen(js_name = newSender)]
+    pub async fn new_sender() -> Builder {
+        yield_to_event_loop().await;
+
+        Builder {
+            inner: Some(e2eoffline::E2EOfflineBuilder::new_sender()),
+        }
+    }
+
+    /// Starts a reciever handshake with a freshly generated key. A hybrid
+    /// handshake also uses ML-KEM-768, against future quantum computers.
+    ///
+    /// Like `newSender()`, this blocks the calling thread while the key is
+    /// generated, so call it from a Web Worker to keep a page responsive.
+    #[wasm_bindgen(js_name = newReciever)]
+    pub async fn new_reciever(hybrid: Option<bool>) -> Builder {
+        let mode = match hybrid.unwrap_or_default() {
+            false => HandshakeMode::Classic,
+            true => HandshakeMode::Hybrid,
+        };
+
+        yield_to_event_loop().await;
+
+        Builder {
+            inner: Some(e2eoffline::E2EOfflineBuilder::new_reciever_with_mode(mode)),
+        }
+    }
+
+    /// This side's public key, which the sender gives to the reciever.
+    #[wasm_bindgen(js_name = getPubkey)]
+    pub fn get_pubkey(&self) -> Result<String> {
+        Ok(self.inner()?.get_pubkey_encoded()?)
+    }
+
+    /// The reciever's signed hello, which the reciever gives to the sender.
+    #[wasm_bindgen(js_name = getHello)]
+    pub fn get_hello(&self) -> Result<String> {
+        Ok(self.inner()?.get_hello_encoded()?)
+    }
+
+    #[wasm_bindgen(js_name = getFingerprint)]
+    pub fn get_fingerprint(&self) -> Result<String> {
+        Ok(self.inner()?.get_fingerprint()?)
+    }
+
+    #[wasm_bindgen(js_name = getOtherFingerprint)]
+    pub fn get_other_fingerprint(&self) -> Result<String> {
+        Ok(self.inner()?.get_other_fingerprint()?)
+    }
+
+    /// Sets the other side's key: the reciever's hello for a sender, or the
+    /// sender's public key for a reciever.
+    #[wasm_bindgen(js_name = setOtherPublicKey)]
+    pub fn set_other_public_key(&mut self, pubkey: &str) -> Result<()> {
+        Ok(self.inner_mut()?.set_other_public_key_encoded(pubkey)?)
+    }
+
+    /// Picks the cipher by name, such as `"xchacha20poly1305"`. Only the
+    /// sender picks.
+    #[wasm_bindgen(js_name = setCipherSuite)]
+    pub fn set_cipher_suite(&mut self, cipher_suite: &str) -> Result<()> {
+        let cipher_suite = cipher_suite.parse()?;
+        self.inner_mut()?.set_cipher_suite(cipher_suite);
+        Ok(())
+    }
+
+    /// The sender's key exchange text, for the reciever's `recieve()`.
+    pub fn send(&mut self) -> Result<String> {
+        Ok(self.inner_mut()?.send()?)
+    }
+
+    pub fn recieve(&mut self, exchange: &str) -> Result<()> {
+        Ok(self.inner_mut()?.recieve(exchange)?)
+    }
+
+    pub fn build(&mut self) -> Result<Session> {
+        let inner = self.inner.take().ok_or(BindingError::AlreadyBuilt)?;
+
+        Ok(Session {
+            inner: inner.build()?,
+        })
+    }
+}
+
+/// A conversation under a shared key.
+#[wasm_bindgen(js_name = E2EOffline)]
+pub struct Session {
+    inner: e2eoffline::E2EOffline,
+}
+
+#[wasm_bindgen(js_class = E2EOffline)]
+impl Session {
+    /// Derives the key from a passphrase both users already share, with no
+    /// handshake at all.
+    ///
+    /// Stretching the passphrase blocks the calling thread for about a
+    /// second, like `E2EOfflineBuilder.newSender()`, so call this from a Web
+    /// Worker to keep a page responsive.
+    #[wasm_bindgen(js_name = fromPassphrase)]
+    pub async fn from_passphrase(passphrase: String) -> Result<Session> {
+        yield_to_event_loop().await;
+
+        Ok(Session {
+            inner: e2eoffline::E2EOffline::from_passphrase(&passphrase)?,
+        })
+    }
+
+    /// Starts a conversation from a raw key, such as one from
+    /// `dangerouslyExportRawKey()`.
+    #[wasm_bindgen(js_name = fromKeyBase64)]
+    pub fn from_key_base64(key: &str) -> Result<Session> {
+        Ok(Session {
+            inner: e2eoffline::E2EOffline::from_key_base64(key)?,
+        })
+    }
+
+    #[wasm_bindgen(js_name = isRekeyMessage)]
+    pub fn is_rekey_message(message: &str) -> bool {
+        e2eoffline::E2EOffline::is_rekey_message(message)
+    }
+
+    #[wasm_bindgen(getter, js_name = cipherSuite)]
+    pub fn cipher_suite(&self) -> String {
+        self.inner.cipher_suite().to_string()
+    }
+
+    pub fn encrypt(&mut self, plaintext: &str) -> Result<String> {
+        Ok(self.inner.encrypt(plaintext)?)
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &str) -> Result<String> {
+        Ok(self.inner.decrypt(ciphertext)?.expose_secret().to_string())
+    }
+
+    /// Switches to a fresh key, returning the message that tells the other
+    /// side about it.
+    pub fn rekey(&mut self) -> Result<String> {
+        Ok(self.inner.rekey()?)
+    }
+
+    #[wasm_bindgen(js_name = applyRekey)]
+    pub fn apply_rekey(&mut self, message: &str) -> Result<()> {
+        Ok(self.inner.apply_rekey(message)?)
+    }
+
+    /// Anyone who has this key can read and forge messages until the next
+    /// rekey.
+    #[wasm_bindgen(js_name = dangerouslyExportRawKey)]
+    pub fn dangerously_export_raw_key(&self) -> String {
+        self.inner
+            .dangerously_export_raw_key()
+            .expose_secret()
+            .to_string()
+    }
+}
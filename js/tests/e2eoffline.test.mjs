@@ -0,0 +1,118 @@
+import assert from "node:assert/strict";
+import { createRequire } from "node:module";
+import { describe, test } from "node:test";
+
+const require = createRequire(import.meta.url);
+const { E2EOffline, E2EOfflineBuilder } = require("../pkg/e2eoffline_js.js");
+
+async function handshake({ hybrid = false, cipherSuite } = {}) {
+  const sender = await E2EOfflineBuilder.newSender();
+  const reciever = await E2EOfflineBuilder.newReciever(hybrid);
+
+  sender.setOtherPublicKey(reciever.getHello());
+  reciever.setOtherPublicKey(sender.getPubkey());
+  if (cipherSuite !== undefined) {
+    sender.setCipherSuite(cipherSuite);
+  }
+
+  reciever.recieve(sender.send());
+
+  return [sender.build(), reciever.build()];
+}
+
+function named(name, message) {
+  return (e) => {
+    assert.ok(e instanceof Error);
+    assert.equal(e.name, name);
+    if (message !== undefined) {
+      assert.match(e.message, message);
+    }
+    return true;
+  };
+}
+
+describe("handshake", () => {
+  for (const hybrid of [false, true]) {
+    test(`round trip, hybrid: ${hybrid}`, async () => {
+      const [sender, reciever] = await handshake({ hybrid });
+
+      assert.equal(reciever.decrypt(sender.encrypt("attack at dawn")), "attack at dawn");
+      assert.equal(sender.decrypt(reciever.encrypt("and back")), "and back");
+    });
+  }
+
+  for (const suite of ["aes256gcm", "xchacha20poly1305", "aes256gcmsiv"]) {
+    test(`cipher suite ${suite}`, async () => {
+      const [sender, reciever] = await handshake({ cipherSuite: suite });
+
+      assert.equal(sender.cipherSuite, suite);
+      assert.equal(reciever.cipherSuite, suite);
+      assert.equal(reciever.decrypt(sender.encrypt("hello")), "hello");
+    });
+  }
+
+  test("key generation returns a promise", () => {
+    const pending = E2EOfflineBuilder.newSender();
+
+    assert.ok(pending instanceof Promise);
+    return pending;
+  });
+
+  test("the builder is used up by build", async () => {
+    const sender = await E2EOfflineBuilder.newSender();
+    const reciever = await E2EOfflineBuilder.newReciever();
+    sender.setOtherPublicKey(reciever.getHello());
+    sender.send();
+    sender.build();
+
+    assert.throws(() => sender.build(), named("E2EOfflineError", /already been built/));
+    assert.throws(() => sender.getPubkey(), named("E2EOfflineError"));
+  });
+});
+
+describe("conversation", () => {
+  test("rekey", async () => {
+    const [sender, reciever] = await handshake();
+
+    const message = sender.rekey();
+    assert.ok(E2EOffline.isRekeyMessage(message));
+    reciever.applyRekey(message);
+
+    assert.equal(reciever.decrypt(sender.encrypt("after the rekey")), "after the rekey");
+  });
+
+  test("a tampered ciphertext is refused", async () => {
+    const [sender, reciever] = await handshake();
+    const ciphertext = sender.encrypt("hello");
+    const swapped = ciphertext.at(-2) === "A" ? "B" : "A";
+    const tampered = ciphertext.slice(0, -2) + swapped + ciphertext.at(-1);
+
+    assert.throws(() => reciever.decrypt(tampered), Error);
+  });
+
+  test("passphrase", async () => {
+    const first = await E2EOffline.fromPassphrase("correct horse battery staple");
+    const second = await E2EOffline.fromPassphrase("correct horse battery staple");
+
+    assert.equal(second.decrypt(first.encrypt("hello")), "hello");
+  });
+
+  test("raw key", async () => {
+    const [sender] = await handshake();
+    const copy = E2EOffline.fromKeyBase64(sender.dangerouslyExportRawKey());
+
+    assert.equal(copy.decrypt(sender.encrypt("hello")), "hello");
+  });
+});
+
+describe("errors", () => {
+  test("are named after the part of the library they came from", async () => {
+    const sender = await E2EOfflineBuilder.newSender();
+
+    assert.throws(() => sender.setOtherPublicKey("not a hello"), named("HelloError", /reciever hello/));
+    assert.throws(() => sender.getHello(), named("HelloError"));
+    assert.throws(() => sender.send(), named("IdentityError"));
+    assert.throws(() => sender.setCipherSuite("rot13"), named("CipherError"));
+    assert.throws(() => E2EOffline.fromKeyBase64("not base64!"), named("EncodingError"));
+  });
+});
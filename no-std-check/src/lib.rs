@@ -0,0 +1,64 @@
+//! Uses e2eoffline without std, the way firmware would. This crate is only
+//! ever built: building it for a bare-metal target such as
+//! `thumbv7em-none-eabihf` fails if the library or any of its dependencies
+//! needs std.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::string::String;
+
+use e2eoffline::{E2EOfflineBuilder, E2EOfflinePakeBuilder, HandshakeMode, Identity, Result};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+/// A whole handshake and one message, with every RNG seeded from `seed`, as a
+/// device without an RNG of its own would get them from its hardware.
+pub fn handshake(seed: u64, mode: HandshakeMode, message: &str) -> Result<String> {
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+
+    let reciever_identity = Identity::generate_with_rng(&mut rng);
+    let sender_identity = Identity::generate_with_rng(&mut rng);
+
+    let mut reciever = E2EOfflineBuilder::new_reciever_with_rng(
+        &reciever_identity,
+        mode,
+        ChaCha20Rng::seed_from_u64(seed + 1),
+    );
+    let mut sender = E2EOfflineBuilder::new_sender_with_rng(
+        &sender_identity,
+        ChaCha20Rng::seed_from_u64(seed + 2),
+    );
+
+    sender.set_other_public_key_encoded(&reciever.get_hello_encoded()?)?;
+    reciever.set_other_public_key_encoded(&sender.get_pubkey_encoded()?)?;
+    reciever.recieve(&sender.send()?)?;
+
+    let mut sender = sender.build()?;
+    let mut reciever = reciever.build()?;
+
+    let ciphertext = sender.encrypt(message)?;
+    reciever.apply_rekey(&sender.rekey()?)?;
+
+    Ok(String::from(reciever.decrypt(&ciphertext)?.expose_secret()))
+}
+
+/// Code pairing, which needs no RSA keys at all.
+pub fn pake(seed: u64, message: &str) -> Result<String> {
+    let code = E2EOfflinePakeBuilder::generate_code_with_rng(&mut ChaCha20Rng::seed_from_u64(seed));
+
+    let mut sender =
+        E2EOfflinePakeBuilder::new_sender_with_rng(&code, ChaCha20Rng::seed_from_u64(seed + 1));
+    let mut reciever =
+        E2EOfflinePakeBuilder::new_reciever_with_rng(&code, ChaCha20Rng::seed_from_u64(seed + 2));
+
+    reciever.recieve(&sender.send()?)?;
+    sender.recieve(&reciever.send()?)?;
+    reciever.recieve(&sender.send()?)?;
+
+    let ciphertext = sender.build()?.encrypt(message)?;
+
+    Ok(String::from(
+        reciever.build()?.decrypt(&ciphertext)?.expose_secret(),
+    ))
+}
@@ -0,0 +1,268 @@
+//! Python bindings for e2eoffline, built into a wheel with maturin (see
+//! `pyproject.toml`). Every error the library returns is raised as the
+//! subclass of `E2EOfflineError` named after the part of the library it came
+//! from.
+
+use e2eoffline::{Error, HandshakeMode};
+use pyo3::{create_exception, exceptions::PyException, prelude::*};
+
+create_exception!(
+    e2eoffline,
+    E2EOfflineError,
+    PyException,
+    "Base class of everything raised by e2eoffline."
+);
+create_exception!(e2eoffline, CipherError, E2EOfflineError);
+create_exception!(e2eoffline, ContactError, E2EOfflineError);
+create_exception!(e2eoffline, HelloError, E2EOfflineError);
+create_exception!(e2eoffline, HybridError, E2EOfflineError);
+create_exception!(e2eoffline, IdentityError, E2EOfflineError);
+create_exception!(e2eoffline, KeyFileError, E2EOfflineError);
+create_exception!(e2eoffline, PakeError, E2EOfflineError);
+create_exception!(e2eoffline, PassphraseError, E2EOfflineError);
+create_exception!(e2eoffline, PrekeyError, E2EOfflineError);
+create_exception!(e2eoffline, RecieveError, E2EOfflineError);
+create_exception!(e2eoffline, RekeyError, E2EOfflineError);
+create_exception!(e2eoffline, SessionError, E2EOfflineError);
+create_exception!(e2eoffline, StateError, E2EOfflineError);
+create_exception!(e2eoffline, EncodingError, E2EOfflineError);
+create_exception!(e2eoffline, CryptoError, E2EOfflineError);
+
+enum BindingError {
+    Library(Error),
+    AlreadyBuilt,
+}
+
+impl From<Error> for BindingError {
+    fn from(e: Error) -> Self {
+        BindingError::Library(e)
+    }
+}
+
+impl From<BindingError> for PyErr {
+    fn from(e: BindingError) -> Self {
+        let e = match e {
+            BindingError::Library(e) => e,
+            BindingError::AlreadyBuilt => {
+                return E2EOfflineError::new_err("the handshake has already been built")
+            }
+        };
+
+        let message = e.to_string();
+        match e {
+            Error::Cipher(_) => CipherError::new_err(message),
+            Error::Contact(_) => ContactError::new_err(message),
+            Error::Hello(_) => HelloError::new_err(message),
+            Error::Hybrid(_) => HybridError::new_err(message),
+            Error::Identity(_) => IdentityError::new_err(message),
+            Error::KeyFile(_) => KeyFileError::new_err(message),
+            Error::Pake(_) => PakeError::new_err(message),
+            Error::Passphrase(_) => PassphraseError::new_err(message),
+            Error::Prekey(_) => PrekeyError::new_err(message),
+            Error::Recieve(_) => RecieveError::new_err(message),
+            Error::Rekey(_) => RekeyError::new_err(message),
+            Error::Session(_) => SessionError::new_err(message),
+            Error::State(_) => StateError::new_err(message),
+            Error::Encoding(_) => EncodingError::new_err(message),
+            Error::Crypto(_) => CryptoError::new_err(message),
+            _ => E2EOfflineError::new_err(message),
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, BindingError>;
+
+/// A handshake in progress. `build()` turns it into an `E2EOffline`, after
+/// which it can't be used again.
+#[pyclass(name = "E2EOfflineBuilder", module = "e2eoffline")]
+struct Builder {
+    inner: Option<e2eoffline::E2EOfflineBuilder>,
+}
+
+impl Builder {
+    fn inner(&self) -> Result<&e2eoffline::E2EOfflineBuilder> {
+        self.inner.as_ref().ok_or(BindingError::AlreadyBuilt)
+    }
+
+    fn inner_mut(&mut self) -> Result<&mut e2eoffline::E2EOfflineBuilder> {
+        self.inner.as_mut().ok_or(BindingError::AlreadyBuilt)
+    }
+}
+
+#[pymethods]
+impl Builder {
+    /// Starts a sender handshake with a freshly generated key.
+    #[staticmethod]
+    fn new_sender(py: Python<'_>) -> Self {
+        Self {
+            inner: Some(py.detach(e2eoffline::E2EOfflineBuilder::new_sender)),
+        }
+    }
+
+    /// Starts a reciever handshake with a freshly generated key. A hybrid
+    /// handshake also uses ML-KEM-768, against future quantum computers.
+    #[staticmethod]
+    #[pyo3(signature = (hybrid = false))]
+    fn new_reciever(py: Python<'_>, hybrid: bool) -> Self {
+        let mode = match hybrid {
+            false => HandshakeMode::Classic,
+            true => HandshakeMode::Hybrid,
+        };
+
+        Self {
+            inner: Some(py.detach(|| e2eoffline::E2EOfflineBuilder::new_reciever_with_mode(mode))),
+        }
+    }
+
+    /// This side's public key, which the sender gives to the reciever.
+    fn get_pubkey(&self) -> Result<String> {
+        Ok(self.inner()?.get_pubkey_encoded()?)
+    }
+
+    /// The reciever's signed hello, which the reciever gives to the sender.
+    fn get_hello(&self) -> Result<String> {
+        Ok(self.inner()?.get_hello_encoded()?)
+    }
+
+    fn get_fingerprint(&self) -> Result<String> {
+        Ok(self.inner()?.get_fingerprint()?)
+    }
+
+    fn get_other_fingerprint(&self) -> Result<String> {
+        Ok(self.inner()?.get_other_fingerprint()?)
+    }
+
+    /// Sets the other side's key: the reciever's hello for a sender, or the
+    /// sender's public key for a reciever.
+    fn set_other_public_key(&mut self, pubkey: &str) -> Result<()> {
+        Ok(self.inner_mut()?.set_other_public_key_encoded(pubkey)?)
+    }
+
+    /// Picks the cipher by name, such as `"xchacha20poly1305"`. Only the
+    /// sender picks.
+    fn set_cipher_suite(&mut self, cipher_suite: &str) -> Result<()> {
+        let cipher_suite = cipher_suite.parse()?;
+        self.inner_mut()?.set_cipher_suite(cipher_suite);
+        Ok(())
+    }
+
+    /// The sender's key exchange text, for the reciever's `recieve()`.
+    fn send(&mut self) -> Result<String> {
+        Ok(self.inner_mut()?.send()?)
+    }
+
+    fn recieve(&mut self, exchange: &str) -> Result<()> {
+        Ok(self.inner_mut()?.recieve(exchange)?)
+    }
+
+    fn build(&mut self) -> Result<Session> {
+        let inner = self.inner.take().ok_or(BindingError::AlreadyBuilt)?;
+
+        Ok(Session {
+            inner: inner.build()?,
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        match &self.inner {
+            Some(inner) => format!("{inner:?}"),
+            None => "E2EOfflineBuilder { built }".to_string(),
+        }
+    }
+}
+
+/// A conversation under a shared key.
+#[pyclass(name = "E2EOffline", module = "e2eoffline")]
+struct Session {
+    inner: e2eoffline::E2EOffline,
+}
+
+#[pymethods]
+impl Session {
+    /// Derives the key from a passphrase both users already share, with no
+    /// handshake at all.
+    #[staticmethod]
+    fn from_passphrase(py: Python<'_>, passphrase: &str) -> Result<Self> {
+        Ok(Self {
+            inner: py.detach(|| e2eoffline::E2EOffline::from_passphrase(passphrase))?,
+        })
+    }
+
+    /// Starts a conversation from a raw key, such as one from
+    /// `dangerously_export_raw_key()`.
+    #[staticmethod]
+    fn from_key_base64(key: &str) -> Result<Self> {
+        Ok(Self {
+            inner: e2eoffline::E2EOffline::from_key_base64(key)?,
+        })
+    }
+
+    #[staticmethod]
+    fn is_rekey_message(message: &str) -> bool {
+        e2eoffline::E2EOffline::is_rekey_message(message)
+    }
+
+    #[getter]
+    fn cipher_suite(&self) -> String {
+        self.inner.cipher_suite().to_string()
+    }
+
+    fn encrypt(&mut self, plaintext: &str) -> Result<String> {
+        Ok(self.inner.encrypt(plaintext)?)
+    }
+
+    fn decrypt(&mut self, ciphertext: &str) -> Result<String> {
+        Ok(self.inner.decrypt(ciphertext)?.expose_secret().to_string())
+    }
+
+    /// Switches to a fresh key, returning the message that tells the other
+    /// side about it.
+    fn rekey(&mut self) -> Result<String> {
+        Ok(self.inner.rekey()?)
+    }
+
+    fn apply_rekey(&mut self, message: &str) -> Result<()> {
+        Ok(self.inner.apply_rekey(message)?)
+    }
+
+    /// Anyone who has this key can read and forge messages until the next
+    /// rekey.
+    fn dangerously_export_raw_key(&self) -> String {
+        self.inner
+            .dangerously_export_raw_key()
+            .expose_secret()
+            .to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self.inner)
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "e2eoffline")]
+fn e2eoffline_python(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+
+    m.add_class::<Builder>()?;
+    m.add_class::<Session>()?;
+
+    m.add("E2EOfflineError", py.get_type::<E2EOfflineError>())?;
+    m.add("CipherError", py.get_type::<CipherError>())?;
+    m.add("ContactError", py.get_type::<ContactError>())?;
+    m.add("HelloError", py.get_type::<HelloError>())?;
+    m.add("HybridError", py.get_type::<HybridError>())?;
+    m.add("IdentityError", py.get_type::<IdentityError>())?;
+    m.add("KeyFileError", py.get_type::<KeyFileError>())?;
+    m.add("PakeError", py.get_type::<PakeError>())?;
+    m.add("PassphraseError", py.get_type::<PassphraseError>())?;
+    m.add("PrekeyError", py.get_type::<PrekeyError>())?;
+    m.add("RecieveError", py.get_type::<RecieveError>())?;
+    m.add("RekeyError", py.get_type::<RekeyError>())?;
+    m.add("SessionError", py.get_type::<SessionError>())?;
+    m.add("StateError", py.get_type::<StateError>())?;
+    m.add("EncodingError", py.get_type::<EncodingError>())?;
+    m.add("CryptoError", py.get_type::<CryptoError>())?;
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+import pytest
+
+import e2eoffline
+from e2eoffline import E2EOffline, E2EOfflineBuilder
+
+
+def handshake(hybrid=False, cipher_suite=None):
+    sender = E2EOfflineBuilder.new_sender()
+    reciever = E2EOfflineBuilder.new_reciever(hybrid=hybrid)
+
+    sender.set_other_public_key(reciever.get_hello())
+    reciever.set_other_public_key(sender.get_pubkey())
+    if cipher_suite is not None:
+        sender.set_cipher_suite(cipher_suite)
+
+    reciever.recieve(sender.send())
+
+    return sender.build(), reciever.build()
+
+
+@pytest.fixture(scope="module")
+def conversation():
+    return handshake()
+
+
+@pytest.mark.parametrize("hybrid", [False, True])
+def test_handshake_round_trip(hybrid):
+    sender, reciever = handshake(hybrid=hybrid)
+
+    assert reciever.decrypt(sender.encrypt("attack at dawn")) == "attack at dawn"
+    assert sender.decrypt(reciever.encrypt("and back")) == "and back"
+
+
+@pytest.mark.parametrize("suite", ["aes256gcm", "xchacha20poly1305", "aes256gcmsiv"])
+def test_cipher_suites(suite):
+    sender, reciever = handshake(cipher_suite=suite)
+
+    assert sender.cipher_suite == suite
+    assert reciever.cipher_suite == suite
+    assert reciever.decrypt(sender.encrypt("hello")) == "hello"
+
+
+def test_unknown_cipher_suite():
+    with pytest.raises(e2eoffline.CipherError):
+        E2EOfflineBuilder.new_sender().set_cipher_suite("rot13")
+
+
+def test_rekey(conversation):
+    sender, reciever = conversation
+
+    message = sender.rekey()
+    assert E2EOffline.is_rekey_message(message)
+    reciever.apply_rekey(message)
+
+    assert reciever.decrypt(sender.encrypt("after the rekey")) == "after the rekey"
+
+
+def test_tampered_ciphertext_is_refused(conversation):
+    sender, reciever = conversation
+    ciphertext = sender.encrypt("hello")
+    tampered = ciphertext[:-2] + ("A" if ciphertext[-2] != "A" else "B") + ciphertext[-1]
+
+    with pytest.raises(e2eoffline.E2EOfflineError):
+        reciever.decrypt(tampered)
+
+
+def test_passphrase():
+    first = E2EOffline.from_passphrase("correct horse battery staple")
+    second = E2EOffline.from_passphrase("correct horse battery staple")
+
+    assert second.decrypt(first.encrypt("hello")) == "hello"
+
+
+def test_raw_key(conversation):
+    sender, _ = conversation
+    copy = E2EOffline.from_key_base64(sender.dangerously_export_raw_key())
+
+    assert copy.decrypt(sender.encrypt("hello")) == "hello"
+
+
+def test_errors_map_to_exceptions():
+    sender = E2EOfflineBuilder.new_sender()
+
+    with pytest.raises(e2eoffline.HelloError, match="reciever hello"):
+        sender.set_other_public_key("not a hello")
+    with pytest.raises(e2eoffline.HelloError):
+        sender.get_hello()
+    with pytest.raises(e2eoffline.IdentityError):
+        sender.send()
+    with pytest.raises(e2eoffline.EncodingError):
+        E2EOffline.from_key_base64("not base64!")
+    with pytest.raises(e2eoffline.E2EOfflineError):
+        E2EOffline.from_key_base64("c2hvcnQ=")
+
+
+def test_exceptions_share_a_base_class():
+    for name in ["CipherError", "HelloError", "RecieveError", "EncodingError", "CryptoError"]:
+        assert issubclass(getattr(e2eoffline, name), e2eoffline.E2EOfflineError)
+    assert issubclass(e2eoffline.E2EOfflineError, Exception)
+
+
+def test_builder_is_used_up_by_build():
+    sender = E2EOfflineBuilder.new_sender()
+    reciever = E2EOfflineBuilder.new_reciever()
+    sender.set_other_public_key(reciever.get_hello())
+    sender.send()
+    sender.build()
+
+    with pytest.raises(e2eoffline.E2EOfflineError, match="already been built"):
+        sender.build()
+    with pytest.raises(e2eoffline.E2EOfflineError):
+        sender.get_pubkey()
+
+
+def test_repr_hides_secrets():
+    e2e = E2EOffline.from_passphrase("correct horse")
+
+    assert "correct horse" not in repr(e2e)
+    assert "REDACTED" in repr(e2e)
@@ -0,0 +1,168 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt::Display, str::FromStr};
+
+use aes_gcm::{
+    aead::{generic_array::typenum::Unsigned, Aead, AeadCore, KeyInit, Nonce},
+    Aes256Gcm,
+};
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::{CryptoRng, RngCore};
+use thiserror::Error;
+
+use crate::Result;
+
+/// The AEAD messages are encrypted with. The sender picks it during the
+/// handshake (see [`crate::E2EOfflineBuilder::set_cipher_suite`]) and every
+/// ciphertext names the one it was made with. A session agreed in a handshake
+/// refuses ciphertexts in any other suite; a passphrase conversation reads
+/// them all, since each side picks its own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CipherSuite {
+    /// AES-256-GCM with random 96-bit nonces. Its ciphertexts carry no name,
+    /// as they always have.
+    #[default]
+    Aes256Gcm,
+    /// XChaCha20-Poly1305. Its 192-bit nonces are safe to pick at random for
+    /// any number of messages, and it is fast without AES hardware, such as
+    /// in the webapp.
+    XChaCha20Poly1305,
+    /// AES-256-GCM-SIV, which only leaks whether two messages were the same
+    /// if a nonce is ever repeated, rather than the key.
+    Aes256GcmSiv,
+}
+
+impl CipherSuite {
+    const ALL: [CipherSuite; 3] = [
+        CipherSuite::Aes256Gcm,
+        CipherSuite::XChaCha20Poly1305,
+        CipherSuite::Aes256GcmSiv,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            CipherSuite::Aes256Gcm => "aes256gcm",
+            CipherSuite::XChaCha20Poly1305 => "xchacha20poly1305",
+            CipherSuite::Aes256GcmSiv => "aes256gcmsiv",
+        }
+    }
+
+    /// What goes in front of handshake messages and ciphertexts to name this
+    /// suite. It's empty for AES-256-GCM so older versions can still read
+    /// those.
+    pub(crate) fn prefix(self) -> String {
+        match self {
+            CipherSuite::Aes256Gcm => "".to_string(),
+            suite => format!("{}.", suite.name()),
+        }
+    }
+
+    /// Splits the name of a suite off the front of `text`, if it has one.
+    pub(crate) fn split_prefix(text: &str) -> (Self, &str) {
+        Self::ALL
+            .into_iter()
+            .skip(1)
+            .find_map(|suite| text.strip_prefix(&suite.prefix()).map(|rest| (suite, rest)))
+            .unwrap_or((CipherSuite::Aes256Gcm, text))
+    }
+
+    /// Encrypts under a fresh random nonce, returning the nonce and the
+    /// ciphertext.
+    pub(crate) fn encrypt<R: CryptoRng + RngCore>(
+        self,
+        key: &[u8],
+        rng: &mut R,
+        plaintext: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>)> {
+        match self {
+            CipherSuite::Aes256Gcm => seal::<Aes256Gcm, R>(key, rng, plaintext),
+            CipherSuite::XChaCha20Poly1305 => seal::<XChaCha20Poly1305, R>(key, rng, plaintext),
+            CipherSuite::Aes256GcmSiv => seal::<Aes256GcmSiv, R>(key, rng, plaintext),
+        }
+    }
+
+    pub(crate) fn decrypt(self, key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            CipherSuite::Aes256Gcm => open::<Aes256Gcm>(key, nonce, ciphertext),
+            CipherSuite::XChaCha20Poly1305 => open::<XChaCha20Poly1305>(key, nonce, ciphertext),
+            CipherSuite::Aes256GcmSiv => open::<Aes256GcmSiv>(key, nonce, ciphertext),
+        }
+    }
+}
+
+impl Display for CipherSuite {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for CipherSuite {
+    type Err = crate::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self::ALL
+            .into_iter()
+            .find(|suite| suite.name() == s.trim())
+            .ok_or(CipherError::UnknownSuite)?)
+    }
+}
+
+// all the suites take 256-bit keys, which is checked when a key is set
+fn seal<A: Aead + KeyInit, R: CryptoRng + RngCore>(
+    key: &[u8],
+    rng: &mut R,
+    plaintext: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut nonce = Nonce::<A>::default();
+    rng.fill_bytes(&mut nonce);
+
+    let ciphertext = A::new_from_slice(key)
+        .expect("key length is checked when the key is set")
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| CipherError::FailedEncryption)?;
+
+    Ok((nonce.to_vec(), ciphertext))
+}
+
+fn open<A: Aead + KeyInit>(key: &[u8], nonce: &[u8], ciphertext: &[u8]) -> Option<Vec<u8>> {
+    if nonce.len() != <A as AeadCore>::NonceSize::USIZE {
+        return None;
+    }
+
+    let mut nonce_array = Nonce::<A>::default();
+    nonce_array.copy_from_slice(nonce);
+
+    A::new_from_slice(key)
+        .expect("key length is checked when the key is set")
+        .decrypt(&nonce_array, ciphertext)
+        .ok()
+}
+
+#[derive(Error, Debug)]
+pub enum CipherError {
+    UnknownSuite,
+    FailedEncryption,
+    WrongSuite(CipherSuite),
+}
+
+impl Display for CipherError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CipherError::UnknownSuite => write!(
+                f,
+                "unknown cipher suite, expected one of aes256gcm, xchacha20poly1305 or aes256gcmsiv"
+            ),
+            CipherError::FailedEncryption => write!(f, "failed to encrypt message"),
+            CipherError::WrongSuite(suite) => {
+                write!(
+                    f,
+                    "message is encrypted with {suite}, which this session doesn't use"
+                )
+            }
+        }
+    }
+}
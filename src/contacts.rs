@@ -0,0 +1,269 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Display;
+
+use thiserror::Error;
+
+use crate::{identity, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verification {
+    /// The key was trusted the first time it was seen.
+    Unverified,
+    /// The fingerprint was compared with the contact face to face.
+    VerifiedInPerson,
+}
+
+impl Verification {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Verification::Unverified => "unverified",
+            Verification::VerifiedInPerson => "verified-in-person",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "unverified" => Some(Verification::Unverified),
+            "verified-in-person" => Some(Verification::VerifiedInPerson),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Verification {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Verification::Unverified => write!(f, "unverified"),
+            Verification::VerifiedInPerson => write!(f, "verified in person"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Contact {
+    pub name: String,
+    pub fingerprint: String,
+    pub verification: Verification,
+}
+
+/// What [`ContactBook::check`] found out about a contact's key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trust {
+    /// The contact wasn't known, and has been remembered with this key.
+    FirstSeen,
+    /// The key matches the one remembered for the contact.
+    Known(Verification),
+}
+
+/// Remembers the key fingerprint first seen for each contact, like SSH's
+/// `known_hosts`, and refuses a different key for them later on.
+///
+/// A key once seen with an expiry is refused without one from then on, since
+/// anyone can strip the expiry off a key.
+///
+/// The encoded form has one contact per line as
+/// `<fingerprint> <verification> <name>`, plus `revoked <fingerprint>` lines
+/// for keys revoked with a revocation certificate and
+/// `expires <fingerprint> <expires_at>` lines for keys seen with an expiry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContactBook {
+    contacts: Vec<Contact>,
+    revoked: Vec<String>,
+    expiring: Vec<(String, u64)>,
+}
+
+impl ContactBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_encoded(encoded: &str) -> Result<Self> {
+        let mut book = Self::new();
+
+        for line in encoded.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let mut parts = line.splitn(3, ' ');
+
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("revoked"), Some(fingerprint), None) => {
+                    book.revoked.push(fingerprint.to_string())
+                }
+                (Some("expires"), Some(fingerprint), Some(expires_at)) => book.expiring.push((
+                    fingerprint.to_string(),
+                    expires_at.parse().map_err(|_| ContactError::InvalidLine)?,
+                )),
+                (Some(fingerprint), Some(verification), Some(name)) => {
+                    book.contacts.push(Contact {
+                        name: name.to_string(),
+                        fingerprint: fingerprint.to_string(),
+                        verification: Verification::parse(verification)
+                            .ok_or(ContactError::InvalidLine)?,
+                    })
+                }
+                _ => Err(ContactError::InvalidLine)?,
+            }
+        }
+
+        Ok(book)
+    }
+
+    pub fn to_encoded(&self) -> String {
+        let contacts = self.contacts.iter().map(|contact| {
+            format!(
+                "{} {} {}\n",
+                contact.fingerprint,
+                contact.verification.as_str(),
+                contact.name
+            )
+        });
+        let revoked = self
+            .revoked
+            .iter()
+            .map(|fingerprint| format!("revoked {fingerprint}\n"));
+        let expiring = self
+            .expiring
+            .iter()
+            .map(|(fingerprint, expires_at)| format!("expires {fingerprint} {expires_at}\n"));
+
+        contacts.chain(revoked).chain(expiring).collect()
+    }
+
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Contact> {
+        let name = normalize_name(name);
+
+        self.contacts.iter().find(|contact| contact.name == name)
+    }
+
+    /// Checks a contact's key fingerprint and expiry (see
+    /// [`E2EOfflineBuilder::get_other_fingerprint`](crate::E2EOfflineBuilder::get_other_fingerprint)
+    /// and [`E2EOfflineBuilder::get_other_expiry`](crate::E2EOfflineBuilder::get_other_expiry)).
+    /// A new contact is remembered with it; a known contact presenting a
+    /// different key is an error, as is a revoked key or a key that has lost
+    /// the expiry it was seen with.
+    pub fn check(
+        &mut self,
+        name: &str,
+        fingerprint: &str,
+        expires_at: Option<u64>,
+    ) -> Result<Trust> {
+        let name = normalize_name(name);
+
+        if name.is_empty() || name.contains('\n') {
+            Err(ContactError::InvalidName)?;
+        }
+
+        if self.revoked.iter().any(|revoked| revoked == fingerprint) {
+            Err(ContactError::Revoked(name.to_string()))?;
+        }
+
+        let seen_expiring = self.expiring.iter().position(|(f, _)| f == fingerprint);
+
+        if seen_expiring.is_some() && expires_at.is_none() {
+            Err(ContactError::ExpiryStripped(name.to_string()))?;
+        }
+
+        let trust = match self.get(name) {
+            Some(contact) if contact.fingerprint == fingerprint => {
+                Trust::Known(contact.verification)
+            }
+            Some(_) => Err(ContactError::KeyChanged(name.to_string()))?,
+            None => {
+                self.contacts.push(Contact {
+                    name: name.to_string(),
+                    fingerprint: fingerprint.to_string(),
+                    verification: Verification::Unverified,
+                });
+
+                Trust::FirstSeen
+            }
+        };
+
+        // the expiry is signed with the key, so a newer one can replace it
+        match (seen_expiring, expires_at) {
+            (Some(i), Some(expires_at)) => self.expiring[i].1 = expires_at,
+            (None, Some(expires_at)) => self.expiring.push((fingerprint.to_string(), expires_at)),
+            _ => {}
+        }
+
+        Ok(trust)
+    }
+
+    pub fn mark_verified(&mut self, name: &str) -> Result<()> {
+        let name = normalize_name(name);
+
+        let contact = self
+            .contacts
+            .iter_mut()
+            .find(|contact| contact.name == name)
+            .ok_or(ContactError::UnknownContact)?;
+
+        contact.verification = Verification::VerifiedInPerson;
+
+        Ok(())
+    }
+
+    /// Forgets a contact, so the next key seen for them is trusted again. This
+    /// is how a deliberate key change is accepted.
+    pub fn forget(&mut self, name: &str) {
+        let name = normalize_name(name);
+
+        if let Some(contact) = self.get(name) {
+            let fingerprint = contact.fingerprint.clone();
+            self.expiring.retain(|(f, _)| *f != fingerprint);
+        }
+
+        self.contacts.retain(|contact| contact.name != name);
+    }
+
+    pub fn add_revocation_certificate_encoded(&mut self, certificate: &str) -> Result<()> {
+        let fingerprint = identity::verify_revocation_certificate(certificate)?;
+
+        if !self.revoked.contains(&fingerprint) {
+            self.revoked.push(fingerprint);
+        }
+
+        Ok(())
+    }
+}
+
+// names are compared as they're stored, without surrounding whitespace
+fn normalize_name(name: &str) -> &str {
+    name.trim()
+}
+
+#[derive(Error, Debug)]
+pub enum ContactError {
+    InvalidLine,
+    InvalidName,
+    UnknownContact,
+    KeyChanged(String),
+    Revoked(String),
+    ExpiryStripped(String),
+}
+
+impl Display for ContactError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ContactError::InvalidLine => write!(f, "invalid contact book line"),
+            ContactError::InvalidName => write!(f, "invalid contact name"),
+            ContactError::UnknownContact => write!(f, "unknown contact"),
+            ContactError::KeyChanged(name) => write!(
+                f,
+                "WARNING: THE KEY FOR {name} HAS CHANGED! Someone could be intercepting \
+                your messages. Check with {name} in person before trusting the new key"
+            ),
+            ContactError::Revoked(name) => write!(f, "the key for {name} has been revoked"),
+            ContactError::ExpiryStripped(name) => write!(
+                f,
+                "the key for {name} came without the expiry date it was seen with before"
+            ),
+        }
+    }
+}
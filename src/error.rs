@@ -0,0 +1,134 @@
+use alloc::string::{String, ToString};
+use core::fmt::Display;
+
+use thiserror::Error;
+
+pub use crate::cipher::CipherError;
+pub use crate::contacts::ContactError;
+pub use crate::hybrid::HybridError;
+pub use crate::identity::IdentityError;
+pub use crate::keyfile::KeyFileError;
+pub use crate::pake::PakeError;
+pub use crate::passphrase::PassphraseError;
+pub use crate::prekey::PrekeyError;
+#[cfg(feature = "std")]
+pub use crate::session::SessionError;
+#[cfg(feature = "std")]
+pub use crate::state::StateError;
+pub use crate::{HelloError, RecieveError, RekeyError};
+
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+/// Every error this crate returns. It is a plain enum so it works without
+/// std, and `?` still turns it into an `anyhow::Error` or a boxed error.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    Cipher(CipherError),
+    Contact(ContactError),
+    Hello(HelloError),
+    Hybrid(HybridError),
+    Identity(IdentityError),
+    KeyFile(KeyFileError),
+    Pake(PakeError),
+    Passphrase(PassphraseError),
+    Prekey(PrekeyError),
+    Recieve(RecieveError),
+    Rekey(RekeyError),
+    #[cfg(feature = "std")]
+    Session(SessionError),
+    #[cfg(feature = "std")]
+    State(StateError),
+    /// Text that isn't valid base64, UTF-8, JSON or a key encoding.
+    Encoding(String),
+    /// A failure inside one of the cryptography crates.
+    Crypto(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Cipher(e) => e.fmt(f),
+            Error::Contact(e) => e.fmt(f),
+            Error::Hello(e) => e.fmt(f),
+            Error::Hybrid(e) => e.fmt(f),
+            Error::Identity(e) => e.fmt(f),
+            Error::KeyFile(e) => e.fmt(f),
+            Error::Pake(e) => e.fmt(f),
+            Error::Passphrase(e) => e.fmt(f),
+            Error::Prekey(e) => e.fmt(f),
+            Error::Recieve(e) => e.fmt(f),
+            Error::Rekey(e) => e.fmt(f),
+            #[cfg(feature = "std")]
+            Error::Session(e) => e.fmt(f),
+            #[cfg(feature = "std")]
+            Error::State(e) => e.fmt(f),
+            Error::Encoding(message) | Error::Crypto(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+macro_rules! from_module_errors {
+    ($($variant:ident($error:ty)),* $(,)?) => {
+        $(
+            impl From<$error> for Error {
+                fn from(e: $error) -> Self {
+                    Error::$variant(e)
+                }
+            }
+        )*
+    };
+}
+
+from_module_errors!(
+    Cipher(CipherError),
+    Contact(ContactError),
+    Hello(HelloError),
+    Hybrid(HybridError),
+    Identity(IdentityError),
+    KeyFile(KeyFileError),
+    Pake(PakeError),
+    Passphrase(PassphraseError),
+    Prekey(PrekeyError),
+    Recieve(RecieveError),
+    Rekey(RekeyError),
+);
+
+#[cfg(feature = "std")]
+from_module_errors!(Session(SessionError), State(StateError));
+
+// errors from other crates only keep their message, so their types don't
+// become part of this crate's API
+macro_rules! from_foreign_errors {
+    ($variant:ident: $($error:ty),* $(,)?) => {
+        $(
+            impl From<$error> for Error {
+                fn from(e: $error) -> Self {
+                    Error::$variant(e.to_string())
+                }
+            }
+        )*
+    };
+}
+
+from_foreign_errors!(
+    Encoding: base64::DecodeError,
+    core::str::Utf8Error,
+    core::num::ParseIntError,
+    pkcs8::Error,
+    pkcs8::der::Error,
+    pkcs8::spki::Error,
+    rsa::pkcs1::Error,
+    rsa::pkcs8::Error,
+    rsa::pkcs8::spki::Error,
+    ssh_key::Error,
+);
+
+#[cfg(feature = "std")]
+from_foreign_errors!(Encoding: serde_json::Error);
+
+from_foreign_errors!(
+    Crypto: argon2::Error,
+    hkdf::hmac::digest::InvalidLength,
+    rsa::errors::Error,
+);
@@ -0,0 +1,182 @@
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Display;
+
+use base64::Engine;
+use hkdf::Hkdf;
+use rand::{CryptoRng, RngCore};
+use rsa::sha2::Sha256;
+use thiserror::Error;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::mlkem::{DecapsulationKey, EncapsulationKey};
+use crate::prekey::{decode_key, encode_key};
+use crate::Result;
+
+const KDF_INFO: &[u8] = b"E2EOffline ML-KEM-768+X25519";
+
+/// The reciever's secret keys for a hybrid handshake.
+pub(crate) struct HybridSecrets {
+    kem: DecapsulationKey,
+    x25519: StaticSecret,
+}
+
+impl HybridSecrets {
+    pub(crate) fn new<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        Self {
+            kem: DecapsulationKey::generate(rng),
+            x25519: StaticSecret::random_from_rng(rng),
+        }
+    }
+
+    /// The encapsulation key and X25519 public key, as they appear in the hello.
+    pub(crate) fn public_encoded(&self) -> String {
+        let ek = self.kem.encapsulation_key();
+
+        format!(
+            "{}.{}",
+            base64::engine::general_purpose::URL_SAFE.encode(ek.as_bytes()),
+            encode_key(&PublicKey::from(&self.x25519))
+        )
+    }
+
+    /// The secret keys, for saving an unfinished handshake.
+    pub(crate) fn secret_encoded(&self) -> String {
+        format!(
+            "{}.{}",
+            base64::engine::general_purpose::URL_SAFE.encode(self.kem.as_bytes()),
+            base64::engine::general_purpose::URL_SAFE.encode(self.x25519.to_bytes())
+        )
+    }
+
+    pub(crate) fn decode_secret(dk: &str, x25519: &str) -> Result<Self> {
+        let dk = Zeroizing::new(base64::engine::general_purpose::URL_SAFE.decode(dk)?);
+        let x25519: [u8; 32] = base64::engine::general_purpose::URL_SAFE
+            .decode(x25519)?
+            .try_into()
+            .map_err(|_| HybridError::InvalidKey)?;
+
+        Ok(Self {
+            kem: DecapsulationKey::from_bytes(&dk).ok_or(HybridError::InvalidKey)?,
+            x25519: StaticSecret::from(x25519),
+        })
+    }
+
+    pub(crate) fn agree(&self, ciphertext: &str, ephemeral: &str) -> Result<Zeroizing<[u8; 32]>> {
+        let ciphertext = base64::engine::general_purpose::URL_SAFE.decode(ciphertext)?;
+        let ephemeral = decode_key(ephemeral)?;
+
+        let kem_secret = self
+            .kem
+            .decapsulate(&ciphertext)
+            .ok_or(HybridError::InvalidCiphertext)?;
+        let dh_secret = self.x25519.diffie_hellman(&ephemeral);
+
+        Ok(combine(
+            &kem_secret,
+            dh_secret.as_bytes(),
+            &ciphertext,
+            &ephemeral,
+            &PublicKey::from(&self.x25519),
+        ))
+    }
+}
+
+/// The reciever's public keys for a hybrid handshake, as seen by the sender.
+pub(crate) struct HybridPublic {
+    kem: EncapsulationKey,
+    x25519: PublicKey,
+}
+
+impl HybridPublic {
+    pub(crate) fn decode(ek: &str, x25519: &str) -> Result<Self> {
+        let ek = base64::engine::general_purpose::URL_SAFE.decode(ek)?;
+
+        Ok(Self {
+            kem: EncapsulationKey::from_bytes(&ek).ok_or(HybridError::InvalidKey)?,
+            x25519: decode_key(x25519)?,
+        })
+    }
+
+    /// Encodes the keys the same way as [`HybridSecrets::public_encoded`].
+    pub(crate) fn encoded(&self) -> String {
+        format!(
+            "{}.{}",
+            base64::engine::general_purpose::URL_SAFE.encode(self.kem.as_bytes()),
+            encode_key(&self.x25519)
+        )
+    }
+
+    /// Returns the encoded ML-KEM ciphertext, the encoded ephemeral X25519 key
+    /// and the derived key.
+    pub(crate) fn agree<R: CryptoRng + RngCore>(
+        &self,
+        rng: &mut R,
+    ) -> (String, String, Zeroizing<[u8; 32]>) {
+        let (ciphertext, kem_secret) = self.kem.encapsulate(rng);
+
+        let ephemeral = StaticSecret::random_from_rng(rng);
+        let ephemeral_public = PublicKey::from(&ephemeral);
+        let dh_secret = ephemeral.diffie_hellman(&self.x25519);
+
+        let key = combine(
+            &kem_secret,
+            dh_secret.as_bytes(),
+            &ciphertext,
+            &ephemeral_public,
+            &self.x25519,
+        );
+
+        (
+            base64::engine::general_purpose::URL_SAFE.encode(ciphertext),
+            encode_key(&ephemeral_public),
+            key,
+        )
+    }
+}
+
+// both shared secrets go into the KDF along with the transcript, so the key
+// stays secret as long as either ML-KEM or X25519 holds
+fn combine(
+    kem_secret: &[u8; 32],
+    dh_secret: &[u8; 32],
+    ciphertext: &[u8],
+    ephemeral: &PublicKey,
+    reciever: &PublicKey,
+) -> Zeroizing<[u8; 32]> {
+    let mut ikm = Zeroizing::new(Vec::with_capacity(64));
+    ikm.extend_from_slice(kem_secret);
+    ikm.extend_from_slice(dh_secret);
+
+    let mut salt = ciphertext.to_vec();
+    salt.extend_from_slice(ephemeral.as_bytes());
+    salt.extend_from_slice(reciever.as_bytes());
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(&salt), &ikm)
+        .expand(KDF_INFO, &mut *key)
+        .expect("32 bytes is a valid HKDF output length");
+
+    key
+}
+
+#[derive(Error, Debug)]
+pub enum HybridError {
+    InvalidKey,
+    InvalidCiphertext,
+    InvalidString,
+    MissingKeys,
+    NotHybrid,
+}
+
+impl Display for HybridError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HybridError::InvalidKey => write!(f, "invalid ML-KEM encapsulation key"),
+            HybridError::InvalidCiphertext => write!(f, "invalid ML-KEM ciphertext"),
+            HybridError::InvalidString => write!(f, "invalid hybrid sender string"),
+            HybridError::MissingKeys => write!(f, "not set up for a hybrid handshake"),
+            HybridError::NotHybrid => write!(f, "expected a hybrid key exchange"),
+        }
+    }
+}
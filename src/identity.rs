@@ -0,0 +1,358 @@
+use alloc::{format, string::String, vec::Vec};
+use core::fmt::Display;
+
+use base64::Engine;
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit, Nonce};
+use hkdf::Hkdf;
+use rand::{CryptoRng, RngCore};
+use rsa::{
+    pkcs1::{DecodeRsaPublicKey, EncodeRsaPublicKey},
+    pkcs1v15::{Signature, SigningKey, VerifyingKey},
+    pkcs8::{DecodePrivateKey as _, EncodePrivateKey as _},
+    sha2::{Digest, Sha256},
+    signature::{Signer, Verifier},
+    Pkcs1v15Encrypt, PublicKey as _, RsaPrivateKey, RsaPublicKey,
+};
+use thiserror::Error;
+use zeroize::Zeroizing;
+// the `rsa` crate is on an older `pkcs8` than `ed25519-dalek`, so these are
+// the newer traits, for the Ed25519 keys
+use pkcs8::{
+    spki::{DecodePublicKey as _, EncodePublicKey as _},
+    DecodePrivateKey as _, EncodePrivateKey as _, SecretDocument,
+};
+
+use crate::{RecieveError, Result};
+
+const REVOCATION_TAG: &str = "revoke";
+const KEY_WRAP_INFO: &[u8] = b"E2EOffline Ed25519 key wrap";
+
+/// The private half of an identity key.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) enum PrivateKey {
+    Rsa(RsaPrivateKey),
+    Ed25519(ed25519_dalek::SigningKey),
+}
+
+/// The public half of an identity key.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) enum PublicKey {
+    Rsa(RsaPublicKey),
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+impl PrivateKey {
+    pub(crate) fn public_key(&self) -> PublicKey {
+        match self {
+            PrivateKey::Rsa(private_key) => PublicKey::Rsa(RsaPublicKey::from(private_key)),
+            PrivateKey::Ed25519(signing_key) => PublicKey::Ed25519(signing_key.verifying_key()),
+        }
+    }
+
+    /// Signs with PKCS#1 v1.5 and SHA-256 for RSA keys, or plain Ed25519.
+    pub(crate) fn sign(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            PrivateKey::Rsa(private_key) => SigningKey::<Sha256>::new(private_key.clone())
+                .sign(message)
+                .as_ref()
+                .to_vec(),
+            PrivateKey::Ed25519(signing_key) => signing_key.sign(message).to_vec(),
+        }
+    }
+
+    /// Unwraps a key wrapped with [`PublicKey::wrap_key`].
+    pub(crate) fn unwrap_key(&self, wrapped: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        match self {
+            PrivateKey::Rsa(private_key) => Ok(Zeroizing::new(
+                private_key.decrypt(Pkcs1v15Encrypt, wrapped)?,
+            )),
+            PrivateKey::Ed25519(signing_key) => {
+                if wrapped.len() < 32 {
+                    Err(RecieveError::InvalidString)?;
+                }
+                let (ephemeral, ciphertext) = wrapped.split_at(32);
+                let ephemeral = x25519_dalek::PublicKey::from(
+                    <[u8; 32]>::try_from(ephemeral).expect("split at 32 bytes"),
+                );
+
+                let secret = x25519_dalek::StaticSecret::from(*Zeroizing::new(
+                    signing_key.to_scalar_bytes(),
+                ));
+                let reciever = x25519_dalek::PublicKey::from(&secret);
+                let wrapping_key =
+                    wrapping_key(&secret.diffie_hellman(&ephemeral), &ephemeral, &reciever)?;
+
+                Ok(Zeroizing::new(
+                    ChaCha20Poly1305::new(wrapping_key.as_slice().into())
+                        .decrypt(&Nonce::default(), ciphertext)
+                        .map_err(|_| RecieveError::FailedDecryption)?,
+                ))
+            }
+        }
+    }
+
+    pub(crate) fn to_pkcs8_der(&self) -> Result<SecretDocument> {
+        match self {
+            PrivateKey::Rsa(private_key) => Ok(SecretDocument::try_from(
+                private_key.to_pkcs8_der()?.as_bytes(),
+            )?),
+            PrivateKey::Ed25519(signing_key) => Ok(signing_key.to_pkcs8_der()?),
+        }
+    }
+
+    /// Reads an RSA or Ed25519 PKCS#8 private key.
+    pub(crate) fn from_pkcs8_der(der: &[u8]) -> Result<Self> {
+        match RsaPrivateKey::from_pkcs8_der(der) {
+            Ok(private_key) => Ok(PrivateKey::Rsa(private_key)),
+            Err(e) => Ok(PrivateKey::Ed25519(
+                ed25519_dalek::SigningKey::from_pkcs8_der(der).map_err(|_| e)?,
+            )),
+        }
+    }
+}
+
+impl PublicKey {
+    pub(crate) fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            PublicKey::Rsa(pubkey) => VerifyingKey::<Sha256>::from(pubkey.clone())
+                .verify(
+                    message,
+                    &Signature::from(Vec::from(signature).into_boxed_slice()),
+                )
+                .is_ok(),
+            PublicKey::Ed25519(verifying_key) => ed25519_dalek::Signature::from_slice(signature)
+                .is_ok_and(|signature| verifying_key.verify_strict(message, &signature).is_ok()),
+        }
+    }
+
+    /// Wraps a key so only the holder of the private key can read it. RSA
+    /// keys encrypt it with PKCS#1 v1.5. Ed25519 keys can't encrypt, so the
+    /// key is wrapped under an X25519 exchange between a fresh ephemeral key
+    /// and the Ed25519 key's X25519 form, as `age` does for `ssh-ed25519`
+    /// recipients.
+    pub(crate) fn wrap_key<R: CryptoRng + RngCore>(
+        &self,
+        rng: &mut R,
+        key: &[u8],
+    ) -> Result<Vec<u8>> {
+        match self {
+            PublicKey::Rsa(pubkey) => Ok(pubkey.encrypt(rng, Pkcs1v15Encrypt, key)?),
+            PublicKey::Ed25519(verifying_key) => {
+                let reciever =
+                    x25519_dalek::PublicKey::from(verifying_key.to_montgomery().to_bytes());
+
+                let ephemeral = x25519_dalek::StaticSecret::random_from_rng(rng);
+                let ephemeral_public = x25519_dalek::PublicKey::from(&ephemeral);
+                let wrapping_key = wrapping_key(
+                    &ephemeral.diffie_hellman(&reciever),
+                    &ephemeral_public,
+                    &reciever,
+                )?;
+
+                // each wrapping key is only ever used once, so the nonce can be fixed
+                let ciphertext = ChaCha20Poly1305::new(wrapping_key.as_slice().into())
+                    .encrypt(&Nonce::default(), key)
+                    .expect("a key is well within ChaCha20-Poly1305's message size");
+
+                Ok([ephemeral_public.as_bytes(), &ciphertext[..]].concat())
+            }
+        }
+    }
+
+    /// PKCS#1 DER for RSA keys, which is what this crate has always used, and
+    /// SPKI DER for Ed25519 keys.
+    pub(crate) fn to_der(&self) -> Result<Vec<u8>> {
+        match self {
+            PublicKey::Rsa(pubkey) => Ok(pubkey.to_pkcs1_der()?.into_vec()),
+            PublicKey::Ed25519(verifying_key) => Ok(verifying_key.to_public_key_der()?.into_vec()),
+        }
+    }
+
+    pub(crate) fn from_der(der: &[u8]) -> Result<Self> {
+        match RsaPublicKey::from_pkcs1_der(der) {
+            Ok(pubkey) => Ok(PublicKey::Rsa(pubkey)),
+            Err(e) => Ok(PublicKey::Ed25519(
+                ed25519_dalek::VerifyingKey::from_public_key_der(der).map_err(|_| e)?,
+            )),
+        }
+    }
+}
+
+fn wrapping_key(
+    shared_secret: &x25519_dalek::SharedSecret,
+    ephemeral: &x25519_dalek::PublicKey,
+    reciever: &x25519_dalek::PublicKey,
+) -> Result<Zeroizing<[u8; 32]>> {
+    // a low order key would make the shared secret predictable
+    if !shared_secret.was_contributory() {
+        Err(IdentityError::InvalidString)?;
+    }
+
+    let salt = [ephemeral.as_bytes(), &reciever.as_bytes()[..]].concat();
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(&salt), shared_secret.as_bytes())
+        .expand(KEY_WRAP_INFO, &mut *key)
+        .expect("32 bytes is a valid HKDF output length");
+
+    Ok(key)
+}
+
+// parts of an identity are joined with '~' so it can sit inside the
+// '.'-separated hello and prekey bundle formats
+const IDENTITY_SEPARATOR: char = '~';
+
+/// Encodes a public key, along with its expiry if it has one. An expiring key
+/// is signed together with its expiry so the date can't be stripped or moved.
+pub(crate) fn encode_identity(private_key: &PrivateKey, expires_at: Option<u64>) -> Result<String> {
+    let der = private_key.public_key().to_der()?;
+    let pubkey_encoded = base64::engine::general_purpose::URL_SAFE.encode(der);
+
+    let Some(expires_at) = expires_at else {
+        return Ok(pubkey_encoded);
+    };
+
+    let signed_text = format!("{pubkey_encoded}{IDENTITY_SEPARATOR}{expires_at}");
+
+    let signature = private_key.sign(signed_text.as_bytes());
+    let signature_encoded = base64::engine::general_purpose::URL_SAFE.encode(signature);
+
+    Ok(format!(
+        "{signed_text}{IDENTITY_SEPARATOR}{signature_encoded}"
+    ))
+}
+
+/// Decodes a public key, refusing it if it carries an expiry that has passed.
+/// Without std, any key with an expiry is refused.
+///
+/// Anyone can strip the expiry off and pass the bare key, which decodes as
+/// never expiring. [`ContactBook`](crate::ContactBook) refuses a bare key once
+/// it has seen that key with an expiry.
+pub(crate) fn decode_identity(identity: &str) -> Result<PublicKey> {
+    Ok(decode_identity_with_expiry(identity)?.0)
+}
+
+/// Like [`decode_identity`], but also returns the key's expiry, if it has one.
+pub(crate) fn decode_identity_with_expiry(identity: &str) -> Result<(PublicKey, Option<u64>)> {
+    let mut parts = identity.trim().split(IDENTITY_SEPARATOR);
+
+    let pubkey_encoded = parts.next().ok_or(IdentityError::InvalidString)?;
+    let der = base64::engine::general_purpose::URL_SAFE.decode(pubkey_encoded)?;
+    let pubkey = PublicKey::from_der(&der)?;
+
+    let (Some(expires_at), Some(signature_encoded)) = (parts.next(), parts.next()) else {
+        return Ok((pubkey, None));
+    };
+
+    let signature = base64::engine::general_purpose::URL_SAFE.decode(signature_encoded)?;
+
+    if !pubkey.verify(
+        format!("{pubkey_encoded}{IDENTITY_SEPARATOR}{expires_at}").as_bytes(),
+        &signature,
+    ) {
+        Err(IdentityError::FailedSignatureCheck)?;
+    }
+
+    let expires_at = expires_at.parse::<u64>()?;
+
+    // without a clock an expiring key can't be checked, so it isn't trusted
+    if expires_at <= now().ok_or(IdentityError::NoClock)? {
+        Err(IdentityError::Expired)?;
+    }
+
+    Ok((pubkey, Some(expires_at)))
+}
+
+/// A short, stable name for a public key: the URL-safe base64 SHA-256 of its
+/// DER encoding (see [`PublicKey::to_der`]).
+pub(crate) fn fingerprint(pubkey: &PublicKey) -> Result<String> {
+    Ok(base64::engine::general_purpose::URL_SAFE.encode(Sha256::digest(pubkey.to_der()?)))
+}
+
+pub(crate) fn revocation_certificate(private_key: &PrivateKey) -> Result<String> {
+    let der = private_key.public_key().to_der()?;
+    let pubkey_encoded = base64::engine::general_purpose::URL_SAFE.encode(der);
+
+    let signed_text = format!("{REVOCATION_TAG}.{pubkey_encoded}");
+
+    let signature = private_key.sign(signed_text.as_bytes());
+    let signature_encoded = base64::engine::general_purpose::URL_SAFE.encode(signature);
+
+    Ok(format!("{signed_text}.{signature_encoded}"))
+}
+
+/// Checks a revocation certificate and returns the fingerprint of the key it
+/// revokes.
+pub(crate) fn verify_revocation_certificate(certificate: &str) -> Result<String> {
+    let (signed_text, signature_encoded) = certificate
+        .trim()
+        .rsplit_once('.')
+        .ok_or(IdentityError::InvalidRevocation)?;
+
+    let pubkey_encoded = signed_text
+        .strip_prefix(&format!("{REVOCATION_TAG}."))
+        .ok_or(IdentityError::InvalidRevocation)?;
+
+    let der = base64::engine::general_purpose::URL_SAFE.decode(pubkey_encoded)?;
+    let pubkey = PublicKey::from_der(&der)?;
+
+    let signature = base64::engine::general_purpose::URL_SAFE.decode(signature_encoded)?;
+
+    if !pubkey.verify(signed_text.as_bytes(), &signature) {
+        Err(IdentityError::InvalidRevocation)?;
+    }
+
+    fingerprint(&pubkey)
+}
+
+/// Seconds since the Unix epoch, or `None` without std, where there is no
+/// clock to ask. `SystemTime` panics on the web, so the webapp asks the
+/// browser instead.
+pub(crate) fn now() -> Option<u64> {
+    #[cfg(all(feature = "std", target_arch = "wasm32"))]
+    {
+        Some((js_sys::Date::now() / 1000.0) as u64)
+    }
+
+    #[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+    {
+        Some(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        )
+    }
+
+    #[cfg(not(feature = "std"))]
+    {
+        None
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum IdentityError {
+    InvalidString,
+    InvalidRevocation,
+    FailedSignatureCheck,
+    Expired,
+    Revoked,
+    MissingOtherKey,
+    NoClock,
+}
+
+impl Display for IdentityError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            IdentityError::InvalidString => write!(f, "invalid public key"),
+            IdentityError::InvalidRevocation => write!(f, "invalid revocation certificate"),
+            IdentityError::FailedSignatureCheck => write!(f, "public key signature failed"),
+            IdentityError::Expired => write!(f, "public key has expired"),
+            IdentityError::Revoked => write!(f, "public key has been revoked"),
+            IdentityError::MissingOtherKey => write!(f, "the other public key hasn't been set"),
+            IdentityError::NoClock => {
+                write!(f, "can't check the public key's expiry without a clock")
+            }
+        }
+    }
+}
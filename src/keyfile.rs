@@ -0,0 +1,359 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+};
+use core::fmt::{Debug, Display};
+
+#[cfg(feature = "std")]
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use rsa::{
+    pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey},
+    pkcs8::{DecodePublicKey, EncodePublicKey, LineEnding},
+    BigUint, PublicKeyParts, RsaPrivateKey, RsaPublicKey,
+};
+use ssh_key::{private::KeypairData, public::KeyData, Mpint};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use crate::{
+    identity::{self, PrivateKey, PublicKey},
+    Result, SecretString,
+};
+
+const ENCRYPTED_PRIVATE_KEY_LABEL: &str = "ENCRYPTED PRIVATE KEY";
+const OPENSSH_COMMENT: &str = "e2eoffline";
+const PBKDF2_ITERATIONS: u32 = 600_000;
+const RSA_BITS: usize = 3072;
+// RSA keys smaller than this are refused when they are loaded or recieved
+const MIN_RSA_BITS: usize = 2048;
+
+/// A long-term RSA or Ed25519 key pair, which can be saved to and loaded from
+/// the usual key file formats. Existing `ssh-rsa` and `ssh-ed25519` SSH keys
+/// can be used as identities too; other key types are refused with
+/// [`KeyFileError::UnsupportedKeyType`].
+///
+/// An Ed25519 key can't encrypt, so in a classic handshake the shared key is
+/// wrapped to its X25519 form instead (see [`HandshakeMode::Classic`]).
+///
+/// [`HandshakeMode::Classic`]: crate::HandshakeMode::Classic
+#[derive(Clone, PartialEq, Eq)]
+pub struct Identity {
+    private_key: PrivateKey,
+}
+
+impl Identity {
+    /// Generates a 3072-bit RSA identity.
+    #[cfg(feature = "std")]
+    pub fn generate() -> Self {
+        Self::generate_with_rng(&mut OsRng)
+    }
+
+    /// Like [`Self::generate`], but the key comes from `rng`, so a seeded RNG
+    /// always gives the same key.
+    pub fn generate_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        Self {
+            private_key: PrivateKey::Rsa(RsaPrivateKey::new(rng, RSA_BITS).unwrap()),
+        }
+    }
+
+    /// Generates an Ed25519 identity, which is much quicker to make than an
+    /// RSA one and has shorter public keys.
+    #[cfg(feature = "std")]
+    pub fn generate_ed25519() -> Self {
+        Self::generate_ed25519_with_rng(&mut OsRng)
+    }
+
+    /// Like [`Self::generate_ed25519`], but the key comes from `rng`.
+    pub fn generate_ed25519_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        Self {
+            private_key: PrivateKey::Ed25519(ed25519_dalek::SigningKey::generate(rng)),
+        }
+    }
+
+    pub(crate) fn from_private_key(private_key: PrivateKey) -> Self {
+        Self { private_key }
+    }
+
+    pub(crate) fn private_key(&self) -> &PrivateKey {
+        &self.private_key
+    }
+
+    /// Loads a private key from PEM. Passphrase-protected and plain PKCS#8,
+    /// PKCS#1 and OpenSSH (`ssh-keygen`) private keys are understood, holding
+    /// RSA or Ed25519 keys. The passphrase is ignored for unprotected keys,
+    /// and RSA keys under 2048 bits are refused.
+    pub fn from_private_key_pem(pem: &str, passphrase: &str) -> Result<Self> {
+        let pem = pem.trim();
+
+        let private_key = match pem_label(pem)? {
+            ENCRYPTED_PRIVATE_KEY_LABEL => {
+                let (_, der) = pkcs8::der::Document::from_pem(pem)?;
+                let encrypted = pkcs8::EncryptedPrivateKeyInfo::try_from(der.as_bytes())?;
+                let decrypted = encrypted
+                    .decrypt(passphrase)
+                    .map_err(|_| KeyFileError::WrongPassphrase)?;
+
+                PrivateKey::from_pkcs8_der(decrypted.as_bytes())?
+            }
+            "PRIVATE KEY" => {
+                let (_, der) = pkcs8::SecretDocument::from_pem(pem)?;
+                PrivateKey::from_pkcs8_der(der.as_bytes())?
+            }
+            "RSA PRIVATE KEY" => PrivateKey::Rsa(RsaPrivateKey::from_pkcs1_pem(pem)?),
+            "OPENSSH PRIVATE KEY" => decode_openssh_private_key(pem, passphrase)?,
+            _ => Err(KeyFileError::UnknownFormat)?,
+        };
+        check_key_size(&private_key.public_key())?;
+
+        Ok(Self { private_key })
+    }
+
+    /// Returns the private key as PKCS#8 PEM, encrypted with the passphrase
+    /// (PBKDF2-SHA256 and AES-256-CBC, which OpenSSL also reads).
+    #[cfg(feature = "std")]
+    pub fn to_private_key_pem(&self, passphrase: &str) -> Result<String> {
+        self.to_private_key_pem_with_rng(passphrase, &mut OsRng)
+    }
+
+    /// Like [`Self::to_private_key_pem`], but the salt and IV come from `rng`.
+    pub fn to_private_key_pem_with_rng<R: CryptoRng + RngCore>(
+        &self,
+        passphrase: &str,
+        rng: &mut R,
+    ) -> Result<String> {
+        let der = self.private_key.to_pkcs8_der()?;
+
+        let mut salt = [0u8; 16];
+        let mut iv = [0u8; 16];
+        rng.fill_bytes(&mut salt);
+        rng.fill_bytes(&mut iv);
+
+        let params =
+            pkcs8::pkcs5::pbes2::Parameters::pbkdf2_sha256_aes256cbc(PBKDF2_ITERATIONS, &salt, &iv)
+                .map_err(pkcs8::Error::from)?;
+        let encrypted = pkcs8::PrivateKeyInfo::try_from(der.as_bytes())?
+            .encrypt_with_params(params, passphrase)?;
+
+        Ok(encrypted
+            .to_pem(ENCRYPTED_PRIVATE_KEY_LABEL, pkcs8::LineEnding::LF)?
+            .to_string())
+    }
+
+    /// Returns the private key as plain PKCS#8 PEM, which
+    /// [`Self::from_private_key_pem`] loads quickly since there's no
+    /// passphrase to stretch. Anyone who has it can pose as this identity, so
+    /// use [`Self::to_private_key_pem`] for anything that gets stored.
+    pub fn dangerously_export_private_key_pem(&self) -> Result<SecretString> {
+        Ok(self
+            .private_key
+            .to_pkcs8_der()?
+            .to_pem("PRIVATE KEY", pkcs8::LineEnding::LF)?
+            .as_str()
+            .to_string()
+            .into())
+    }
+
+    pub fn get_pubkey_encoded(&self) -> Result<String> {
+        identity::encode_identity(&self.private_key, None)
+    }
+
+    /// Returns the public key as SPKI (`BEGIN PUBLIC KEY`) PEM.
+    pub fn get_pubkey_pem(&self) -> Result<String> {
+        encode_public_key_pem(&self.private_key.public_key())
+    }
+
+    /// Returns the public key as an OpenSSH `ssh-rsa` or `ssh-ed25519` line,
+    /// like a line of `authorized_keys`.
+    pub fn get_pubkey_openssh(&self) -> Result<String> {
+        encode_public_key_openssh(&self.private_key.public_key())
+    }
+
+    pub fn get_fingerprint(&self) -> Result<String> {
+        identity::fingerprint(&self.private_key.public_key())
+    }
+}
+
+pub(crate) fn encode_public_key_pem(pubkey: &PublicKey) -> Result<String> {
+    match pubkey {
+        PublicKey::Rsa(pubkey) => Ok(pubkey.to_public_key_pem(LineEnding::LF)?),
+        PublicKey::Ed25519(pubkey) => Ok(pkcs8::spki::EncodePublicKey::to_public_key_pem(
+            pubkey,
+            pkcs8::LineEnding::LF,
+        )?),
+    }
+}
+
+pub(crate) fn encode_public_key_openssh(pubkey: &PublicKey) -> Result<String> {
+    let key_data = match pubkey {
+        PublicKey::Rsa(pubkey) => KeyData::Rsa(ssh_key::public::RsaPublicKey {
+            e: Mpint::from_positive_bytes(&pubkey.e().to_bytes_be())?,
+            n: Mpint::from_positive_bytes(&pubkey.n().to_bytes_be())?,
+        }),
+        PublicKey::Ed25519(pubkey) => {
+            KeyData::Ed25519(ssh_key::public::Ed25519PublicKey(pubkey.to_bytes()))
+        }
+    };
+
+    Ok(ssh_key::PublicKey::new(key_data, OPENSSH_COMMENT).to_openssh()?)
+}
+
+/// Decodes a public key given as SPKI or PKCS#1 PEM, an OpenSSH line, or in
+/// this crate's own encoding, along with its expiry. Only this crate's own
+/// encoding can carry an expiry. RSA keys under 2048 bits are refused.
+pub(crate) fn decode_public_key(pubkey: &str) -> Result<(PublicKey, Option<u64>)> {
+    let (public_key, expires_at) = parse_public_key(pubkey)?;
+    check_key_size(&public_key)?;
+
+    Ok((public_key, expires_at))
+}
+
+fn parse_public_key(pubkey: &str) -> Result<(PublicKey, Option<u64>)> {
+    let pubkey = pubkey.trim();
+
+    if pubkey.starts_with("-----BEGIN") {
+        return match pem_label(pubkey)? {
+            "PUBLIC KEY" => match RsaPublicKey::from_public_key_pem(pubkey) {
+                Ok(pubkey) => Ok((PublicKey::Rsa(pubkey), None)),
+                Err(e) => Ok((
+                    PublicKey::Ed25519(
+                        pkcs8::spki::DecodePublicKey::from_public_key_pem(pubkey).map_err(|_| e)?,
+                    ),
+                    None,
+                )),
+            },
+            "RSA PUBLIC KEY" => Ok((PublicKey::Rsa(RsaPublicKey::from_pkcs1_pem(pubkey)?), None)),
+            _ => Err(KeyFileError::UnknownFormat)?,
+        };
+    }
+
+    if ["ssh-", "ecdsa-", "sk-"]
+        .iter()
+        .any(|prefix| pubkey.starts_with(prefix))
+    {
+        // other key types may not even parse, so they are refused by name
+        let algorithm = pubkey.split_whitespace().next().unwrap_or_default();
+        if !["ssh-rsa", "ssh-ed25519"].contains(&algorithm) {
+            Err(KeyFileError::UnsupportedKeyType(algorithm.to_string()))?;
+        }
+
+        return match ssh_key::PublicKey::from_openssh(pubkey)?.key_data() {
+            KeyData::Rsa(rsa) => Ok((
+                PublicKey::Rsa(RsaPublicKey::new(
+                    mpint_to_biguint(&rsa.n)?,
+                    mpint_to_biguint(&rsa.e)?,
+                )?),
+                None,
+            )),
+            KeyData::Ed25519(ed25519) => Ok((
+                PublicKey::Ed25519(
+                    ed25519_dalek::VerifyingKey::from_bytes(&ed25519.0)
+                        .map_err(|_| KeyFileError::UnknownFormat)?,
+                ),
+                None,
+            )),
+            key_data => Err(KeyFileError::UnsupportedKeyType(
+                key_data.algorithm().to_string(),
+            ))?,
+        };
+    }
+
+    identity::decode_identity_with_expiry(pubkey)
+}
+
+fn check_key_size(public_key: &PublicKey) -> Result<()> {
+    if let PublicKey::Rsa(public_key) = public_key {
+        let bits = public_key.n().bits();
+        if bits < MIN_RSA_BITS {
+            Err(KeyFileError::KeyTooSmall(bits))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn decode_openssh_private_key(pem: &str, passphrase: &str) -> Result<PrivateKey> {
+    let mut private_key = ssh_key::PrivateKey::from_openssh(pem)?;
+
+    if private_key.is_encrypted() {
+        private_key = private_key
+            .decrypt(passphrase)
+            .map_err(|_| KeyFileError::WrongPassphrase)?;
+    }
+
+    match private_key.key_data() {
+        KeypairData::Rsa(keypair) => Ok(PrivateKey::Rsa(RsaPrivateKey::from_components(
+            mpint_to_biguint(&keypair.public.n)?,
+            mpint_to_biguint(&keypair.public.e)?,
+            mpint_to_biguint(&keypair.private.d)?,
+            vec![
+                mpint_to_biguint(&keypair.private.p)?,
+                mpint_to_biguint(&keypair.private.q)?,
+            ],
+        )?)),
+        KeypairData::Ed25519(keypair) => {
+            let secret = Zeroizing::new(keypair.private.to_bytes());
+            let signing_key = ed25519_dalek::SigningKey::from_bytes(&secret);
+
+            // the public half is stored separately, so check it belongs
+            if signing_key.verifying_key().to_bytes() != keypair.public.0 {
+                Err(KeyFileError::UnknownFormat)?;
+            }
+
+            Ok(PrivateKey::Ed25519(signing_key))
+        }
+        _ => Err(KeyFileError::UnsupportedKeyType(
+            private_key.algorithm().to_string(),
+        ))?,
+    }
+}
+
+fn mpint_to_biguint(mpint: &Mpint) -> Result<BigUint> {
+    let bytes = mpint
+        .as_positive_bytes()
+        .ok_or(KeyFileError::UnknownFormat)?;
+
+    Ok(BigUint::from_bytes_be(bytes))
+}
+
+impl Debug for Identity {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Identity")
+            .field("fingerprint", &self.get_fingerprint().unwrap_or_default())
+            .finish_non_exhaustive()
+    }
+}
+
+fn pem_label(pem: &str) -> Result<&str> {
+    Ok(pem
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix("-----BEGIN "))
+        .and_then(|line| line.strip_suffix("-----"))
+        .ok_or(KeyFileError::UnknownFormat)?)
+}
+
+#[derive(Error, Debug)]
+pub enum KeyFileError {
+    UnknownFormat,
+    WrongPassphrase,
+    UnsupportedKeyType(String),
+    KeyTooSmall(usize),
+}
+
+impl Display for KeyFileError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            KeyFileError::UnknownFormat => write!(f, "unknown key format"),
+            KeyFileError::WrongPassphrase => write!(f, "wrong passphrase for private key"),
+            KeyFileError::UnsupportedKeyType(algorithm) => write!(
+                f,
+                "{algorithm} keys can't be used as identities, only RSA (ssh-rsa) and Ed25519 (ssh-ed25519) keys are supported"
+            ),
+            KeyFileError::KeyTooSmall(bits) => write!(
+                f,
+                "{bits}-bit RSA keys are too small to be identities, at least {MIN_RSA_BITS} bits are needed"
+            ),
+        }
+    }
+}
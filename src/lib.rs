@@ -1,227 +1,1168 @@
-use aes_gcm::{aead::Aead, Aes256Gcm, KeyInit, Nonce};
+#![no_std]
+// the encodings of handshake secrets are only used by the std-only state and
+// session exports
+#![cfg_attr(not(feature = "std"), allow(dead_code))]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use aes_gcm::{Aes256Gcm, KeyInit};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 use base64::Engine;
+use core::fmt::{Debug, Display};
+#[cfg(feature = "std")]
 use rand::rngs::OsRng;
-use rand::RngCore;
-use rsa::{
-    pkcs1::{DecodeRsaPublicKey, EncodeRsaPublicKey},
-    pkcs1v15::{Signature, SigningKey, VerifyingKey},
-    sha2::Sha256,
-    signature::{Signer, Verifier},
-    Pkcs1v15Encrypt, PublicKey, RsaPrivateKey, RsaPublicKey,
-};
-use std::fmt::Display;
+use rand::{CryptoRng, RngCore};
+use rsa::sha2::{Digest, Sha256};
 use thiserror::Error;
+use zeroize::Zeroizing;
+
+mod cipher;
+mod contacts;
+pub mod error;
+mod hybrid;
+mod identity;
+mod keyfile;
+mod mlkem;
+mod pake;
+mod passphrase;
+mod prekey;
+mod secret;
+#[cfg(feature = "std")]
+mod session;
+#[cfg(feature = "std")]
+mod state;
+
+use cipher::CipherError;
+use hybrid::{HybridError, HybridPublic, HybridSecrets};
+use identity::{IdentityError, PrivateKey, PublicKey};
+use passphrase::{PassphraseError, PassphraseKeys};
+use prekey::{PrekeyBundle, PrekeyError, PrekeySecrets};
+
+pub use cipher::CipherSuite;
+pub use contacts::{Contact, ContactBook, Trust, Verification};
+pub use error::{Error, Result};
+pub use keyfile::Identity;
+pub use pake::E2EOfflinePakeBuilder;
+pub use secret::SecretString;
+#[cfg(feature = "std")]
+pub use session::SessionExport;
+
+const PREKEY_TAG: &str = "x3dh";
+const HYBRID_TAG: &str = "hybrid";
+const REKEY_TAG: &str = "rekey";
+
+// stands in for secrets in Debug output
+const REDACTED: &str = "[REDACTED]";
 
+/// How many decrypts a key replaced by a rekey stays usable for.
+pub const REKEY_GRACE_MESSAGES: usize = 32;
+
+#[derive(Debug)]
 enum E2EType {
     Sender,
     Reciever,
 }
 
-pub struct E2EOfflineBuilder {
-    rng: OsRng,
-    reciever_public_key: Option<RsaPublicKey>,
-    sender_public_key: Option<RsaPublicKey>,
+/// How the reciever wants the shared key to be agreed on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HandshakeMode {
+    /// The sender wraps a random key with the reciever's identity key: RSA
+    /// encryption for an RSA key, or an X25519 exchange with the X25519 form
+    /// of an Ed25519 key.
+    #[default]
+    Classic,
+    /// The key is derived from both an ML-KEM-768 encapsulation and an X25519
+    /// exchange, so recorded traffic stays safe against a future quantum
+    /// computer as long as ML-KEM holds.
+    Hybrid,
+}
+
+/// The RNG used when none is passed in: the operating system's.
+#[cfg(feature = "std")]
+pub type DefaultRng = OsRng;
+
+/// Without std there is no RNG to fall back on, so one is always passed to the
+/// `_with_rng` constructors.
+#[cfg(not(feature = "std"))]
+pub enum DefaultRng {}
+
+pub struct E2EOfflineBuilder<R = DefaultRng> {
+    rng: R,
+    reciever_public_key: Option<PublicKey>,
+    sender_public_key: Option<PublicKey>,
+
+    my_private_key: PrivateKey,
+
+    shared_key: Option<Zeroizing<Vec<u8>>>,
+    cipher_suite: CipherSuite,
+
+    hello_nonce: Option<Vec<u8>>,
+
+    prekeys: Option<PrekeySecrets>,
+    prekey_bundle: Option<PrekeyBundle>,
 
-    my_private_key: RsaPrivateKey,
+    hybrid_secrets: Option<HybridSecrets>,
+    hybrid_public: Option<HybridPublic>,
 
-    shared_key: Option<Vec<u8>>,
-    aes: Option<Aes256Gcm>,
+    expires_at: Option<u64>,
+    other_expires_at: Option<u64>,
+    revoked: Vec<String>,
 
     my_type: E2EType,
 }
 
+#[cfg(feature = "std")]
 impl E2EOfflineBuilder {
     pub fn new_sender() -> Self {
-        let mut rng = OsRng;
-        let my_private_key = RsaPrivateKey::new(&mut rng, 1024).unwrap();
+        Self::new_sender_with_identity(&Identity::generate())
+    }
+
+    /// Starts a sender handshake with an existing identity, so the other side
+    /// sees the same public key every time.
+    pub fn new_sender_with_identity(identity: &Identity) -> Self {
+        Self::new_sender_with_rng(identity, OsRng)
+    }
+
+    pub fn new_reciever() -> Self {
+        Self::new_reciever_with_mode(HandshakeMode::Classic)
+    }
+
+    pub fn new_reciever_with_mode(mode: HandshakeMode) -> Self {
+        Self::new_reciever_with_identity(&Identity::generate(), mode)
+    }
+
+    /// Starts a reciever handshake with an existing identity. The identity can
+    /// be reused for any number of handshakes; each one still gets its own
+    /// hello nonce and shared key.
+    pub fn new_reciever_with_identity(identity: &Identity, mode: HandshakeMode) -> Self {
+        Self::new_reciever_with_rng(identity, mode, OsRng)
+    }
+}
+
+impl<R: CryptoRng + RngCore> E2EOfflineBuilder<R> {
+    /// Like [`E2EOfflineBuilder::new_sender_with_identity`], but everything
+    /// random in the handshake and the session it builds comes from `rng`.
+    /// Meant for reproducible tests with a seeded RNG.
+    pub fn new_sender_with_rng(identity: &Identity, mut rng: R) -> Self {
+        let my_private_key = identity.private_key().clone();
 
-        let shared_key = Aes256Gcm::generate_key(&mut rng);
+        let mut shared_key = Zeroizing::new(vec![0u8; 32]);
+        rng.fill_bytes(&mut shared_key);
 
         Self {
             rng,
             reciever_public_key: None,
-            sender_public_key: Some(RsaPublicKey::from(&my_private_key)),
+            sender_public_key: Some(my_private_key.public_key()),
             my_private_key,
-            shared_key: Some((&shared_key[..]).into()),
+            shared_key: Some(shared_key),
+            cipher_suite: CipherSuite::default(),
             my_type: E2EType::Sender,
-            aes: Some(Aes256Gcm::new(&shared_key)),
+            hello_nonce: None,
+            prekeys: None,
+            prekey_bundle: None,
+            hybrid_secrets: None,
+            hybrid_public: None,
+            expires_at: None,
+            other_expires_at: None,
+            revoked: Vec::new(),
         }
     }
 
-    pub fn new_reciever() -> Self {
-        let mut rng = rand::rngs::OsRng;
+    /// Like [`E2EOfflineBuilder::new_reciever_with_identity`], but everything
+    /// random comes from `rng`, as with [`Self::new_sender_with_rng`].
+    pub fn new_reciever_with_rng(identity: &Identity, mode: HandshakeMode, mut rng: R) -> Self {
+        let my_private_key = identity.private_key().clone();
 
-        let my_private_key = RsaPrivateKey::new(&mut rng, 1024).unwrap();
+        let mut hello_nonce = vec![0u8; 16];
+        rng.fill_bytes(&mut hello_nonce);
+
+        let hybrid_secrets = match mode {
+            HandshakeMode::Classic => None,
+            HandshakeMode::Hybrid => Some(HybridSecrets::new(&mut rng)),
+        };
 
         Self {
             rng,
-            reciever_public_key: Some(RsaPublicKey::from(&my_private_key)),
+            reciever_public_key: Some(my_private_key.public_key()),
             sender_public_key: None,
             my_private_key,
             shared_key: None,
-            aes: None,
+            cipher_suite: CipherSuite::default(),
+            hello_nonce: Some(hello_nonce),
+            prekeys: None,
+            prekey_bundle: None,
+            hybrid_secrets,
+            hybrid_public: None,
+            expires_at: None,
+            other_expires_at: None,
+            revoked: Vec::new(),
             my_type: E2EType::Reciever,
         }
     }
 
-    pub fn set_other_public_key_encoded(&mut self, pubkey: &str) -> anyhow::Result<()> {
-        let pubkey = pubkey.trim();
+    /// Sets the other party's public key.
+    ///
+    /// A reciever passes the sender's bare public key, which may also be SPKI
+    /// PEM or an OpenSSH `ssh-rsa` or `ssh-ed25519` line. A sender passes the
+    /// reciever's hello (see [`Self::get_hello_encoded`]), whose signature is
+    /// checked against the public key it carries before the key is accepted.
+    /// The hello also decides the [`HandshakeMode`] the sender will use.
+    pub fn set_other_public_key_encoded(&mut self, pubkey: &str) -> Result<()> {
+        let (pubkey, expires_at) = match self.my_type {
+            E2EType::Sender => {
+                let (pubkey, expires_at, nonce, hybrid_public) = verify_hello(pubkey)?;
+                self.hello_nonce.replace(nonce);
+                self.hybrid_public = hybrid_public;
+                (pubkey, expires_at)
+            }
+            E2EType::Reciever => decode_pubkey(pubkey)?,
+        };
 
-        let der = base64::engine::general_purpose::URL_SAFE
-            .decode(pubkey)
-            .expect("Failed to decode pubkey: ");
+        self.set_other_public_key(pubkey, expires_at)
+    }
 
-        let pubkey = RsaPublicKey::from_pkcs1_der(&der)?;
+    fn set_other_public_key(&mut self, pubkey: PublicKey, expires_at: Option<u64>) -> Result<()> {
+        if self.revoked.contains(&identity::fingerprint(&pubkey)?) {
+            Err(IdentityError::Revoked)?;
+        }
 
-        self.set_other_public_key(pubkey);
+        match self.my_type {
+            E2EType::Sender => self.reciever_public_key.replace(pubkey),
+            E2EType::Reciever => self.sender_public_key.replace(pubkey),
+        };
+        self.other_expires_at = expires_at;
 
         Ok(())
     }
 
-    fn set_other_public_key(&mut self, pubkey: RsaPublicKey) {
-        match self.my_type {
-            E2EType::Sender => self.reciever_public_key.replace(pubkey),
-            E2EType::Reciever => self.sender_public_key.replace(pubkey),
+    /// Includes an expiry date (in seconds since the Unix epoch) in this
+    /// side's public key. The other side refuses the key once it has passed.
+    pub fn set_expiry(&mut self, expires_at: u64) {
+        self.expires_at.replace(expires_at);
+    }
+
+    /// Picks the AEAD the conversation is encrypted with. Only the sender
+    /// picks; the choice is sent to the reciever in the key exchange text.
+    pub fn set_cipher_suite(&mut self, cipher_suite: CipherSuite) {
+        self.cipher_suite = cipher_suite;
+    }
+
+    pub fn get_pubkey_encoded(&self) -> Result<String> {
+        identity::encode_identity(&self.my_private_key, self.expires_at)
+    }
+
+    /// Returns this side's public key as SPKI PEM.
+    pub fn get_pubkey_pem(&self) -> Result<String> {
+        keyfile::encode_public_key_pem(&self.my_private_key.public_key())
+    }
+
+    /// Returns this side's public key as an OpenSSH `ssh-rsa` or
+    /// `ssh-ed25519` line.
+    pub fn get_pubkey_openssh(&self) -> Result<String> {
+        keyfile::encode_public_key_openssh(&self.my_private_key.public_key())
+    }
+
+    /// Returns this side's private key as PKCS#8 PEM, encrypted with the
+    /// passphrase.
+    #[cfg(feature = "std")]
+    pub fn get_private_key_pem(&self, passphrase: &str) -> Result<String> {
+        self.get_identity().to_private_key_pem(passphrase)
+    }
+
+    pub fn get_identity(&self) -> Identity {
+        Identity::from_private_key(self.my_private_key.clone())
+    }
+
+    /// Returns the fingerprint of this side's public key, for comparing in
+    /// person.
+    pub fn get_fingerprint(&self) -> Result<String> {
+        identity::fingerprint(&self.my_private_key.public_key())
+    }
+
+    /// Returns the fingerprint of the other side's public key, once it is set,
+    /// for checking against a [`ContactBook`].
+    pub fn get_other_fingerprint(&self) -> Result<String> {
+        let other_public_key = match self.my_type {
+            E2EType::Sender => self.reciever_public_key.as_ref(),
+            E2EType::Reciever => self.sender_public_key.as_ref(),
         };
+
+        identity::fingerprint(other_public_key.ok_or(IdentityError::MissingOtherKey)?)
     }
 
-    fn get_pubkey(&self) -> &RsaPublicKey {
-        match self.my_type {
-            E2EType::Sender => self.sender_public_key.as_ref().unwrap(),
-            E2EType::Reciever => self.reciever_public_key.as_ref().unwrap(),
+    /// Returns the expiry the other side's public key came with, if any, for
+    /// checking against a [`ContactBook`] along with its fingerprint.
+    pub fn get_other_expiry(&self) -> Option<u64> {
+        self.other_expires_at
+    }
+
+    /// Returns a certificate revoking this side's public key. It is meant to
+    /// be made in advance and kept safe, so the key can still be revoked after
+    /// the private key is lost.
+    pub fn get_revocation_certificate_encoded(&self) -> Result<String> {
+        identity::revocation_certificate(&self.my_private_key)
+    }
+
+    /// Checks a revocation certificate and refuses the key it revokes from
+    /// then on.
+    pub fn add_revocation_certificate_encoded(&mut self, certificate: &str) -> Result<()> {
+        let fingerprint = identity::verify_revocation_certificate(certificate)?;
+
+        if !self.revoked.contains(&fingerprint) {
+            self.revoked.push(fingerprint);
         }
+
+        Ok(())
+    }
+
+    /// Returns the reciever's hello: its public key and a fresh nonce, signed
+    /// with its private key. This is what the reciever gives to the sender
+    /// instead of a bare public key.
+    pub fn get_hello_encoded(&self) -> Result<String> {
+        let (E2EType::Reciever, Some(nonce)) = (&self.my_type, &self.hello_nonce) else {
+            Err(HelloError::NotReciever)?
+        };
+
+        let pubkey_encoded = self.get_pubkey_encoded()?;
+        let nonce_encoded = base64::engine::general_purpose::URL_SAFE.encode(nonce);
+
+        let signed_text = match &self.hybrid_secrets {
+            None => format!("{pubkey_encoded}.{nonce_encoded}"),
+            Some(hybrid_secrets) => format!(
+                "{HYBRID_TAG}.{pubkey_encoded}.{nonce_encoded}.{}",
+                hybrid_secrets.public_encoded()
+            ),
+        };
+
+        let signature = self.my_private_key.sign(signed_text.as_bytes());
+        let signature_encoded = base64::engine::general_purpose::URL_SAFE.encode(signature);
+
+        Ok(format!("{signed_text}.{signature_encoded}"))
+    }
+
+    /// Returns a publishable prekey bundle for the reciever, adding
+    /// `one_time_prekeys` fresh one-time prekeys to it. A sender holding the
+    /// bundle can send its exchange text without waiting for a hello.
+    pub fn get_prekey_bundle_encoded(&mut self, one_time_prekeys: usize) -> Result<String> {
+        let E2EType::Reciever = self.my_type else {
+            Err(PrekeyError::NotReciever)?
+        };
+
+        let pubkey_encoded = self.get_pubkey_encoded()?;
+
+        let prekeys = self
+            .prekeys
+            .get_or_insert_with(|| PrekeySecrets::new(&mut self.rng));
+        prekeys.generate_one_time_prekeys(&mut self.rng, one_time_prekeys);
+
+        let signed_text = format!(
+            "{pubkey_encoded}.{}.{}",
+            prekeys.signed_prekey_encoded(),
+            prekeys.one_time_prekeys_encoded()
+        );
+
+        let signature = self.my_private_key.sign(signed_text.as_bytes());
+        let signature_encoded = base64::engine::general_purpose::URL_SAFE.encode(signature);
+
+        Ok(format!("{signed_text}.{signature_encoded}"))
+    }
+
+    /// Returns the ids of the one-time prekeys that senders have consumed.
+    pub fn used_one_time_prekeys(&self) -> &[u32] {
+        self.prekeys
+            .as_ref()
+            .map(PrekeySecrets::used_one_time_prekeys)
+            .unwrap_or_default()
     }
 
-    pub fn get_pubkey_encoded(&self) -> anyhow::Result<String> {
-        let der = self.get_pubkey().to_pkcs1_der()?;
+    /// Sets the reciever's prekey bundle in place of its hello. The bundle's
+    /// signature is checked against the identity key it carries.
+    pub fn set_prekey_bundle_encoded(&mut self, bundle: &str) -> Result<()> {
+        let E2EType::Sender = self.my_type else {
+            Err(PrekeyError::NotSender)?
+        };
+
+        let mut bundle = bundle.trim().split('.');
+
+        let (pubkey_encoded, signed_prekey, one_time_prekeys, signature_encoded) = (
+            bundle.next().ok_or(PrekeyError::InvalidBundle)?,
+            bundle.next().ok_or(PrekeyError::InvalidBundle)?,
+            bundle.next().ok_or(PrekeyError::InvalidBundle)?,
+            bundle.next().ok_or(PrekeyError::InvalidBundle)?,
+        );
+
+        let (pubkey, expires_at) = decode_pubkey(pubkey_encoded)?;
+        let signature = base64::engine::general_purpose::URL_SAFE.decode(signature_encoded)?;
 
-        Ok(base64::engine::general_purpose::URL_SAFE.encode(der.as_bytes()))
+        if !pubkey.verify(
+            format!("{pubkey_encoded}.{signed_prekey}.{one_time_prekeys}").as_bytes(),
+            &signature,
+        ) {
+            Err(PrekeyError::FailedSignatureCheck)?;
+        }
+
+        self.prekey_bundle
+            .replace(PrekeyBundle::decode(signed_prekey, one_time_prekeys)?);
+        self.set_other_public_key(pubkey, expires_at)
     }
 
-    pub fn send(&mut self) -> anyhow::Result<String> {
-        let signing_key = SigningKey::<Sha256>::new(self.my_private_key.clone());
+    pub fn send(&mut self) -> Result<String> {
+        if self.prekey_bundle.is_some() {
+            return self.send_prekey();
+        }
+        if self.hybrid_public.is_some() {
+            return self.send_hybrid();
+        }
+
+        let reciever_public_key = self
+            .reciever_public_key
+            .as_ref()
+            .ok_or(IdentityError::MissingOtherKey)?;
+        let shared_key = self.shared_key.as_ref().ok_or(RecieveError::NotRecieved)?;
 
-        let shared_key_encrypted = self.reciever_public_key.as_ref().unwrap().encrypt(
-            &mut self.rng,
-            Pkcs1v15Encrypt,
-            &self.shared_key.as_ref().unwrap()[..],
-        )?;
+        let shared_key_encrypted = reciever_public_key.wrap_key(&mut self.rng, shared_key)?;
 
-        let ske_encoded = base64::engine::general_purpose::URL_SAFE.encode(shared_key_encrypted);
+        let ske_encoded = format!(
+            "{}{}",
+            self.cipher_suite.prefix(),
+            base64::engine::general_purpose::URL_SAFE.encode(shared_key_encrypted)
+        );
 
-        let signature = signing_key.sign(ske_encoded.as_bytes());
+        let signature = self
+            .my_private_key
+            .sign(self.signed_exchange_text(&ske_encoded)?.as_bytes());
         let signature_encoded = base64::engine::general_purpose::URL_SAFE.encode(signature);
 
         Ok(format!("{ske_encoded}.{signature_encoded}"))
     }
 
-    pub fn recieve(&mut self, message: &str) -> anyhow::Result<()> {
-        let mut message = message.trim().split('.');
+    // derives the shared key from the reciever's prekey bundle rather than
+    // wrapping the pre-generated one
+    fn send_prekey(&mut self) -> Result<String> {
+        let bundle = self.prekey_bundle.as_mut().unwrap();
+
+        let (ephemeral, one_time_prekey_id, shared_key) = bundle.agree(&mut self.rng);
+
+        let signed_text = format!(
+            "{}{PREKEY_TAG}.{}.{}.{}",
+            self.cipher_suite.prefix(),
+            prekey::encode_key(&ephemeral),
+            one_time_prekey_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+            bundle.signed_prekey_encoded(),
+        );
+
+        let signature = self.my_private_key.sign(signed_text.as_bytes());
+        let signature_encoded = base64::engine::general_purpose::URL_SAFE.encode(signature);
+
+        self.shared_key.replace(Zeroizing::new(shared_key.to_vec()));
+
+        // the signed prekey is implied by the bundle, so it isn't sent
+        let (exchange_text, _) = signed_text.rsplit_once('.').unwrap();
+
+        Ok(format!("{exchange_text}.{signature_encoded}"))
+    }
+
+    fn send_hybrid(&mut self) -> Result<String> {
+        let (ciphertext, ephemeral, shared_key) =
+            self.hybrid_public.as_ref().unwrap().agree(&mut self.rng);
 
-        let (ske_encoded, signature_encoded) = (
-            message.next().ok_or(RecieveError::InvalidString)?,
-            message.next().ok_or(RecieveError::InvalidString)?,
+        let exchange_text = format!(
+            "{}{HYBRID_TAG}.{ciphertext}.{ephemeral}",
+            self.cipher_suite.prefix()
         );
 
+        let signature = self
+            .my_private_key
+            .sign(self.signed_exchange_text(&exchange_text)?.as_bytes());
+        let signature_encoded = base64::engine::general_purpose::URL_SAFE.encode(signature);
+
+        self.shared_key.replace(Zeroizing::new(shared_key.to_vec()));
+
+        Ok(format!("{exchange_text}.{signature_encoded}"))
+    }
+
+    pub fn recieve(&mut self, message: &str) -> Result<()> {
+        // the sender's cipher suite is named in front of the rest, and is
+        // covered by the signature along with it
+        let (cipher_suite, exchange) = CipherSuite::split_prefix(message.trim());
+
+        if exchange.starts_with(&format!("{HYBRID_TAG}.")) {
+            self.recieve_hybrid(message)?;
+        } else if exchange.starts_with(&format!("{PREKEY_TAG}.")) {
+            // only possible against a bundle the reciever chose to publish
+            self.recieve_prekey(message, cipher_suite)?;
+        } else if self.hybrid_secrets.is_some() {
+            // a hybrid reciever never falls back to a classic exchange, or
+            // the post-quantum half could simply be stripped off
+            Err(HybridError::NotHybrid)?;
+        } else {
+            self.recieve_classic(message)?;
+        }
+
+        // only take the suite once the signature over it has checked out
+        self.cipher_suite = cipher_suite;
+
+        Ok(())
+    }
+
+    fn recieve_classic(&mut self, message: &str) -> Result<()> {
+        let (ske_text, signature_encoded) = message
+            .trim()
+            .rsplit_once('.')
+            .ok_or(RecieveError::InvalidString)?;
+        let ske_encoded = CipherSuite::split_prefix(ske_text).1;
+
         let signature = base64::engine::general_purpose::URL_SAFE.decode(signature_encoded)?;
 
-        let verifier =
-            VerifyingKey::<Sha256>::from(self.sender_public_key.as_ref().unwrap().clone());
+        let sender_public_key = self
+            .sender_public_key
+            .as_ref()
+            .ok_or(IdentityError::MissingOtherKey)?;
 
-        if verifier
-            .verify(
-                ske_encoded.as_bytes(),
-                &Signature::from(signature.into_boxed_slice()),
-            )
-            .is_err()
-        {
+        if !sender_public_key.verify(self.signed_exchange_text(ske_text)?.as_bytes(), &signature) {
             Err(RecieveError::FailedSignatureCheck)?;
         }
 
         let shared_key_encrypted = base64::engine::general_purpose::URL_SAFE.decode(ske_encoded)?;
 
-        let shared_key = self
-            .my_private_key
-            .decrypt(Pkcs1v15Encrypt, &shared_key_encrypted[..])?;
+        let shared_key = self.my_private_key.unwrap_key(&shared_key_encrypted)?;
 
-        self.aes.replace(Aes256Gcm::new_from_slice(&shared_key)?);
+        // the decrypted key could be any length, so check it's a usable key
+        Aes256Gcm::new_from_slice(&shared_key)?;
 
         self.shared_key.replace(shared_key);
 
         Ok(())
     }
 
-    pub fn get_shared_key(&self) -> anyhow::Result<String> {
-        Ok(base64::engine::general_purpose::URL_SAFE.encode(self.shared_key.as_ref().unwrap()))
+    fn recieve_prekey(&mut self, message: &str, cipher_suite: CipherSuite) -> Result<()> {
+        let mut message = CipherSuite::split_prefix(message.trim())
+            .1
+            .split('.')
+            .skip(1);
+
+        let (ephemeral_encoded, one_time_prekey_id, signature_encoded) = (
+            message.next().ok_or(PrekeyError::InvalidString)?,
+            message.next().ok_or(PrekeyError::InvalidString)?,
+            message.next().ok_or(PrekeyError::InvalidString)?,
+        );
+
+        let prekeys = self.prekeys.as_mut().ok_or(PrekeyError::NoPrekeys)?;
+
+        let signature = base64::engine::general_purpose::URL_SAFE.decode(signature_encoded)?;
+
+        let sender_public_key = self
+            .sender_public_key
+            .as_ref()
+            .ok_or(IdentityError::MissingOtherKey)?;
+
+        if !sender_public_key.verify(
+            format!(
+                "{}{PREKEY_TAG}.{ephemeral_encoded}.{one_time_prekey_id}.{}",
+                cipher_suite.prefix(),
+                prekeys.signed_prekey_encoded()
+            )
+            .as_bytes(),
+            &signature,
+        ) {
+            Err(PrekeyError::FailedSignatureCheck)?;
+        }
+
+        let ephemeral = prekey::decode_key(ephemeral_encoded)?;
+        let one_time_prekey_id = match one_time_prekey_id {
+            "" => None,
+            id => Some(id.parse()?),
+        };
+
+        let shared_key = prekeys.agree(&ephemeral, one_time_prekey_id)?;
+
+        self.shared_key.replace(Zeroizing::new(shared_key.to_vec()));
+
+        Ok(())
+    }
+
+    fn recieve_hybrid(&mut self, message: &str) -> Result<()> {
+        let (exchange_text, signature_encoded) = message
+            .trim()
+            .rsplit_once('.')
+            .ok_or(HybridError::InvalidString)?;
+
+        let mut parts = CipherSuite::split_prefix(exchange_text)
+            .1
+            .split('.')
+            .skip(1);
+        let (ciphertext, ephemeral) = (
+            parts.next().ok_or(HybridError::InvalidString)?,
+            parts.next().ok_or(HybridError::InvalidString)?,
+        );
+
+        let hybrid_secrets = self
+            .hybrid_secrets
+            .as_ref()
+            .ok_or(HybridError::MissingKeys)?;
+
+        let signature = base64::engine::general_purpose::URL_SAFE.decode(signature_encoded)?;
+
+        let sender_public_key = self
+            .sender_public_key
+            .as_ref()
+            .ok_or(IdentityError::MissingOtherKey)?;
+
+        if !sender_public_key.verify(
+            self.signed_exchange_text(exchange_text)?.as_bytes(),
+            &signature,
+        ) {
+            Err(RecieveError::FailedSignatureCheck)?;
+        }
+
+        let shared_key = hybrid_secrets.agree(ciphertext, ephemeral)?;
+
+        self.shared_key.replace(Zeroizing::new(shared_key.to_vec()));
+
+        Ok(())
+    }
+
+    // the exchange text is signed together with the reciever's hello nonce so
+    // that it can't be replayed against a different hello
+    fn signed_exchange_text(&self, ske_encoded: &str) -> Result<String> {
+        let nonce = self.hello_nonce.as_ref().ok_or(HelloError::MissingHello)?;
+        let nonce_encoded = base64::engine::general_purpose::URL_SAFE.encode(nonce);
+
+        Ok(format!("{ske_encoded}.{nonce_encoded}"))
     }
 
-    pub fn build(self) -> anyhow::Result<E2EOffline> {
+    /// Finishes the handshake, returning the session. The shared key stays
+    /// inside it; see [`E2EOffline::dangerously_export_raw_key`] if it really
+    /// has to be taken out.
+    pub fn build(self) -> Result<E2EOffline<R>> {
+        let other_public_key = match self.my_type {
+            E2EType::Sender => self.reciever_public_key,
+            E2EType::Reciever => self.sender_public_key,
+        };
+
         Ok(E2EOffline {
-            aes: self.aes.unwrap(),
-            rng: self.rng,
+            cipher_suite: self.cipher_suite,
+            my_private_key: Some(self.my_private_key),
+            other_public_key,
+            ..E2EOffline::new(
+                self.shared_key.as_ref().ok_or(RecieveError::NotRecieved)?,
+                self.rng,
+            )?
         })
     }
 }
 
-pub struct E2EOffline {
-    aes: Aes256Gcm,
-    rng: OsRng,
+impl<R> Debug for E2EOfflineBuilder<R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("E2EOfflineBuilder")
+            .field("my_type", &self.my_type)
+            .field(
+                "reciever_public_key",
+                &self.reciever_public_key.as_ref().map(debug_fingerprint),
+            )
+            .field(
+                "sender_public_key",
+                &self.sender_public_key.as_ref().map(debug_fingerprint),
+            )
+            .field("my_private_key", &REDACTED)
+            .field("shared_key", &self.shared_key.as_ref().map(|_| REDACTED))
+            .field("cipher_suite", &self.cipher_suite)
+            .field("expires_at", &self.expires_at)
+            .field("other_expires_at", &self.other_expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
+fn decode_pubkey(pubkey: &str) -> Result<(PublicKey, Option<u64>)> {
+    keyfile::decode_public_key(pubkey)
+}
+
+#[allow(clippy::type_complexity)]
+fn verify_hello(hello: &str) -> Result<(PublicKey, Option<u64>, Vec<u8>, Option<HybridPublic>)> {
+    let (signed_text, signature_encoded) = hello
+        .trim()
+        .rsplit_once('.')
+        .ok_or(HelloError::InvalidString)?;
+
+    let is_hybrid = signed_text.starts_with(&format!("{HYBRID_TAG}."));
+
+    let mut hello = signed_text.split('.').skip(is_hybrid as usize);
+
+    let (pubkey_encoded, nonce_encoded) = (
+        hello.next().ok_or(HelloError::InvalidString)?,
+        hello.next().ok_or(HelloError::InvalidString)?,
+    );
+
+    let (pubkey, expires_at) = decode_pubkey(pubkey_encoded)?;
+    let signature = base64::engine::general_purpose::URL_SAFE.decode(signature_encoded)?;
+
+    if !pubkey.verify(signed_text.as_bytes(), &signature) {
+        Err(HelloError::FailedSignatureCheck)?;
+    }
+
+    let nonce = base64::engine::general_purpose::URL_SAFE.decode(nonce_encoded)?;
+
+    let hybrid_public = match is_hybrid {
+        false => None,
+        true => Some(HybridPublic::decode(
+            hello.next().ok_or(HelloError::InvalidString)?,
+            hello.next().ok_or(HelloError::InvalidString)?,
+        )?),
+    };
+
+    Ok((pubkey, expires_at, nonce, hybrid_public))
+}
+
+/// A conversation under a shared key, from a handshake, a passphrase or an
+/// imported session. The key stays inside; it is only handed out by
+/// [`Self::dangerously_export_raw_key`].
+pub struct E2EOffline<R = DefaultRng> {
+    key: Zeroizing<Vec<u8>>,
+    cipher_suite: CipherSuite,
+    rng: R,
+
+    passphrase: Option<PassphraseKeys>,
+
+    // keys replaced by a rekey, each with how many more decrypts it is kept for
+    previous_keys: Vec<(Zeroizing<Vec<u8>>, usize)>,
+    rekeyed: bool,
+
+    // what rekey messages are signed along with, so one can't be replayed
+    // into another session or applied twice
+    session_id: [u8; 32],
+    rekey_sequence: u64,
+    other_rekey_sequence: u64,
+    // the key this side's last rekey switched to, for settling on one key when
+    // both sides rekey at once
+    my_rekey_key: Option<Zeroizing<Vec<u8>>>,
+
+    my_private_key: Option<PrivateKey>,
+    other_public_key: Option<PublicKey>,
+}
+
+#[cfg(feature = "std")]
+impl E2EOffline {
+    /// Starts a session from a raw key, such as one from
+    /// [`Self::dangerously_export_raw_key`].
+    pub fn from_key_base64(key: &str) -> Result<E2EOffline> {
+        Self::from_key_base64_with_rng(key, OsRng)
+    }
+
+    /// Derives the key from a passphrase both users already share, with no
+    /// handshake at all. The key is stretched with Argon2id under a random
+    /// salt that is sent along with every ciphertext.
+    pub fn from_passphrase(passphrase: &str) -> Result<E2EOffline> {
+        Self::from_passphrase_with_rng(passphrase, OsRng)
+    }
 }
 
 impl E2EOffline {
-    pub fn from_key_base64(key: &str) -> anyhow::Result<E2EOffline> {
-        let rng = OsRng;
+    pub fn is_rekey_message(message: &str) -> bool {
+        message.trim().starts_with(&format!("{REKEY_TAG}."))
+    }
+}
+
+impl<R: CryptoRng + RngCore> E2EOffline<R> {
+    /// Like [`E2EOffline::from_key_base64`], but nonces and rekeys come from
+    /// `rng`. Meant for reproducible tests with a seeded RNG.
+    pub fn from_key_base64_with_rng(key: &str, rng: R) -> Result<Self> {
+        let key = Zeroizing::new(base64::engine::general_purpose::URL_SAFE.decode(key.trim())?);
+
+        Self::new(&key, rng)
+    }
+
+    /// Like [`E2EOffline::from_passphrase`], but the salt, nonces and rekeys
+    /// come from `rng`.
+    pub fn from_passphrase_with_rng(passphrase: &str, mut rng: R) -> Result<Self> {
+        let (passphrase, key) = PassphraseKeys::new(passphrase, &mut rng)?;
+
+        Ok(Self {
+            passphrase: Some(passphrase),
+            ..Self::new(&*key, rng)?
+        })
+    }
+
+    /// Like [`E2EOffline::from_passphrase`], but under the salt `ciphertext`
+    /// was encrypted with, so decrypting it only stretches the passphrase
+    /// once.
+    pub(crate) fn from_passphrase_for_ciphertext(
+        passphrase: &str,
+        ciphertext: &str,
+        rng: R,
+    ) -> Result<Self> {
+        let (_, ciphertext) = CipherSuite::split_prefix(ciphertext.trim());
+
+        let [salt, _, _] = ciphertext.split('.').collect::<Vec<_>>()[..] else {
+            Err(PassphraseError::NotPassphrase)?
+        };
+        let (passphrase, key) = PassphraseKeys::with_salt(passphrase, salt)?;
+
+        Ok(Self {
+            passphrase: Some(passphrase),
+            ..Self::new(&*key, rng)?
+        })
+    }
+
+    /// Returns the current key as base64, for [`Self::from_key_base64`].
+    /// Anyone who has it can read and forge messages until the next rekey, so
+    /// prefer [`Self::export_session`], which is passphrase protected, for
+    /// moving a conversation elsewhere.
+    pub fn dangerously_export_raw_key(&self) -> SecretString {
+        base64::engine::general_purpose::URL_SAFE
+            .encode(&self.key)
+            .into()
+    }
 
-        let key = base64::engine::general_purpose::URL_SAFE.decode(key.trim())?;
-        let aes = Aes256Gcm::new_from_slice(&key)?;
+    fn new(key: &[u8], rng: R) -> Result<Self> {
+        // checks the key length once, so ciphers made from it later can't fail
+        Aes256Gcm::new_from_slice(key)?;
 
-        Ok(Self { aes, rng })
+        Ok(Self {
+            key: Zeroizing::new(key.to_vec()),
+            cipher_suite: CipherSuite::default(),
+            rng,
+            passphrase: None,
+            previous_keys: Vec::new(),
+            rekeyed: false,
+            session_id: Sha256::new()
+                .chain_update(b"E2EOffline session ")
+                .chain_update(key)
+                .finalize()
+                .into(),
+            rekey_sequence: 0,
+            other_rekey_sequence: 0,
+            my_rekey_key: None,
+            my_private_key: None,
+            other_public_key: None,
+        })
     }
 
-    pub fn encrypt(&mut self, plaintext: &str) -> anyhow::Result<String> {
-        let mut nonce = [0u8; 12];
-        self.rng.fill_bytes(&mut nonce);
+    /// The AEAD this side encrypts with.
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite
+    }
 
-        let nonce = Nonce::from_mut_slice(&mut nonce);
-        let nonce_encoded = base64::engine::general_purpose::URL_SAFE.encode(&nonce);
+    /// Picks the AEAD for a session made from a raw key, which doesn't carry
+    /// one. Both sides need the same suite, as ciphertexts in any other are
+    /// refused.
+    pub fn set_cipher_suite(&mut self, cipher_suite: CipherSuite) {
+        self.cipher_suite = cipher_suite;
+    }
 
-        let ciphertext = self.aes.encrypt(nonce, plaintext.as_bytes()).unwrap();
+    pub fn encrypt(&mut self, plaintext: &str) -> Result<String> {
+        self.encrypt_bytes(plaintext.as_bytes())
+    }
+
+    fn encrypt_bytes(&mut self, plaintext: &[u8]) -> Result<String> {
+        let (nonce, ciphertext) = self
+            .cipher_suite
+            .encrypt(&self.key, &mut self.rng, plaintext)?;
 
+        let nonce_encoded = base64::engine::general_purpose::URL_SAFE.encode(nonce);
         let ciphertext = base64::engine::general_purpose::URL_SAFE.encode(ciphertext);
 
-        Ok(format!("{nonce_encoded}.{ciphertext}"))
+        let prefix = self.cipher_suite.prefix();
+
+        match (&self.passphrase, self.rekeyed) {
+            (Some(passphrase), false) => Ok(format!(
+                "{prefix}{}.{nonce_encoded}.{ciphertext}",
+                passphrase.salt_encoded()
+            )),
+            _ => Ok(format!("{prefix}{nonce_encoded}.{ciphertext}")),
+        }
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &str) -> Result<SecretString> {
+        let result = self.decrypt_bytes(ciphertext)?;
+
+        // borrowing rather than moving, so an invalid message isn't kept in the error
+        let s = core::str::from_utf8(&result)?;
+        Ok(s.to_string().into())
     }
 
-    pub fn decrypt(&mut self, ciphertext: &str) -> anyhow::Result<String> {
-        let mut ciphertext = ciphertext.trim().split('.');
+    fn decrypt_bytes(&mut self, ciphertext: &str) -> Result<Zeroizing<Vec<u8>>> {
+        // a passphrase conversation works whichever suite each side uses, but
+        // a session agreed in a handshake only takes its own suite
+        let (cipher_suite, ciphertext) = CipherSuite::split_prefix(ciphertext.trim());
+        if self.passphrase.is_none() && cipher_suite != self.cipher_suite {
+            Err(CipherError::WrongSuite(cipher_suite))?;
+        }
+
+        // passphrase ciphertexts carry their salt as an extra leading part
+        let (salt, ciphertext) = match ciphertext.split('.').count() {
+            3 => {
+                let (salt, ciphertext) = ciphertext.split_once('.').unwrap();
+                (Some(salt), ciphertext)
+            }
+            _ => (None, ciphertext),
+        };
+
+        let mut ciphertext = ciphertext.split('.');
         let (nonce, ciphertext) = (
             ciphertext.next().ok_or(RecieveError::InvalidString)?,
             ciphertext.next().ok_or(RecieveError::InvalidString)?,
         );
 
         let nonce = base64::engine::general_purpose::URL_SAFE.decode(nonce)?;
-        let nonce = Nonce::from_slice(&nonce);
 
         let ciphertext = base64::engine::general_purpose::URL_SAFE.decode(ciphertext)?;
 
-        let result = self.aes.decrypt(nonce, &ciphertext[..]).unwrap();
+        let result = match salt {
+            Some(salt) => {
+                let passphrase = self
+                    .passphrase
+                    .as_mut()
+                    .ok_or(PassphraseError::NotPassphrase)?;
+
+                let result =
+                    cipher_suite.decrypt(passphrase.key_for_salt(salt)?, &nonce, &ciphertext);
+                if result.is_some() {
+                    passphrase.pin_salt(salt);
+                }
+                result
+            }
+            // late messages may still be under a key from before a rekey
+            None => core::iter::once(&self.key)
+                .chain(self.previous_keys.iter().map(|(key, _)| key))
+                .enumerate()
+                .find_map(|(i, key)| Some((i, cipher_suite.decrypt(key, &nonce, &ciphertext)?)))
+                .map(|(i, plaintext)| {
+                    self.count_grace_message(i.checked_sub(1));
+                    plaintext
+                }),
+        };
+
+        Ok(Zeroizing::new(
+            result.ok_or(RecieveError::FailedDecryption)?,
+        ))
+    }
+
+    // a message that decrypted uses up one of the grace messages of every
+    // previous key it wasn't under; ones that didn't decrypt use up nothing,
+    // so they can't push out a key that late messages still need
+    fn count_grace_message(&mut self, used: Option<usize>) {
+        for (i, (_, remaining)) in self.previous_keys.iter_mut().enumerate() {
+            if Some(i) != used {
+                *remaining = remaining.saturating_sub(1);
+            }
+        }
+        self.previous_keys.retain(|(_, remaining)| *remaining > 0);
+    }
+
+    /// Switches to a fresh key and returns a rekey message carrying it,
+    /// encrypted under the current key. When this side has an identity key
+    /// (i.e. it came from [`E2EOfflineBuilder::build`]) the new key is signed
+    /// with it. The old key is kept for [`REKEY_GRACE_MESSAGES`] more decrypts
+    /// so messages already in flight can still be read.
+    ///
+    /// Each rekey message carries this side's rekey count and the last count
+    /// it saw from the other side, so [`Self::apply_rekey`] can refuse one
+    /// that is replayed and tell when both sides rekeyed at once.
+    pub fn rekey(&mut self) -> Result<String> {
+        let mut new_key = Zeroizing::new(vec![0u8; 32]);
+        self.rng.fill_bytes(&mut new_key);
+        let new_key_encoded =
+            Zeroizing::new(base64::engine::general_purpose::URL_SAFE.encode(&new_key));
+
+        let sequence = self.rekey_sequence + 1;
+        let rekey_text = Zeroizing::new(format!(
+            "{sequence}.{}.{}",
+            self.other_rekey_sequence, *new_key_encoded
+        ));
+
+        let payload = match &self.my_private_key {
+            Some(my_private_key) => {
+                let signature = my_private_key.sign(self.signed_rekey_text(&rekey_text).as_bytes());
+                let signature_encoded = base64::engine::general_purpose::URL_SAFE.encode(signature);
+
+                Zeroizing::new(format!("{}.{signature_encoded}", *rekey_text))
+            }
+            None => rekey_text,
+        };
+
+        let message = self.encrypt_bytes(payload.as_bytes())?;
+
+        self.switch_key(&new_key)?;
+        self.rekey_sequence = sequence;
+        self.my_rekey_key.replace(new_key);
+
+        Ok(format!("{REKEY_TAG}.{message}"))
+    }
+
+    /// Switches to the key carried by the other side's rekey message. If the
+    /// other side's identity key is known the message must be signed by it.
+    /// A rekey message that is older than one already applied, or the same
+    /// one again, is refused.
+    ///
+    /// If both sides rekeyed at once, each one's message arrives after it has
+    /// already switched to its own new key. Both sides then keep whichever of
+    /// the two new keys sorts first by hash, and keep the other for its grace
+    /// period, so they still end up on the same key.
+    pub fn apply_rekey(&mut self, message: &str) -> Result<()> {
+        let message = message
+            .trim()
+            .strip_prefix(&format!("{REKEY_TAG}."))
+            .ok_or(RekeyError::NotRekey)?;
+
+        let payload = self.decrypt_bytes(message)?;
+        let payload = core::str::from_utf8(&payload)?;
+
+        let mut parts = payload.split('.');
+        let (Some(sequence), Some(seen), Some(new_key_encoded)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            Err(RekeyError::InvalidString)?
+        };
+        let signature_encoded = parts.next();
+
+        if let Some(other_public_key) = &self.other_public_key {
+            let signature = base64::engine::general_purpose::URL_SAFE
+                .decode(signature_encoded.ok_or(RekeyError::MissingSignature)?)?;
+
+            if !other_public_key.verify(
+                self.signed_rekey_text(&format!("{sequence}.{seen}.{new_key_encoded}"))
+                    .as_bytes(),
+                &signature,
+            ) {
+                Err(RekeyError::FailedSignatureCheck)?;
+            }
+        }
+
+        let (sequence, seen): (u64, u64) = (sequence.parse()?, seen.parse()?);
+
+        if sequence <= self.other_rekey_sequence || seen > self.rekey_sequence {
+            Err(RekeyError::Stale)?;
+        }
+
+        let new_key =
+            Zeroizing::new(base64::engine::general_purpose::URL_SAFE.decode(new_key_encoded)?);
+        Aes256Gcm::new_from_slice(&new_key)?;
+
+        self.other_rekey_sequence = sequence;
+
+        // the other side hadn't seen this side's last rekey when it sent this
+        if let (true, Some(my_rekey_key)) = (seen < self.rekey_sequence, &self.my_rekey_key) {
+            if Sha256::digest(my_rekey_key) < Sha256::digest(&new_key) {
+                self.previous_keys.push((new_key, REKEY_GRACE_MESSAGES));
+                return Ok(());
+            }
+        }
+
+        self.switch_key(&new_key)
+    }
+
+    // rekeys are signed along with the session they belong to
+    fn signed_rekey_text(&self, rekey_text: &str) -> String {
+        format!(
+            "{REKEY_TAG}.{}.{rekey_text}",
+            base64::engine::general_purpose::URL_SAFE.encode(self.session_id)
+        )
+    }
+
+    fn switch_key(&mut self, key: &[u8]) -> Result<()> {
+        Aes256Gcm::new_from_slice(key)?;
+
+        let old = core::mem::replace(&mut self.key, Zeroizing::new(key.to_vec()));
+
+        self.previous_keys.push((old, REKEY_GRACE_MESSAGES));
+        self.rekeyed = true;
+
+        Ok(())
+    }
+}
 
-        let s = String::from_utf8(result)?;
-        Ok(s)
+impl<R> Debug for E2EOffline<R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("E2EOffline")
+            .field("key", &REDACTED)
+            .field("cipher_suite", &self.cipher_suite)
+            .field("passphrase", &self.passphrase.as_ref().map(|_| REDACTED))
+            .field("previous_keys", &self.previous_keys.len())
+            .field("rekeyed", &self.rekeyed)
+            .field("rekey_sequence", &self.rekey_sequence)
+            .field("other_rekey_sequence", &self.other_rekey_sequence)
+            .field(
+                "my_private_key",
+                &self.my_private_key.as_ref().map(|_| REDACTED),
+            )
+            .field(
+                "other_public_key",
+                &self.other_public_key.as_ref().map(debug_fingerprint),
+            )
+            .finish_non_exhaustive()
     }
 }
 
+// public keys are shown by fingerprint in Debug output, as they're long and
+// the fingerprint is what users compare
+fn debug_fingerprint(pubkey: &PublicKey) -> String {
+    identity::fingerprint(pubkey).unwrap_or_default()
+}
+
 #[derive(Error, Debug)]
-enum RecieveError {
+pub enum RecieveError {
     InvalidString,
     FailedSignatureCheck,
+    FailedDecryption,
+    NotRecieved,
 }
 
 impl Display for RecieveError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             RecieveError::InvalidString => write!(f, "invalid sender string"),
             RecieveError::FailedSignatureCheck => write!(f, "signature failed"),
+            RecieveError::FailedDecryption => write!(f, "decryption failed"),
+            RecieveError::NotRecieved => write!(f, "the key exchange hasn't been recieved yet"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RekeyError {
+    NotRekey,
+    InvalidString,
+    MissingSignature,
+    FailedSignatureCheck,
+    Stale,
+}
+
+impl Display for RekeyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RekeyError::NotRekey => write!(f, "not a rekey message"),
+            RekeyError::InvalidString => write!(f, "invalid rekey message"),
+            RekeyError::MissingSignature => write!(f, "rekey message is not signed"),
+            RekeyError::FailedSignatureCheck => write!(f, "rekey signature failed"),
+            RekeyError::Stale => write!(f, "rekey message is older than one already applied"),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum HelloError {
+    InvalidString,
+    FailedSignatureCheck,
+    MissingHello,
+    NotReciever,
+}
+
+impl Display for HelloError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HelloError::InvalidString => write!(f, "invalid reciever hello"),
+            HelloError::FailedSignatureCheck => write!(f, "reciever hello signature failed"),
+            HelloError::MissingHello => write!(f, "no reciever hello has been set"),
+            HelloError::NotReciever => write!(f, "only a reciever has a hello"),
         }
     }
 }
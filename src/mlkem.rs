@@ -0,0 +1,239 @@
+//! ML-KEM-768 from FIPS 203, using the `ml-kem` crate. The input checks FIPS
+//! 203 asks for on untrusted keys are done here, since the crate leaves them to
+//! the caller.
+
+use alloc::vec::Vec;
+
+use ml_kem::{
+    kem::{Decapsulate, Encapsulate},
+    Ciphertext, Encoded, EncodedSizeUser, KemCore, MlKem768,
+};
+use rand::{CryptoRng, RngCore};
+use sha3::{Digest, Sha3_256};
+use zeroize::{Zeroize, Zeroizing};
+
+const Q: u16 = 3329;
+const K: usize = 3;
+
+type Ek = <MlKem768 as KemCore>::EncapsulationKey;
+type Dk = <MlKem768 as KemCore>::DecapsulationKey;
+
+pub(crate) struct EncapsulationKey(Ek);
+
+// the `ml-kem` crate zeroizes the secret parts of a decapsulation key on drop
+pub(crate) struct DecapsulationKey(Dk);
+
+impl EncapsulationKey {
+    /// Parses an encapsulation key, applying the modulus check from FIPS 203.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let encoded = Encoded::<Ek>::try_from(bytes).ok()?;
+
+        if !coefficients_reduced(&bytes[..384 * K]) {
+            return None;
+        }
+
+        Some(Self(Ek::from_bytes(&encoded)))
+    }
+
+    pub(crate) fn as_bytes(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+
+    /// Returns the ciphertext and the shared secret.
+    pub(crate) fn encapsulate<R: CryptoRng + RngCore>(
+        &self,
+        rng: &mut R,
+    ) -> (Vec<u8>, Zeroizing<[u8; 32]>) {
+        let (ciphertext, shared_secret) = self
+            .0
+            .encapsulate(rng)
+            .expect("ML-KEM encapsulation is infallible");
+
+        (ciphertext.to_vec(), take_secret(shared_secret.into()))
+    }
+
+    /// Encapsulates with the given randomness `m`, for known-answer tests.
+    #[cfg(test)]
+    pub(crate) fn encapsulate_deterministic(&self, m: &[u8; 32]) -> (Vec<u8>, [u8; 32]) {
+        use ml_kem::EncapsulateDeterministic;
+
+        let (ciphertext, shared_secret) = self
+            .0
+            .encapsulate_deterministic(&(*m).into())
+            .expect("ML-KEM encapsulation is infallible");
+
+        (ciphertext.to_vec(), shared_secret.into())
+    }
+}
+
+impl DecapsulationKey {
+    pub(crate) fn generate<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        Self(MlKem768::generate(rng).0)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn from_seed(d: &[u8; 32], z: &[u8; 32]) -> Self {
+        Self(MlKem768::generate_deterministic(&(*d).into(), &(*z).into()).0)
+    }
+
+    /// Parses a decapsulation key, applying the hash check from FIPS 203.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let ek = bytes.get(384 * K..768 * K + 32)?;
+        if Sha3_256::digest(ek)[..] != *bytes.get(768 * K + 32..768 * K + 64)? {
+            return None;
+        }
+
+        let mut encoded = Encoded::<Dk>::try_from(bytes).ok()?;
+        let dk = Dk::from_bytes(&encoded);
+        encoded.as_mut_slice().zeroize();
+
+        Some(Self(dk))
+    }
+
+    pub(crate) fn as_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut encoded = self.0.as_bytes();
+        let bytes = Zeroizing::new(encoded.to_vec());
+        encoded.as_mut_slice().zeroize();
+
+        bytes
+    }
+
+    pub(crate) fn encapsulation_key(&self) -> EncapsulationKey {
+        EncapsulationKey(self.0.encapsulation_key().clone())
+    }
+
+    /// Returns the shared secret. An invalid ciphertext yields a pseudorandom
+    /// secret rather than an error (implicit rejection), so only a ciphertext
+    /// of the wrong length gives `None`.
+    pub(crate) fn decapsulate(&self, ciphertext: &[u8]) -> Option<Zeroizing<[u8; 32]>> {
+        let ciphertext = Ciphertext::<MlKem768>::try_from(ciphertext).ok()?;
+
+        let shared_secret = self
+            .0
+            .decapsulate(&ciphertext)
+            .expect("ML-KEM decapsulation is infallible");
+
+        Some(take_secret(shared_secret.into()))
+    }
+}
+
+// moves a shared secret into a buffer that is wiped on drop, wiping the copy
+fn take_secret(mut shared_secret: [u8; 32]) -> Zeroizing<[u8; 32]> {
+    let secret = Zeroizing::new(shared_secret);
+    shared_secret.zeroize();
+
+    secret
+}
+
+// every 12-bit coefficient has to be below Q, or the key is malformed
+fn coefficients_reduced(bytes: &[u8]) -> bool {
+    bytes.chunks_exact(3).all(|b| {
+        let low = u16::from(b[0]) | (u16::from(b[1] & 0x0f) << 8);
+        let high = (u16::from(b[1]) >> 4) | (u16::from(b[2]) << 4);
+
+        low < Q && high < Q
+    })
+}
+
+// the known answers in `test-vectors/mlkem768.json` come from OpenSSL's FIPS 203
+// implementation (see `test-vectors/README.md`)
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use serde::Deserialize;
+
+    use super::{DecapsulationKey, EncapsulationKey};
+
+    #[derive(Deserialize)]
+    struct KnownAnswer {
+        d: String,
+        z: String,
+        m: String,
+        ek: String,
+        dk: String,
+        ct: String,
+        ss: String,
+        rejected_ct: String,
+        rejected_ss: String,
+    }
+
+    fn load_known_answers() -> Vec<KnownAnswer> {
+        serde_json::from_str(include_str!("../test-vectors/mlkem768.json")).unwrap()
+    }
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn seed(text: &str) -> [u8; 32] {
+        hex(text).try_into().unwrap()
+    }
+
+    #[test]
+    fn key_generation_matches() {
+        for known in load_known_answers() {
+            let dk = DecapsulationKey::from_seed(&seed(&known.d), &seed(&known.z));
+
+            assert_eq!(*dk.as_bytes(), hex(&known.dk));
+            assert_eq!(dk.encapsulation_key().as_bytes(), hex(&known.ek));
+        }
+    }
+
+    #[test]
+    fn encapsulation_matches() {
+        for known in load_known_answers() {
+            let ek = EncapsulationKey::from_bytes(&hex(&known.ek)).unwrap();
+
+            let (ct, ss) = ek.encapsulate_deterministic(&seed(&known.m));
+
+            assert_eq!(ct, hex(&known.ct));
+            assert_eq!(ss.to_vec(), hex(&known.ss));
+        }
+    }
+
+    #[test]
+    fn decapsulation_matches() {
+        for known in load_known_answers() {
+            let dk = DecapsulationKey::from_bytes(&hex(&known.dk)).unwrap();
+
+            assert_eq!(
+                dk.decapsulate(&hex(&known.ct)).unwrap().to_vec(),
+                hex(&known.ss)
+            );
+        }
+    }
+
+    #[test]
+    fn tampered_ciphertext_is_implicitly_rejected() {
+        for known in load_known_answers() {
+            let dk = DecapsulationKey::from_bytes(&hex(&known.dk)).unwrap();
+
+            let rejected = dk.decapsulate(&hex(&known.rejected_ct)).unwrap();
+
+            assert_eq!(rejected.to_vec(), hex(&known.rejected_ss));
+            assert_ne!(rejected.to_vec(), hex(&known.ss));
+        }
+    }
+
+    #[test]
+    fn malformed_keys_are_refused() {
+        let known = &load_known_answers()[0];
+
+        // a coefficient of Q or more fails the modulus check
+        let mut ek = hex(&known.ek);
+        ek[0] = 0xff;
+        ek[1] |= 0x0f;
+        assert!(EncapsulationKey::from_bytes(&ek).is_none());
+
+        // a changed encapsulation key no longer matches its hash
+        let mut dk = hex(&known.dk);
+        dk[384 * 3] ^= 1;
+        assert!(DecapsulationKey::from_bytes(&dk).is_none());
+
+        assert!(EncapsulationKey::from_bytes(&ek[1..]).is_none());
+    }
+}
@@ -0,0 +1,342 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::{Debug, Display};
+
+use base64::Engine;
+use curve25519_dalek::{ristretto::CompressedRistretto, RistrettoPoint, Scalar};
+use hkdf::Hkdf;
+#[cfg(feature = "std")]
+use rand::rngs::OsRng;
+use rand::{CryptoRng, Rng, RngCore};
+use rsa::sha2::{Digest, Sha256, Sha512};
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{DefaultRng, E2EOffline, Result, REDACTED};
+
+const PAKE_TAG: &str = "cpace";
+const DSI: &[u8] = b"E2EOfflineCPaceRistretto255";
+
+#[derive(Debug)]
+enum PakeRole {
+    Sender,
+    Reciever,
+}
+
+/// Agrees on a shared key from a short code, such as one read out over the
+/// phone, using a CPace-style PAKE over Ristretto255. Each run gives an
+/// attacker on the channel at most one online guess at the code, and nothing
+/// sent over the channel lets them test guesses offline.
+///
+/// The sender calls [`Self::send`] and gives the result to the reciever, who
+/// passes it to [`Self::recieve`] and returns its own [`Self::send`] output.
+/// The sender then passes that to [`Self::recieve`], which fails if the codes
+/// didn't match, and calls [`Self::send`] again for a last message that lets
+/// the reciever check the same. Neither side can [`Self::build`] until it
+/// knows the codes matched.
+pub struct E2EOfflinePakeBuilder<R = DefaultRng> {
+    rng: R,
+    code: Zeroizing<String>,
+    session_id: Option<Vec<u8>>,
+
+    my_secret: Zeroizing<Scalar>,
+    my_share: Option<CompressedRistretto>,
+    other_share: Option<CompressedRistretto>,
+
+    session_key: Option<Zeroizing<Vec<u8>>>,
+    // whether the other side has shown it has the same session key
+    confirmed: bool,
+    // set once a confirmation fails; the secret is wiped so it can't be
+    // reused against another share, which would give an extra guess
+    failed: bool,
+
+    my_type: PakeRole,
+}
+
+#[cfg(feature = "std")]
+impl E2EOfflinePakeBuilder {
+    /// Generates a random code of six digits, grouped in threes.
+    pub fn generate_code() -> String {
+        Self::generate_code_with_rng(&mut OsRng)
+    }
+
+    pub fn new_sender(code: &str) -> Self {
+        Self::new_sender_with_rng(code, OsRng)
+    }
+
+    pub fn new_reciever(code: &str) -> Self {
+        Self::new_reciever_with_rng(code, OsRng)
+    }
+}
+
+impl<R: CryptoRng + RngCore> E2EOfflinePakeBuilder<R> {
+    /// Like [`E2EOfflinePakeBuilder::generate_code`], but the code comes from
+    /// `rng`.
+    pub fn generate_code_with_rng(rng: &mut R) -> String {
+        let code: u32 = rng.gen_range(0..1_000_000);
+
+        format!("{:03}-{:03}", code / 1000, code % 1000)
+    }
+
+    /// Like [`E2EOfflinePakeBuilder::new_sender`], but everything random in
+    /// the exchange and the session it builds comes from `rng`.
+    pub fn new_sender_with_rng(code: &str, mut rng: R) -> Self {
+        let mut session_id = vec![0u8; 16];
+        rng.fill_bytes(&mut session_id);
+
+        Self::new(code, Some(session_id), PakeRole::Sender, rng)
+    }
+
+    pub fn new_reciever_with_rng(code: &str, rng: R) -> Self {
+        Self::new(code, None, PakeRole::Reciever, rng)
+    }
+
+    fn new(code: &str, session_id: Option<Vec<u8>>, my_type: PakeRole, mut rng: R) -> Self {
+        Self {
+            my_secret: Zeroizing::new(random_scalar(&mut rng)),
+            rng,
+            code: Zeroizing::new(code.trim().to_string()),
+            session_id,
+            my_share: None,
+            other_share: None,
+            session_key: None,
+            confirmed: false,
+            failed: false,
+            my_type,
+        }
+    }
+
+    /// Returns this side's next message. For the reciever this can only be
+    /// called after [`Self::recieve`], and it carries a confirmation tag so the
+    /// sender can tell whether the codes matched. Once the sender has checked
+    /// it, the sender's next message is its own confirmation tag.
+    pub fn send(&mut self) -> Result<String> {
+        if self.failed {
+            Err(PakeError::WrongCode)?;
+        }
+
+        if let (PakeRole::Sender, true) = (&self.my_type, self.confirmed) {
+            let confirmation =
+                base64::engine::general_purpose::URL_SAFE.encode(self.confirmation(b"sender"));
+
+            return Ok(format!("{PAKE_TAG}.{confirmation}"));
+        }
+
+        let session_id = self.session_id.as_ref().ok_or(PakeError::OutOfOrder)?;
+        let session_id_encoded = base64::engine::general_purpose::URL_SAFE.encode(session_id);
+
+        let share = (generator(&self.code, session_id) * *self.my_secret).compress();
+        self.my_share.replace(share);
+
+        let share_encoded = base64::engine::general_purpose::URL_SAFE.encode(share.as_bytes());
+
+        match self.my_type {
+            PakeRole::Sender => Ok(format!("{PAKE_TAG}.{session_id_encoded}.{share_encoded}")),
+            PakeRole::Reciever => {
+                self.derive_session_key()?;
+                let confirmation = base64::engine::general_purpose::URL_SAFE
+                    .encode(self.confirmation(b"reciever"));
+
+                Ok(format!("{PAKE_TAG}.{share_encoded}.{confirmation}"))
+            }
+        }
+    }
+
+    /// Takes the other side's next message. Once a confirmation tag doesn't
+    /// match, this and every other call fails with [`PakeError::WrongCode`],
+    /// and pairing has to start over with new builders.
+    pub fn recieve(&mut self, message: &str) -> Result<()> {
+        if self.failed {
+            Err(PakeError::WrongCode)?;
+        }
+
+        let mut message = message.trim().split('.');
+
+        if message.next() != Some(PAKE_TAG) {
+            Err(PakeError::InvalidString)?;
+        }
+
+        match self.my_type {
+            // the sender's confirmation comes once the reciever has its key
+            PakeRole::Reciever if self.session_key.is_some() => {
+                let confirmation = message.next().ok_or(PakeError::InvalidString)?;
+
+                self.check_confirmation(confirmation, b"sender")?;
+            }
+            PakeRole::Reciever => {
+                let (session_id, share) = (
+                    message.next().ok_or(PakeError::InvalidString)?,
+                    message.next().ok_or(PakeError::InvalidString)?,
+                );
+
+                self.session_id
+                    .replace(base64::engine::general_purpose::URL_SAFE.decode(session_id)?);
+                self.other_share.replace(decode_share(share)?);
+            }
+            PakeRole::Sender => {
+                let (share, confirmation) = (
+                    message.next().ok_or(PakeError::InvalidString)?,
+                    message.next().ok_or(PakeError::InvalidString)?,
+                );
+
+                if self.my_share.is_none() {
+                    Err(PakeError::OutOfOrder)?;
+                }
+
+                self.other_share.replace(decode_share(share)?);
+                self.derive_session_key()?;
+
+                self.check_confirmation(confirmation, b"reciever")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_confirmation(&mut self, confirmation: &str, role: &[u8]) -> Result<()> {
+        let confirmation = base64::engine::general_purpose::URL_SAFE.decode(confirmation)?;
+
+        if !bool::from(confirmation.ct_eq(&self.confirmation(role))) {
+            self.session_key.take();
+            self.my_secret.zeroize();
+            self.failed = true;
+            Err(PakeError::WrongCode)?;
+        }
+        self.confirmed = true;
+
+        Ok(())
+    }
+
+    /// Finishes the pairing once the other side's confirmation has been
+    /// recieved.
+    pub fn build(self) -> Result<E2EOffline<R>> {
+        if self.failed {
+            Err(PakeError::WrongCode)?;
+        }
+        if !self.confirmed {
+            Err(PakeError::Unconfirmed)?;
+        }
+        let session_key = self.session_key.ok_or(PakeError::OutOfOrder)?;
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        Hkdf::<Sha256>::new(None, &session_key)
+            .expand(b"E2EOffline CPace key", &mut *key)
+            .expect("32 bytes is a valid HKDF output length");
+
+        E2EOffline::new(&*key, self.rng)
+    }
+
+    fn derive_session_key(&mut self) -> Result<()> {
+        let (Some(my_share), Some(other_share), Some(session_id)) =
+            (&self.my_share, &self.other_share, &self.session_id)
+        else {
+            Err(PakeError::OutOfOrder)?
+        };
+
+        let point = other_share.decompress().ok_or(PakeError::InvalidShare)?;
+        let shared = (point * *self.my_secret).compress();
+
+        if shared == RistrettoPoint::default().compress() {
+            Err(PakeError::InvalidShare)?;
+        }
+
+        // the transcript is ordered by role so both sides hash the same thing
+        let (sender_share, reciever_share) = match self.my_type {
+            PakeRole::Sender => (my_share, other_share),
+            PakeRole::Reciever => (other_share, my_share),
+        };
+
+        let session_key = Sha512::new()
+            .chain_update(length_prefixed(&[DSI, b"_ISK"].concat()))
+            .chain_update(length_prefixed(session_id))
+            .chain_update(length_prefixed(shared.as_bytes()))
+            .chain_update(length_prefixed(sender_share.as_bytes()))
+            .chain_update(length_prefixed(reciever_share.as_bytes()))
+            .finalize();
+
+        self.session_key
+            .replace(Zeroizing::new(session_key.to_vec()));
+
+        Ok(())
+    }
+
+    fn confirmation(&self, role: &[u8]) -> Vec<u8> {
+        let mut tag = [0u8; 16];
+        Hkdf::<Sha256>::new(None, self.session_key.as_ref().unwrap())
+            .expand(&[b"E2EOffline CPace confirm ", role].concat(), &mut tag)
+            .expect("16 bytes is a valid HKDF output length");
+
+        tag.to_vec()
+    }
+}
+
+// the generator is derived from the code, so only someone who knows the code
+// can produce a share that leads to the same key
+fn generator(code: &str, session_id: &[u8]) -> RistrettoPoint {
+    let hash = Sha512::new()
+        .chain_update(length_prefixed(DSI))
+        .chain_update(length_prefixed(code.as_bytes()))
+        .chain_update(length_prefixed(session_id))
+        .finalize();
+
+    RistrettoPoint::from_uniform_bytes(&hash.into())
+}
+
+fn random_scalar<R: CryptoRng + RngCore>(rng: &mut R) -> Scalar {
+    let mut bytes = [0u8; 64];
+    rng.fill_bytes(&mut bytes);
+
+    Scalar::from_bytes_mod_order_wide(&bytes)
+}
+
+fn decode_share(share: &str) -> Result<CompressedRistretto> {
+    let share = base64::engine::general_purpose::URL_SAFE.decode(share)?;
+
+    Ok(CompressedRistretto::from_slice(&share).map_err(|_| PakeError::InvalidShare)?)
+}
+
+fn length_prefixed(bytes: &[u8]) -> Vec<u8> {
+    let mut out = (bytes.len() as u64).to_le_bytes().to_vec();
+    out.extend_from_slice(bytes);
+    out
+}
+
+impl<R> Debug for E2EOfflinePakeBuilder<R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("E2EOfflinePakeBuilder")
+            .field("my_type", &self.my_type)
+            .field("code", &REDACTED)
+            .field("finished", &self.session_key.is_some())
+            .field("confirmed", &self.confirmed)
+            .field("failed", &self.failed)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PakeError {
+    InvalidString,
+    InvalidShare,
+    OutOfOrder,
+    WrongCode,
+    Unconfirmed,
+}
+
+impl Display for PakeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PakeError::InvalidString => write!(f, "invalid code pairing string"),
+            PakeError::InvalidShare => write!(f, "invalid code pairing share"),
+            PakeError::OutOfOrder => write!(f, "code pairing steps done out of order"),
+            PakeError::WrongCode => write!(f, "the other user entered a different code"),
+            PakeError::Unconfirmed => {
+                write!(f, "the other user hasn't confirmed the code yet")
+            }
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::Display;
+
+use argon2::Argon2;
+use base64::Engine;
+use rand::{CryptoRng, RngCore};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use crate::Result;
+
+const SALT_LEN: usize = 16;
+
+/// Keys derived from a shared passphrase. Each side picks its own salt for the
+/// conversation and sends it with every ciphertext, so the other side can
+/// derive the same key from the passphrase alone.
+///
+/// The other side only ever uses one salt, so once a message under it has
+/// decrypted the salt is pinned and any other is refused without running
+/// Argon2id. Until then only the latest unknown salt's key is kept.
+pub(crate) struct PassphraseKeys {
+    passphrase: Zeroizing<String>,
+    salt_encoded: String,
+    other_salt_encoded: Option<String>,
+    derived: Vec<(String, Zeroizing<[u8; 32]>)>,
+}
+
+impl PassphraseKeys {
+    /// Returns the keys and the key for this side's own salt.
+    pub(crate) fn new<R: CryptoRng + RngCore>(
+        passphrase: &str,
+        rng: &mut R,
+    ) -> Result<(Self, Zeroizing<[u8; 32]>)> {
+        let mut salt = [0u8; SALT_LEN];
+        rng.fill_bytes(&mut salt);
+
+        Self::with_salt(
+            passphrase,
+            &base64::engine::general_purpose::URL_SAFE.encode(salt),
+        )
+    }
+
+    /// Like [`Self::new`], but with a salt that is already in use, such as
+    /// the one a saved session was encrypted under.
+    pub(crate) fn with_salt(
+        passphrase: &str,
+        salt_encoded: &str,
+    ) -> Result<(Self, Zeroizing<[u8; 32]>)> {
+        let salt = base64::engine::general_purpose::URL_SAFE.decode(salt_encoded)?;
+        let key = derive_key(passphrase, &salt)?;
+        let salt_encoded = salt_encoded.to_string();
+
+        Ok((
+            Self {
+                passphrase: Zeroizing::new(passphrase.to_string()),
+                salt_encoded: salt_encoded.clone(),
+                other_salt_encoded: None,
+                derived: vec![(salt_encoded, key.clone())],
+            },
+            key,
+        ))
+    }
+
+    /// Rebuilds the keys for a saved session. Keys for salts are derived
+    /// again as they're needed.
+    pub(crate) fn restore(
+        passphrase: &str,
+        salt_encoded: &str,
+        other_salt_encoded: Option<&str>,
+    ) -> Result<Self> {
+        for salt in core::iter::once(salt_encoded).chain(other_salt_encoded) {
+            let salt = base64::engine::general_purpose::URL_SAFE.decode(salt)?;
+
+            if salt.len() != SALT_LEN {
+                Err(PassphraseError::InvalidSalt)?;
+            }
+        }
+
+        Ok(Self {
+            passphrase: Zeroizing::new(passphrase.to_string()),
+            salt_encoded: salt_encoded.to_string(),
+            other_salt_encoded: other_salt_encoded.map(str::to_string),
+            derived: Vec::new(),
+        })
+    }
+
+    pub(crate) fn passphrase(&self) -> &str {
+        &self.passphrase
+    }
+
+    pub(crate) fn salt_encoded(&self) -> &str {
+        &self.salt_encoded
+    }
+
+    /// The other side's salt, once one of its messages has decrypted.
+    pub(crate) fn other_salt_encoded(&self) -> Option<&str> {
+        self.other_salt_encoded.as_deref()
+    }
+
+    /// Returns the key for a salt seen in a ciphertext, deriving it only the
+    /// first time since Argon2id is deliberately slow.
+    pub(crate) fn key_for_salt(&mut self, salt_encoded: &str) -> Result<&[u8]> {
+        let index = match self.derived.iter().position(|(s, _)| s == salt_encoded) {
+            Some(index) => index,
+            None => {
+                if !self.is_expected_salt(salt_encoded) {
+                    Err(PassphraseError::UnexpectedSalt)?;
+                }
+
+                let salt = base64::engine::general_purpose::URL_SAFE.decode(salt_encoded)?;
+                let key = derive_key(&self.passphrase, &salt)?;
+
+                // forgets the key for a salt that never decrypted anything
+                let (own, other) = (&self.salt_encoded, &self.other_salt_encoded);
+                self.derived
+                    .retain(|(s, _)| s == own || Some(s) == other.as_ref());
+
+                self.derived.push((salt_encoded.to_string(), key));
+                self.derived.len() - 1
+            }
+        };
+
+        Ok(&self.derived[index].1[..])
+    }
+
+    fn is_expected_salt(&self, salt_encoded: &str) -> bool {
+        match &self.other_salt_encoded {
+            Some(other) => salt_encoded == self.salt_encoded || salt_encoded == other,
+            None => true,
+        }
+    }
+
+    /// Pins the salt of a message that decrypted, so no other salt is derived
+    /// from then on.
+    pub(crate) fn pin_salt(&mut self, salt_encoded: &str) {
+        if salt_encoded != self.salt_encoded && self.other_salt_encoded.is_none() {
+            self.other_salt_encoded = Some(salt_encoded.to_string());
+        }
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+    if salt.len() != SALT_LEN {
+        Err(PassphraseError::InvalidSalt)?;
+    }
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::default().hash_password_into(passphrase.as_bytes(), salt, &mut *key)?;
+
+    Ok(key)
+}
+
+#[derive(Error, Debug)]
+pub enum PassphraseError {
+    InvalidSalt,
+    NotPassphrase,
+    UnexpectedSalt,
+}
+
+impl Display for PassphraseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PassphraseError::InvalidSalt => write!(f, "invalid passphrase salt"),
+            PassphraseError::NotPassphrase => {
+                write!(f, "ciphertext needs a passphrase to decrypt")
+            }
+            PassphraseError::UnexpectedSalt => {
+                write!(
+                    f,
+                    "ciphertext is under a different salt from the other side's"
+                )
+            }
+        }
+    }
+}
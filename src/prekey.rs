@@ -0,0 +1,307 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::Display;
+
+use base64::Engine;
+use hkdf::Hkdf;
+use rand::{CryptoRng, Rng, RngCore};
+use rsa::sha2::Sha256;
+use thiserror::Error;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+use crate::Result;
+
+const KDF_INFO: &[u8] = b"E2EOffline X3DH";
+
+/// The reciever's secret half of a prekey bundle.
+pub(crate) struct PrekeySecrets {
+    signed_prekey: StaticSecret,
+    one_time_prekeys: Vec<(u32, StaticSecret)>,
+    used_one_time_prekeys: Vec<u32>,
+    next_id: u32,
+}
+
+impl PrekeySecrets {
+    pub(crate) fn new<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        Self {
+            signed_prekey: StaticSecret::random_from_rng(rng),
+            one_time_prekeys: Vec::new(),
+            used_one_time_prekeys: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub(crate) fn generate_one_time_prekeys<R: CryptoRng + RngCore>(
+        &mut self,
+        rng: &mut R,
+        count: usize,
+    ) {
+        for _ in 0..count {
+            self.one_time_prekeys
+                .push((self.next_id, StaticSecret::random_from_rng(&mut *rng)));
+            self.next_id += 1;
+        }
+    }
+
+    pub(crate) fn signed_prekey_encoded(&self) -> String {
+        encode_key(&PublicKey::from(&self.signed_prekey))
+    }
+
+    pub(crate) fn one_time_prekeys_encoded(&self) -> String {
+        self.one_time_prekeys
+            .iter()
+            .map(|(id, secret)| format!("{id}:{}", encode_key(&PublicKey::from(secret))))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    pub(crate) fn used_one_time_prekeys(&self) -> &[u32] {
+        &self.used_one_time_prekeys
+    }
+
+    /// The secret keys and which one-time prekeys are used up, for saving an
+    /// unfinished handshake.
+    pub(crate) fn secret_encoded(&self) -> String {
+        let one_time_prekeys = self
+            .one_time_prekeys
+            .iter()
+            .map(|(id, secret)| format!("{id}:{}", encode_secret(secret)))
+            .collect::<Vec<_>>()
+            .join(",");
+        let used_one_time_prekeys = self
+            .used_one_time_prekeys
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            "{}.{one_time_prekeys}.{used_one_time_prekeys}.{}",
+            encode_secret(&self.signed_prekey),
+            self.next_id
+        )
+    }
+
+    pub(crate) fn decode_secret(encoded: &str) -> Result<Self> {
+        let mut parts = encoded.split('.');
+        let (
+            Some(signed_prekey),
+            Some(one_time_prekeys),
+            Some(used_one_time_prekeys),
+            Some(next_id),
+            None,
+        ) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        )
+        else {
+            Err(PrekeyError::InvalidBundle)?
+        };
+
+        let one_time_prekeys = one_time_prekeys
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let (id, secret) = s.split_once(':').ok_or(PrekeyError::InvalidBundle)?;
+                Ok((id.parse()?, decode_secret(secret)?))
+            })
+            .collect::<Result<_>>()?;
+        let used_one_time_prekeys = used_one_time_prekeys
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(|id| Ok(id.parse()?))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            signed_prekey: decode_secret(signed_prekey)?,
+            one_time_prekeys,
+            used_one_time_prekeys,
+            next_id: next_id.parse()?,
+        })
+    }
+
+    /// Runs the reciever's side of the key agreement, consuming the one-time
+    /// prekey the sender picked so it can never be used again. Leaving the
+    /// one-time prekey out is only allowed once they have all been used.
+    pub(crate) fn agree(
+        &mut self,
+        ephemeral: &PublicKey,
+        one_time_prekey_id: Option<u32>,
+    ) -> Result<Zeroizing<[u8; 32]>> {
+        let one_time_prekey = match one_time_prekey_id {
+            Some(id) if self.used_one_time_prekeys.contains(&id) => {
+                Err(PrekeyError::OneTimePrekeyUsed)?
+            }
+            Some(id) => {
+                let index = self
+                    .one_time_prekeys
+                    .iter()
+                    .position(|(other, _)| *other == id)
+                    .ok_or(PrekeyError::UnknownOneTimePrekey)?;
+
+                self.used_one_time_prekeys.push(id);
+                Some(self.one_time_prekeys.remove(index).1)
+            }
+            None if !self.one_time_prekeys.is_empty() => Err(PrekeyError::MissingOneTimePrekey)?,
+            None => None,
+        };
+
+        let dh1 = self.signed_prekey.diffie_hellman(ephemeral);
+        let dh2 = one_time_prekey.map(|secret| secret.diffie_hellman(ephemeral));
+
+        Ok(derive_key(
+            dh1.as_bytes(),
+            dh2.as_ref().map(|dh| dh.as_bytes()),
+        ))
+    }
+}
+
+/// The public half of a reciever's prekey bundle, as seen by the sender.
+pub(crate) struct PrekeyBundle {
+    signed_prekey: PublicKey,
+    one_time_prekeys: Vec<(u32, PublicKey)>,
+}
+
+impl PrekeyBundle {
+    pub(crate) fn decode(signed_prekey: &str, one_time_prekeys: &str) -> Result<Self> {
+        let signed_prekey = decode_key(signed_prekey)?;
+
+        let one_time_prekeys = one_time_prekeys
+            .split(',')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                let (id, key) = s.split_once(':').ok_or(PrekeyError::InvalidBundle)?;
+                Ok((id.parse()?, decode_key(key)?))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            signed_prekey,
+            one_time_prekeys,
+        })
+    }
+
+    pub(crate) fn signed_prekey_encoded(&self) -> String {
+        encode_key(&self.signed_prekey)
+    }
+
+    /// Encodes the keys the same way as in a bundle, for
+    /// [`PrekeyBundle::decode`].
+    pub(crate) fn encoded(&self) -> String {
+        let one_time_prekeys = self
+            .one_time_prekeys
+            .iter()
+            .map(|(id, key)| format!("{id}:{}", encode_key(key)))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!("{}.{one_time_prekeys}", self.signed_prekey_encoded())
+    }
+
+    /// Runs the sender's side of the key agreement against a random one-time
+    /// prekey, if any are left. Returns the ephemeral public key, the id of the
+    /// one-time prekey used and the derived key.
+    pub(crate) fn agree<R: CryptoRng + RngCore>(
+        &mut self,
+        rng: &mut R,
+    ) -> (PublicKey, Option<u32>, Zeroizing<[u8; 32]>) {
+        let one_time_prekey = match self.one_time_prekeys.len() {
+            0 => None,
+            len => Some(self.one_time_prekeys.remove(rng.gen_range(0..len))),
+        };
+
+        let ephemeral = StaticSecret::random_from_rng(rng);
+
+        let dh1 = ephemeral.diffie_hellman(&self.signed_prekey);
+        let dh2 = one_time_prekey.map(|(_, key)| ephemeral.diffie_hellman(&key));
+
+        (
+            PublicKey::from(&ephemeral),
+            one_time_prekey.map(|(id, _)| id),
+            derive_key(dh1.as_bytes(), dh2.as_ref().map(|dh| dh.as_bytes())),
+        )
+    }
+}
+
+fn derive_key(dh1: &[u8; 32], dh2: Option<&[u8; 32]>) -> Zeroizing<[u8; 32]> {
+    // as in X3DH, the input is prefixed with 32 0xFF bytes
+    let mut ikm = Zeroizing::new(vec![0xFF; 32]);
+    ikm.extend_from_slice(dh1);
+    if let Some(dh2) = dh2 {
+        ikm.extend_from_slice(dh2);
+    }
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(None, &ikm)
+        .expand(KDF_INFO, &mut *key)
+        .expect("32 bytes is a valid HKDF output length");
+
+    key
+}
+
+pub(crate) fn encode_key(key: &PublicKey) -> String {
+    base64::engine::general_purpose::URL_SAFE.encode(key.as_bytes())
+}
+
+fn encode_secret(secret: &StaticSecret) -> String {
+    base64::engine::general_purpose::URL_SAFE.encode(secret.to_bytes())
+}
+
+fn decode_secret(secret: &str) -> Result<StaticSecret> {
+    let secret: [u8; 32] = base64::engine::general_purpose::URL_SAFE
+        .decode(secret)?
+        .try_into()
+        .map_err(|_| PrekeyError::InvalidKey)?;
+
+    Ok(StaticSecret::from(secret))
+}
+
+pub(crate) fn decode_key(key: &str) -> Result<PublicKey> {
+    let key: [u8; 32] = base64::engine::general_purpose::URL_SAFE
+        .decode(key)?
+        .try_into()
+        .map_err(|_| PrekeyError::InvalidKey)?;
+
+    Ok(PublicKey::from(key))
+}
+
+#[derive(Error, Debug)]
+pub enum PrekeyError {
+    InvalidBundle,
+    InvalidKey,
+    InvalidString,
+    FailedSignatureCheck,
+    NoPrekeys,
+    UnknownOneTimePrekey,
+    OneTimePrekeyUsed,
+    MissingOneTimePrekey,
+    NotReciever,
+    NotSender,
+}
+
+impl Display for PrekeyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PrekeyError::InvalidBundle => write!(f, "invalid prekey bundle"),
+            PrekeyError::InvalidKey => write!(f, "invalid prekey"),
+            PrekeyError::InvalidString => write!(f, "invalid prekey sender string"),
+            PrekeyError::FailedSignatureCheck => write!(f, "prekey signature failed"),
+            PrekeyError::NoPrekeys => write!(f, "no prekey bundle has been generated"),
+            PrekeyError::UnknownOneTimePrekey => write!(f, "unknown one-time prekey"),
+            PrekeyError::OneTimePrekeyUsed => write!(f, "one-time prekey was already used"),
+            PrekeyError::MissingOneTimePrekey => {
+                write!(f, "a one-time prekey is available but wasn't used")
+            }
+            PrekeyError::NotReciever => write!(f, "only a reciever has a prekey bundle"),
+            PrekeyError::NotSender => write!(f, "only a sender can use a prekey bundle"),
+        }
+    }
+}
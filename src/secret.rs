@@ -0,0 +1,27 @@
+use alloc::string::String;
+use core::fmt::Debug;
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// A decrypted message. It is wiped from memory when dropped, and its `Debug`
+/// output leaves the text out, so it can't end up in logs by accident.
+#[derive(Clone, PartialEq, Eq, Zeroize, ZeroizeOnDrop)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(s: String) -> Self {
+        Self(s)
+    }
+}
+
+impl Debug for SecretString {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "SecretString([REDACTED])")
+    }
+}
@@ -0,0 +1,302 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Display;
+
+use base64::Engine;
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::{
+    identity::{self, PrivateKey, PublicKey},
+    passphrase::PassphraseKeys,
+    E2EOffline, Result,
+};
+
+const SESSION_VERSION: u32 = 1;
+
+/// A conversation saved with [`E2EOffline::export_session`], encrypted with a
+/// passphrase. It can be stored with serde, or passed around as text with
+/// [`Self::to_encoded`], to carry on the conversation somewhere else, such as
+/// moving it from the command line to the webapp.
+///
+/// Handshakes saved with
+/// [`E2EOfflineBuilder::export_state`](crate::E2EOfflineBuilder::export_state)
+/// use the same envelope, marked as a handshake so the two can't be mixed up.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionExport {
+    version: u32,
+    // missing from session exports, which came before saved handshakes used
+    // this envelope
+    #[serde(default, skip_serializing_if = "ExportKind::is_session")]
+    kind: ExportKind,
+    session: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ExportKind {
+    #[default]
+    Session,
+    Handshake,
+}
+
+impl ExportKind {
+    fn is_session(&self) -> bool {
+        *self == ExportKind::Session
+    }
+}
+
+impl SessionExport {
+    pub fn to_encoded(&self) -> String {
+        serde_json::to_string(self).expect("a session export is always valid JSON")
+    }
+
+    pub fn from_encoded(encoded: &str) -> Result<Self> {
+        Ok(serde_json::from_str(encoded.trim())?)
+    }
+
+    /// Encrypts `data` as JSON with `vault`'s key.
+    pub(crate) fn seal<T: Serialize, V: CryptoRng + RngCore>(
+        kind: ExportKind,
+        data: &T,
+        vault: &mut E2EOffline<V>,
+    ) -> Result<Self> {
+        Ok(Self {
+            version: SESSION_VERSION,
+            kind,
+            session: vault.encrypt(&Zeroizing::new(serde_json::to_string(data)?))?,
+        })
+    }
+
+    /// The vault for opening an export made with a passphrase, derived from
+    /// the salt the export was encrypted under.
+    pub(crate) fn passphrase_vault(&self, passphrase: &str) -> Result<E2EOffline> {
+        Ok(
+            E2EOffline::from_passphrase_for_ciphertext(passphrase, &self.session, OsRng)
+                .map_err(|_| SessionError::WrongPassphrase)?,
+        )
+    }
+
+    /// Checks the version and kind, then decrypts the data with `vault`'s key.
+    pub(crate) fn open<T: DeserializeOwned, V: CryptoRng + RngCore>(
+        &self,
+        kind: ExportKind,
+        vault: &mut E2EOffline<V>,
+    ) -> Result<T> {
+        if self.version != SESSION_VERSION {
+            Err(SessionError::UnknownVersion(self.version))?;
+        }
+        if self.kind != kind {
+            Err(SessionError::WrongKind)?;
+        }
+
+        let decrypted = vault
+            .decrypt(&self.session)
+            .map_err(|_| SessionError::WrongPassphrase)?;
+
+        Ok(serde_json::from_str(decrypted.expose_secret())?)
+    }
+}
+
+// keys are kept the same way in session exports and saved handshakes
+pub(crate) fn encode_private_key(private_key: &PrivateKey) -> Result<String> {
+    Ok(base64::engine::general_purpose::URL_SAFE.encode(private_key.to_pkcs8_der()?.as_bytes()))
+}
+
+pub(crate) fn decode_private_key(encoded: &str) -> Result<PrivateKey> {
+    PrivateKey::from_pkcs8_der(&Zeroizing::new(
+        base64::engine::general_purpose::URL_SAFE.decode(encoded)?,
+    ))
+}
+
+pub(crate) fn encode_public_key(public_key: &PublicKey) -> Result<String> {
+    Ok(base64::engine::general_purpose::URL_SAFE.encode(public_key.to_der()?))
+}
+
+pub(crate) fn decode_public_key(encoded: &str) -> Result<PublicKey> {
+    identity::decode_identity(encoded)
+}
+
+// what is inside the encrypted part of a session export
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct SessionData {
+    key: String,
+    // missing from exports made before there was a choice of cipher suite
+    #[serde(default)]
+    cipher_suite: Option<String>,
+    previous_keys: Vec<PreviousKey>,
+    rekeyed: bool,
+    // missing from exports made before rekeys were counted; the session id
+    // then comes from the current key
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    rekey_sequence: u64,
+    #[serde(default)]
+    other_rekey_sequence: u64,
+    #[serde(default)]
+    my_rekey_key: Option<String>,
+    passphrase: Option<PassphraseData>,
+    my_private_key: Option<String>,
+    other_public_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Zeroize)]
+struct PreviousKey {
+    key: String,
+    remaining: usize,
+}
+
+#[derive(Serialize, Deserialize, Zeroize)]
+struct PassphraseData {
+    passphrase: String,
+    salt: String,
+    // missing from exports made before the other side's salt was pinned
+    #[serde(default)]
+    other_salt: Option<String>,
+}
+
+impl<R: CryptoRng + RngCore> E2EOffline<R> {
+    /// Saves everything needed to carry on this conversation: the current key,
+    /// keys still in their rekey grace period along with how many messages
+    /// each has left, the rekey counters, the passphrase settings and both
+    /// identities. It is encrypted with `export_passphrase`, which is only for
+    /// protecting the export and has nothing to do with the conversation's own
+    /// passphrase.
+    pub fn export_session(&self, export_passphrase: &str) -> Result<SessionExport> {
+        self.export_session_with(&mut E2EOffline::from_passphrase(export_passphrase)?)
+    }
+
+    /// Like [`Self::export_session`], but encrypted with `vault`'s key instead
+    /// of a passphrase, so many sessions can be kept under one passphrase
+    /// without stretching it again for each of them.
+    pub fn export_session_with<V: CryptoRng + RngCore>(
+        &self,
+        vault: &mut E2EOffline<V>,
+    ) -> Result<SessionExport> {
+        let url_safe = &base64::engine::general_purpose::URL_SAFE;
+
+        let data = SessionData {
+            key: url_safe.encode(&self.key),
+            cipher_suite: Some(self.cipher_suite.to_string()),
+            previous_keys: self
+                .previous_keys
+                .iter()
+                .map(|(key, remaining)| PreviousKey {
+                    key: url_safe.encode(key),
+                    remaining: *remaining,
+                })
+                .collect(),
+            rekeyed: self.rekeyed,
+            session_id: Some(url_safe.encode(self.session_id)),
+            rekey_sequence: self.rekey_sequence,
+            other_rekey_sequence: self.other_rekey_sequence,
+            my_rekey_key: self.my_rekey_key.as_ref().map(|key| url_safe.encode(key)),
+            passphrase: self.passphrase.as_ref().map(|passphrase| PassphraseData {
+                passphrase: passphrase.passphrase().to_string(),
+                salt: passphrase.salt_encoded().to_string(),
+                other_salt: passphrase.other_salt_encoded().map(str::to_string),
+            }),
+            my_private_key: self
+                .my_private_key
+                .as_ref()
+                .map(encode_private_key)
+                .transpose()?,
+            other_public_key: self
+                .other_public_key
+                .as_ref()
+                .map(encode_public_key)
+                .transpose()?,
+        };
+
+        SessionExport::seal(ExportKind::Session, &data, vault)
+    }
+}
+
+impl E2EOffline {
+    /// Restores a conversation saved with [`Self::export_session`].
+    pub fn import_session(export: &SessionExport, export_passphrase: &str) -> Result<E2EOffline> {
+        Self::import_session_with(export, &mut export.passphrase_vault(export_passphrase)?)
+    }
+
+    /// Restores a conversation saved with [`Self::export_session_with`]. The
+    /// wrong `vault` fails just like a wrong passphrase.
+    pub fn import_session_with<V: CryptoRng + RngCore>(
+        export: &SessionExport,
+        vault: &mut E2EOffline<V>,
+    ) -> Result<E2EOffline> {
+        let url_safe = &base64::engine::general_purpose::URL_SAFE;
+
+        let data: SessionData = export.open(ExportKind::Session, vault)?;
+
+        let mut e2e = E2EOffline::new(&Zeroizing::new(url_safe.decode(&data.key)?), OsRng)?;
+
+        for previous_key in &data.previous_keys {
+            let key = Zeroizing::new(url_safe.decode(&previous_key.key)?);
+            e2e.previous_keys.push((key, previous_key.remaining));
+        }
+        e2e.rekeyed = data.rekeyed;
+
+        if let Some(session_id) = &data.session_id {
+            e2e.session_id = url_safe
+                .decode(session_id)?
+                .try_into()
+                .map_err(|_| SessionError::InvalidSessionId)?;
+        }
+        e2e.rekey_sequence = data.rekey_sequence;
+        e2e.other_rekey_sequence = data.other_rekey_sequence;
+        if let Some(my_rekey_key) = &data.my_rekey_key {
+            e2e.my_rekey_key
+                .replace(Zeroizing::new(url_safe.decode(my_rekey_key)?));
+        }
+
+        if let Some(cipher_suite) = &data.cipher_suite {
+            e2e.cipher_suite = cipher_suite.parse()?;
+        }
+
+        if let Some(passphrase) = &data.passphrase {
+            e2e.passphrase.replace(PassphraseKeys::restore(
+                &passphrase.passphrase,
+                &passphrase.salt,
+                passphrase.other_salt.as_deref(),
+            )?);
+        }
+        if let Some(private_key) = &data.my_private_key {
+            e2e.my_private_key.replace(decode_private_key(private_key)?);
+        }
+        if let Some(public_key) = &data.other_public_key {
+            e2e.other_public_key.replace(decode_public_key(public_key)?);
+        }
+
+        Ok(e2e)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum SessionError {
+    UnknownVersion(u32),
+    WrongPassphrase,
+    WrongKind,
+    InvalidSessionId,
+}
+
+impl Display for SessionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SessionError::UnknownVersion(version) => {
+                write!(f, "unknown session export version {version}")
+            }
+            SessionError::WrongPassphrase => {
+                write!(f, "wrong passphrase for session export or saved handshake")
+            }
+            SessionError::WrongKind => {
+                write!(f, "a saved handshake and a session export can't be swapped")
+            }
+            SessionError::InvalidSessionId => write!(f, "invalid session id in session export"),
+        }
+    }
+}
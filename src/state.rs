@@ -0,0 +1,220 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Display;
+
+use aes_gcm::{Aes256Gcm, KeyInit};
+use base64::Engine;
+use rand::{rngs::OsRng, CryptoRng, RngCore};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::{
+    hybrid::{HybridPublic, HybridSecrets},
+    identity::{self, IdentityError},
+    prekey::{PrekeyBundle, PrekeySecrets},
+    session::{self, ExportKind},
+    E2EOffline, E2EOfflineBuilder, E2EType, Result, SessionExport,
+};
+
+// what is inside the encrypted part of a saved handshake, kept in the same
+// envelope as a session export
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct HandshakeData {
+    role: String,
+    my_private_key: String,
+    other_public_key: Option<String>,
+    other_expires_at: Option<u64>,
+    shared_key: Option<String>,
+    cipher_suite: String,
+    hello_nonce: Option<String>,
+    prekeys: Option<String>,
+    prekey_bundle: Option<String>,
+    hybrid_secrets: Option<String>,
+    hybrid_public: Option<String>,
+    expires_at: Option<u64>,
+    revoked: Vec<String>,
+}
+
+impl<R: CryptoRng + RngCore> E2EOfflineBuilder<R> {
+    /// Saves an unfinished handshake, encrypted with a passphrase, so it can be
+    /// picked up again with [`Self::import_state`] once the other side
+    /// replies. The state holds this side's private key, so it needs to be
+    /// kept as safe as the key itself.
+    pub fn export_state(&self, passphrase: &str) -> Result<SessionExport> {
+        self.export_state_with(&mut E2EOffline::from_passphrase(passphrase)?)
+    }
+
+    /// Like [`Self::export_state`], but encrypted with `vault`'s key instead
+    /// of a passphrase, as with [`E2EOffline::export_session_with`].
+    pub fn export_state_with<V: CryptoRng + RngCore>(
+        &self,
+        vault: &mut E2EOffline<V>,
+    ) -> Result<SessionExport> {
+        let url_safe = &base64::engine::general_purpose::URL_SAFE;
+
+        let (role, other_public_key) = match self.my_type {
+            E2EType::Sender => ("sender", &self.reciever_public_key),
+            E2EType::Reciever => ("reciever", &self.sender_public_key),
+        };
+
+        let data = HandshakeData {
+            role: role.to_string(),
+            my_private_key: session::encode_private_key(&self.my_private_key)?,
+            other_public_key: other_public_key
+                .as_ref()
+                .map(session::encode_public_key)
+                .transpose()?,
+            other_expires_at: self.other_expires_at,
+            shared_key: self.shared_key.as_ref().map(|key| url_safe.encode(key)),
+            cipher_suite: self.cipher_suite.to_string(),
+            hello_nonce: self
+                .hello_nonce
+                .as_ref()
+                .map(|nonce| url_safe.encode(nonce)),
+            prekeys: self.prekeys.as_ref().map(PrekeySecrets::secret_encoded),
+            prekey_bundle: self.prekey_bundle.as_ref().map(PrekeyBundle::encoded),
+            hybrid_secrets: self
+                .hybrid_secrets
+                .as_ref()
+                .map(HybridSecrets::secret_encoded),
+            hybrid_public: self.hybrid_public.as_ref().map(HybridPublic::encoded),
+            expires_at: self.expires_at,
+            revoked: self.revoked.clone(),
+        };
+
+        SessionExport::seal(ExportKind::Handshake, &data, vault)
+    }
+
+    /// Like [`E2EOfflineBuilder::import_state_with`], but the rest of the
+    /// handshake takes its randomness from `rng`, as with
+    /// [`Self::new_sender_with_rng`].
+    pub fn import_state_with_rng<V: CryptoRng + RngCore>(
+        state: &SessionExport,
+        vault: &mut E2EOffline<V>,
+        rng: R,
+    ) -> Result<Self> {
+        let url_safe = &base64::engine::general_purpose::URL_SAFE;
+
+        let data: HandshakeData = state.open(ExportKind::Handshake, vault)?;
+
+        let my_type = match data.role.as_str() {
+            "sender" => E2EType::Sender,
+            "reciever" => E2EType::Reciever,
+            _ => Err(StateError::InvalidState)?,
+        };
+        let my_private_key = session::decode_private_key(&data.my_private_key)?;
+        let other_public_key = data
+            .other_public_key
+            .as_deref()
+            .map(session::decode_public_key)
+            .transpose()?;
+
+        // the other side's key may have expired while the handshake was saved
+        if let Some(other_expires_at) = data.other_expires_at {
+            if other_expires_at <= identity::now().ok_or(IdentityError::NoClock)? {
+                Err(IdentityError::Expired)?;
+            }
+        }
+
+        let my_public_key = Some(my_private_key.public_key());
+        let (sender_public_key, reciever_public_key) = match my_type {
+            E2EType::Sender => (my_public_key, other_public_key),
+            E2EType::Reciever => (other_public_key, my_public_key),
+        };
+
+        let shared_key = match &data.shared_key {
+            Some(shared_key) => {
+                let shared_key = Zeroizing::new(url_safe.decode(shared_key)?);
+                Aes256Gcm::new_from_slice(&shared_key)?;
+                Some(shared_key)
+            }
+            None => None,
+        };
+
+        let prekey_bundle = match &data.prekey_bundle {
+            Some(prekey_bundle) => {
+                let (signed_prekey, one_time_prekeys) = prekey_bundle
+                    .split_once('.')
+                    .ok_or(StateError::InvalidState)?;
+                Some(PrekeyBundle::decode(signed_prekey, one_time_prekeys)?)
+            }
+            None => None,
+        };
+        let hybrid_secrets = match &data.hybrid_secrets {
+            Some(hybrid_secrets) => {
+                let (dk, x25519) = hybrid_secrets
+                    .split_once('.')
+                    .ok_or(StateError::InvalidState)?;
+                Some(HybridSecrets::decode_secret(dk, x25519)?)
+            }
+            None => None,
+        };
+        let hybrid_public = match &data.hybrid_public {
+            Some(hybrid_public) => {
+                let (ek, x25519) = hybrid_public
+                    .split_once('.')
+                    .ok_or(StateError::InvalidState)?;
+                Some(HybridPublic::decode(ek, x25519)?)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            rng,
+            reciever_public_key,
+            sender_public_key,
+            my_private_key,
+            shared_key,
+            cipher_suite: data.cipher_suite.parse()?,
+            hello_nonce: data
+                .hello_nonce
+                .as_deref()
+                .map(|nonce| url_safe.decode(nonce))
+                .transpose()?,
+            prekeys: data
+                .prekeys
+                .as_deref()
+                .map(PrekeySecrets::decode_secret)
+                .transpose()?,
+            prekey_bundle,
+            hybrid_secrets,
+            hybrid_public,
+            expires_at: data.expires_at,
+            other_expires_at: data.other_expires_at,
+            revoked: data.revoked.clone(),
+            my_type,
+        })
+    }
+}
+
+impl E2EOfflineBuilder {
+    /// Restores a handshake saved with [`Self::export_state`].
+    pub fn import_state(state: &SessionExport, passphrase: &str) -> Result<Self> {
+        Self::import_state_with(state, &mut state.passphrase_vault(passphrase)?)
+    }
+
+    /// Restores a handshake saved with [`Self::export_state_with`]. The wrong
+    /// `vault` fails just like a wrong passphrase.
+    pub fn import_state_with<V: CryptoRng + RngCore>(
+        state: &SessionExport,
+        vault: &mut E2EOffline<V>,
+    ) -> Result<Self> {
+        Self::import_state_with_rng(state, vault, OsRng)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum StateError {
+    InvalidState,
+}
+
+impl Display for StateError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StateError::InvalidState => write!(f, "invalid saved handshake"),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Fixtures shared by the integration tests and the benches.
+
+// each test and bench uses only some of these
+#![allow(dead_code)]
+
+use e2eoffline::{CipherSuite, E2EOffline, E2EOfflineBuilder, HandshakeMode, Identity};
+
+pub const KEY: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+
+#[derive(Clone, Copy, Debug)]
+pub enum Mode {
+    Classic,
+    Hybrid,
+    Prekey,
+}
+
+impl Mode {
+    pub const ALL: [Mode; 3] = [Mode::Classic, Mode::Hybrid, Mode::Prekey];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Classic => "classic",
+            Mode::Hybrid => "hybrid",
+            Mode::Prekey => "prekey",
+        }
+    }
+}
+
+pub const SUITES: [CipherSuite; 3] = [
+    CipherSuite::Aes256Gcm,
+    CipherSuite::XChaCha20Poly1305,
+    CipherSuite::Aes256GcmSiv,
+];
+
+pub fn reciever(identity: &Identity, mode: Mode) -> E2EOfflineBuilder {
+    let mode = match mode {
+        Mode::Hybrid => HandshakeMode::Hybrid,
+        _ => HandshakeMode::Classic,
+    };
+
+    E2EOfflineBuilder::new_reciever_with_identity(identity, mode)
+}
+
+/// What the reciever publishes: its hello, or its prekey bundle.
+pub fn hello(reciever: &mut E2EOfflineBuilder, mode: Mode) -> String {
+    match mode {
+        Mode::Prekey => reciever.get_prekey_bundle_encoded(1).unwrap(),
+        _ => reciever.get_hello_encoded().unwrap(),
+    }
+}
+
+pub fn set_hello(sender: &mut E2EOfflineBuilder, mode: Mode, hello: &str) {
+    match mode {
+        Mode::Prekey => sender.set_prekey_bundle_encoded(hello).unwrap(),
+        _ => sender.set_other_public_key_encoded(hello).unwrap(),
+    }
+}
+
+/// Both sides of a handshake, up to where the reciever has the sender's
+/// exchange text but hasn't read it yet.
+pub struct Handshake {
+    pub reciever: E2EOfflineBuilder,
+    pub sender: E2EOfflineBuilder,
+    pub exchange: String,
+}
+
+pub fn handshake(
+    reciever_identity: &Identity,
+    sender_identity: &Identity,
+    mode: Mode,
+    cipher_suite: CipherSuite,
+) -> Handshake {
+    let mut reciever = reciever(reciever_identity, mode);
+    let mut sender = E2EOfflineBuilder::new_sender_with_identity(sender_identity);
+
+    let hello = hello(&mut reciever, mode);
+    set_hello(&mut sender, mode, &hello);
+    reciever
+        .set_other_public_key_encoded(&sender.get_pubkey_encoded().unwrap())
+        .unwrap();
+
+    sender.set_cipher_suite(cipher_suite);
+    let exchange = sender.send().unwrap();
+
+    Handshake {
+        reciever,
+        sender,
+        exchange,
+    }
+}
+
+/// The sender's and reciever's sessions from a finished handshake.
+pub fn sessions(mode: Mode, cipher_suite: CipherSuite) -> (E2EOffline, E2EOffline) {
+    let Handshake {
+        mut reciever,
+        sender,
+        exchange,
+    } = handshake(
+        &Identity::generate(),
+        &Identity::generate(),
+        mode,
+        cipher_suite,
+    );
+
+    reciever.recieve(&exchange).unwrap();
+
+    (sender.build().unwrap(), reciever.build().unwrap())
+}
+
+/// Whether a message from `from` decrypts on `to`.
+pub fn round_trips(from: &mut E2EOffline, to: &mut E2EOffline) -> bool {
+    let ciphertext = from.encrypt("hello").unwrap();
+
+    to.decrypt(&ciphertext)
+        .is_ok_and(|plaintext| plaintext.expose_secret() == "hello")
+}
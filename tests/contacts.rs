@@ -0,0 +1,82 @@
+use e2eoffline::{error::ContactError, ContactBook, Error, Identity, Trust, Verification};
+
+fn fingerprint() -> String {
+    Identity::generate().get_fingerprint().unwrap()
+}
+
+#[test]
+fn first_key_is_trusted_and_a_changed_one_is_refused() {
+    let (alice, mallory) = (fingerprint(), fingerprint());
+    let mut book = ContactBook::new();
+
+    assert_eq!(book.check("alice", &alice, None).unwrap(), Trust::FirstSeen);
+    assert_eq!(
+        book.check("alice", &alice, None).unwrap(),
+        Trust::Known(Verification::Unverified)
+    );
+
+    assert!(matches!(
+        book.check("alice", &mallory, None),
+        Err(Error::Contact(ContactError::KeyChanged(name))) if name == "alice"
+    ));
+    assert_eq!(book.get("alice").unwrap().fingerprint, alice);
+
+    // forgetting is how a new key gets accepted
+    book.forget("alice");
+    assert_eq!(
+        book.check("alice", &mallory, None).unwrap(),
+        Trust::FirstSeen
+    );
+}
+
+#[test]
+fn names_are_trimmed_everywhere() {
+    let alice = fingerprint();
+    let mut book = ContactBook::new();
+
+    book.check("  alice ", &alice, None).unwrap();
+    assert_eq!(book.contacts()[0].name, "alice");
+    assert!(book.get(" alice").is_some());
+
+    book.mark_verified("alice  ").unwrap();
+    assert_eq!(
+        book.check("alice", &alice, None).unwrap(),
+        Trust::Known(Verification::VerifiedInPerson)
+    );
+
+    book.forget(" alice\t");
+    assert!(book.contacts().is_empty());
+
+    assert!(matches!(
+        book.mark_verified(" alice "),
+        Err(Error::Contact(ContactError::UnknownContact))
+    ));
+    assert!(matches!(
+        book.check("   ", &alice, None),
+        Err(Error::Contact(ContactError::InvalidName))
+    ));
+}
+
+#[test]
+fn book_survives_encoding() {
+    let (alice, bob) = (fingerprint(), fingerprint());
+    let mut book = ContactBook::new();
+
+    book.check("alice", &alice, None).unwrap();
+    book.check("bob the builder", &bob, Some(4_000_000_000))
+        .unwrap();
+    book.mark_verified("alice").unwrap();
+    book.add_revocation_certificate_encoded(
+        &e2eoffline::E2EOfflineBuilder::new_sender()
+            .get_revocation_certificate_encoded()
+            .unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(ContactBook::from_encoded(&book.to_encoded()).unwrap(), book);
+
+    assert!(matches!(
+        ContactBook::from_encoded(&format!("{alice} trusted alice")),
+        Err(Error::Contact(ContactError::InvalidLine))
+    ));
+}
@@ -0,0 +1,101 @@
+use e2eoffline::{E2EOffline, E2EOfflineBuilder, HandshakeMode, Identity};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+// everything random in a whole conversation, from keygen to the first
+// message, comes from the seed
+fn conversation(seed: u64, mode: HandshakeMode) -> Vec<String> {
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+
+    let reciever_identity = Identity::generate_with_rng(&mut rng);
+    let sender_identity = Identity::generate_with_rng(&mut rng);
+
+    let mut reciever = E2EOfflineBuilder::new_reciever_with_rng(
+        &reciever_identity,
+        mode,
+        ChaCha20Rng::seed_from_u64(seed + 1),
+    );
+    let mut sender = E2EOfflineBuilder::new_sender_with_rng(
+        &sender_identity,
+        ChaCha20Rng::seed_from_u64(seed + 2),
+    );
+
+    let hello = reciever.get_hello_encoded().unwrap();
+    sender.set_other_public_key_encoded(&hello).unwrap();
+    reciever
+        .set_other_public_key_encoded(&sender.get_pubkey_encoded().unwrap())
+        .unwrap();
+
+    let exchange = sender.send().unwrap();
+    reciever.recieve(&exchange).unwrap();
+
+    let mut sender = sender.build().unwrap();
+    let mut reciever = reciever.build().unwrap();
+
+    let ciphertext = sender.encrypt("hello").unwrap();
+    assert_eq!(
+        reciever.decrypt(&ciphertext).unwrap().expose_secret(),
+        "hello"
+    );
+
+    vec![
+        reciever_identity.get_pubkey_pem().unwrap(),
+        hello,
+        exchange,
+        ciphertext,
+        sender.rekey().unwrap(),
+    ]
+}
+
+#[test]
+fn keygen_is_reproducible() {
+    let first = Identity::generate_with_rng(&mut ChaCha20Rng::seed_from_u64(7));
+    let second = Identity::generate_with_rng(&mut ChaCha20Rng::seed_from_u64(7));
+    let other = Identity::generate_with_rng(&mut ChaCha20Rng::seed_from_u64(8));
+
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+}
+
+#[test]
+fn classic_conversation_is_reproducible() {
+    assert_eq!(
+        conversation(1, HandshakeMode::Classic),
+        conversation(1, HandshakeMode::Classic)
+    );
+    assert_ne!(
+        conversation(1, HandshakeMode::Classic),
+        conversation(10, HandshakeMode::Classic)
+    );
+}
+
+#[test]
+fn hybrid_conversation_is_reproducible() {
+    assert_eq!(
+        conversation(3, HandshakeMode::Hybrid),
+        conversation(3, HandshakeMode::Hybrid)
+    );
+}
+
+#[test]
+fn encrypt_is_reproducible() {
+    let key = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+
+    let encrypt = |seed| {
+        E2EOffline::from_key_base64_with_rng(key, ChaCha20Rng::seed_from_u64(seed))
+            .unwrap()
+            .encrypt("hello")
+            .unwrap()
+    };
+
+    assert_eq!(encrypt(5), encrypt(5));
+    assert_ne!(encrypt(5), encrypt(6));
+
+    let passphrase_encrypt = |seed| {
+        E2EOffline::from_passphrase_with_rng("correct horse", ChaCha20Rng::seed_from_u64(seed))
+            .unwrap()
+            .encrypt("hello")
+            .unwrap()
+    };
+
+    assert_eq!(passphrase_encrypt(5), passphrase_encrypt(5));
+}
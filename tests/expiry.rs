@@ -0,0 +1,154 @@
+use e2eoffline::{
+    error::{ContactError, IdentityError},
+    ContactBook, E2EOfflineBuilder, Error, Identity, Trust,
+};
+
+// far enough ahead that it won't pass while the tests run
+const LATER: u64 = 4_000_000_000;
+
+fn sender(identity: &Identity, expires_at: Option<u64>) -> E2EOfflineBuilder {
+    let mut sender = E2EOfflineBuilder::new_sender_with_identity(identity);
+    if let Some(expires_at) = expires_at {
+        sender.set_expiry(expires_at);
+    }
+
+    sender
+}
+
+fn reciever() -> E2EOfflineBuilder {
+    E2EOfflineBuilder::new_reciever()
+}
+
+#[test]
+fn expiring_key_is_accepted_until_it_expires() {
+    let identity = Identity::generate();
+    let mut reciever = reciever();
+
+    reciever
+        .set_other_public_key_encoded(&sender(&identity, Some(LATER)).get_pubkey_encoded().unwrap())
+        .unwrap();
+    assert_eq!(reciever.get_other_expiry(), Some(LATER));
+
+    assert!(matches!(
+        reciever.set_other_public_key_encoded(
+            &sender(&identity, Some(1)).get_pubkey_encoded().unwrap()
+        ),
+        Err(Error::Identity(IdentityError::Expired))
+    ));
+}
+
+#[test]
+fn expiry_cant_be_moved() {
+    let identity = Identity::generate();
+    let pubkey = sender(&identity, Some(1)).get_pubkey_encoded().unwrap();
+
+    let mut parts: Vec<&str> = pubkey.split('~').collect();
+    let later = LATER.to_string();
+    parts[1] = &later;
+
+    assert!(matches!(
+        reciever().set_other_public_key_encoded(&parts.join("~")),
+        Err(Error::Identity(IdentityError::FailedSignatureCheck))
+    ));
+}
+
+#[test]
+fn stripped_expiry_is_refused_by_the_contact_book() {
+    let identity = Identity::generate();
+    let pubkey = sender(&identity, Some(LATER)).get_pubkey_encoded().unwrap();
+    let mut book = ContactBook::new();
+
+    let mut reciever = reciever();
+    reciever.set_other_public_key_encoded(&pubkey).unwrap();
+    let fingerprint = reciever.get_other_fingerprint().unwrap();
+    assert_eq!(
+        book.check("alice", &fingerprint, reciever.get_other_expiry())
+            .unwrap(),
+        Trust::FirstSeen
+    );
+
+    // the bare key decodes on its own, as never expiring
+    let (stripped, _) = pubkey.split_once('~').unwrap();
+    let mut reciever = self::reciever();
+    reciever.set_other_public_key_encoded(stripped).unwrap();
+    assert_eq!(reciever.get_other_expiry(), None);
+
+    // but the contact book remembers the key had an expiry, under any name
+    let book = ContactBook::from_encoded(&book.to_encoded()).unwrap();
+    for name in ["alice", "mallory"] {
+        assert!(matches!(
+            book.clone().check(name, &fingerprint, None),
+            Err(Error::Contact(ContactError::ExpiryStripped(_)))
+        ));
+    }
+
+    // an expiry signed later replaces the old one
+    let mut book = book;
+    assert!(book.check("alice", &fingerprint, Some(LATER + 1)).is_ok());
+    assert!(book.to_encoded().contains(&(LATER + 1).to_string()));
+}
+
+#[test]
+fn key_without_expiry_can_gain_one() {
+    let identity = Identity::generate();
+    let fingerprint = identity.get_fingerprint().unwrap();
+    let mut book = ContactBook::new();
+
+    book.check("alice", &fingerprint, None).unwrap();
+    book.check("alice", &fingerprint, Some(LATER)).unwrap();
+    assert!(matches!(
+        book.check("alice", &fingerprint, None),
+        Err(Error::Contact(ContactError::ExpiryStripped(_)))
+    ));
+
+    // forgetting the contact forgets the expiry too
+    book.forget("alice");
+    assert_eq!(
+        book.check("alice", &fingerprint, None).unwrap(),
+        Trust::FirstSeen
+    );
+}
+
+#[test]
+fn revoked_key_is_refused() {
+    let identity = Identity::generate();
+    let sender = sender(&identity, None);
+    let certificate = sender.get_revocation_certificate_encoded().unwrap();
+
+    let mut reciever = reciever();
+    reciever
+        .add_revocation_certificate_encoded(&certificate)
+        .unwrap();
+    assert!(matches!(
+        reciever.set_other_public_key_encoded(&sender.get_pubkey_encoded().unwrap()),
+        Err(Error::Identity(IdentityError::Revoked))
+    ));
+
+    let mut book = ContactBook::new();
+    book.add_revocation_certificate_encoded(&certificate)
+        .unwrap();
+    assert!(matches!(
+        book.check("alice", &identity.get_fingerprint().unwrap(), None),
+        Err(Error::Contact(ContactError::Revoked(_)))
+    ));
+}
+
+#[test]
+fn forged_revocation_is_refused() {
+    let certificate = sender(&Identity::generate(), None)
+        .get_revocation_certificate_encoded()
+        .unwrap();
+    let other = sender(&Identity::generate(), None)
+        .get_revocation_certificate_encoded()
+        .unwrap();
+
+    // one key's revocation text with another key's signature
+    let (signed_text, _) = certificate.rsplit_once('.').unwrap();
+    let (_, signature) = other.rsplit_once('.').unwrap();
+
+    assert!(matches!(
+        ContactBook::new()
+            .add_revocation_certificate_encoded(&format!("{signed_text}.{signature}")),
+        Err(Error::Identity(IdentityError::InvalidRevocation))
+    ));
+}
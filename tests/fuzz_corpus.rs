@@ -0,0 +1,45 @@
+//! Replays the checked-in fuzz corpus, including every input that has made a
+//! fuzz target panic, so those stay fixed without needing cargo-fuzz.
+
+use std::{fs, path::PathBuf};
+
+#[path = "../fuzz/src/lib.rs"]
+mod fuzz;
+
+fn replay(target: &str, run: fn(&[u8])) {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz/corpus")
+        .join(target);
+
+    for entry in fs::read_dir(dir).unwrap() {
+        run(&fs::read(entry.unwrap().path()).unwrap());
+    }
+}
+
+macro_rules! corpus_tests {
+    ($($target:ident),*) => {
+        $(
+            #[test]
+            fn $target() {
+                replay(stringify!($target), fuzz::$target);
+            }
+        )*
+    };
+}
+
+corpus_tests!(
+    hello,
+    pubkey,
+    prekey_bundle,
+    recieve,
+    key_base64,
+    decrypt,
+    rekey,
+    state,
+    session,
+    contacts,
+    revocation,
+    private_key,
+    pake,
+    handshake
+);
@@ -0,0 +1,91 @@
+use e2eoffline::{
+    error::{HelloError, HybridError, RecieveError},
+    E2EOfflineBuilder, Error, HandshakeMode, Identity,
+};
+
+#[test]
+fn hello_is_checked_before_its_key_is_used() {
+    let identity = Identity::generate();
+    let reciever = E2EOfflineBuilder::new_reciever_with_identity(&identity, HandshakeMode::Classic);
+    let mut sender = E2EOfflineBuilder::new_sender_with_identity(&identity);
+
+    let hello = reciever.get_hello_encoded().unwrap();
+    sender.set_other_public_key_encoded(&hello).unwrap();
+    assert_eq!(
+        sender.get_other_fingerprint().unwrap(),
+        reciever.get_fingerprint().unwrap()
+    );
+
+    // a bare public key is no longer enough for a sender
+    assert!(matches!(
+        sender.set_other_public_key_encoded(&reciever.get_pubkey_encoded().unwrap()),
+        Err(Error::Hello(HelloError::InvalidString))
+    ));
+
+    // the nonce is signed along with the key
+    let (signed_text, signature) = hello.rsplit_once('.').unwrap();
+    let (pubkey, _) = signed_text.rsplit_once('.').unwrap();
+    let other_nonce = "AAAAAAAAAAAAAAAAAAAAAA==";
+    assert!(matches!(
+        sender.set_other_public_key_encoded(&format!("{pubkey}.{other_nonce}.{signature}")),
+        Err(Error::Hello(HelloError::FailedSignatureCheck))
+    ));
+}
+
+#[test]
+fn exchange_is_bound_to_the_hello_nonce() {
+    let identity = Identity::generate();
+    let first = E2EOfflineBuilder::new_reciever_with_identity(&identity, HandshakeMode::Classic);
+    let mut second =
+        E2EOfflineBuilder::new_reciever_with_identity(&identity, HandshakeMode::Classic);
+    let mut sender = E2EOfflineBuilder::new_sender_with_identity(&identity);
+
+    // the same identity, but a fresh nonce for each hello
+    assert_ne!(
+        first.get_hello_encoded().unwrap(),
+        second.get_hello_encoded().unwrap()
+    );
+
+    sender
+        .set_other_public_key_encoded(&first.get_hello_encoded().unwrap())
+        .unwrap();
+    second
+        .set_other_public_key_encoded(&sender.get_pubkey_encoded().unwrap())
+        .unwrap();
+
+    assert!(matches!(
+        second.recieve(&sender.send().unwrap()),
+        Err(Error::Recieve(RecieveError::FailedSignatureCheck))
+    ));
+}
+
+#[test]
+fn hybrid_reciever_refuses_a_classic_exchange() {
+    let identity = Identity::generate();
+    let classic = E2EOfflineBuilder::new_reciever_with_identity(&identity, HandshakeMode::Classic);
+    let mut hybrid =
+        E2EOfflineBuilder::new_reciever_with_identity(&identity, HandshakeMode::Hybrid);
+    let mut sender = E2EOfflineBuilder::new_sender_with_identity(&identity);
+
+    sender
+        .set_other_public_key_encoded(&classic.get_hello_encoded().unwrap())
+        .unwrap();
+    hybrid
+        .set_other_public_key_encoded(&sender.get_pubkey_encoded().unwrap())
+        .unwrap();
+
+    assert!(matches!(
+        hybrid.recieve(&sender.send().unwrap()),
+        Err(Error::Hybrid(HybridError::NotHybrid))
+    ));
+}
+
+#[test]
+fn only_a_reciever_has_a_hello() {
+    let sender = E2EOfflineBuilder::new_sender();
+
+    assert!(matches!(
+        sender.get_hello_encoded(),
+        Err(Error::Hello(HelloError::NotReciever))
+    ));
+}
@@ -0,0 +1,116 @@
+use e2eoffline::{
+    CipherSuite, ContactBook, E2EOffline, E2EOfflineBuilder, Identity, Trust, Verification,
+};
+
+mod common;
+
+use common::{Handshake, Mode};
+
+// one handshake between a long-term reciever identity and a sender identity
+fn handshake(
+    reciever_identity: &Identity,
+    sender_identity: &Identity,
+) -> (E2EOfflineBuilder, E2EOfflineBuilder) {
+    let Handshake {
+        mut reciever,
+        sender,
+        exchange,
+    } = common::handshake(
+        reciever_identity,
+        sender_identity,
+        Mode::Classic,
+        CipherSuite::default(),
+    );
+    reciever.recieve(&exchange).unwrap();
+
+    (sender, reciever)
+}
+
+fn raw_key(session: &E2EOffline) -> String {
+    session
+        .dangerously_export_raw_key()
+        .expose_secret()
+        .to_string()
+}
+
+#[test]
+fn identity_keeps_its_key_across_handshakes() {
+    let (alice, bob) = (Identity::generate(), Identity::generate());
+    let mut book = ContactBook::new();
+
+    let mut keys = Vec::new();
+    for i in 0..3 {
+        let (sender, reciever) = handshake(&alice, &bob);
+
+        assert_eq!(sender.get_identity(), bob);
+        assert_eq!(reciever.get_identity(), alice);
+        assert_eq!(
+            sender.get_other_fingerprint().unwrap(),
+            alice.get_fingerprint().unwrap()
+        );
+
+        // the reciever's contact book pins the sender after the first time
+        let trust = book
+            .check("bob", &reciever.get_other_fingerprint().unwrap(), None)
+            .unwrap();
+        let expected = match i {
+            0 => Trust::FirstSeen,
+            _ => Trust::Known(Verification::Unverified),
+        };
+        assert_eq!(trust, expected);
+
+        let mut sender = sender.build().unwrap();
+        let mut reciever = reciever.build().unwrap();
+        let decrypted = reciever.decrypt(&sender.encrypt("hello").unwrap()).unwrap();
+        assert_eq!(decrypted.expose_secret(), "hello");
+
+        keys.push(raw_key(&sender));
+    }
+
+    // the identity is long-term, but every session gets a fresh key
+    keys.sort();
+    keys.dedup();
+    assert_eq!(keys.len(), 3);
+}
+
+#[test]
+fn identity_can_play_either_role() {
+    let identity = Identity::generate();
+    let other = Identity::generate();
+
+    let (_, as_reciever) = handshake(&identity, &other);
+    let (as_sender, _) = handshake(&other, &identity);
+
+    assert_eq!(
+        as_reciever.get_pubkey_encoded().unwrap(),
+        as_sender.get_pubkey_encoded().unwrap()
+    );
+    assert_eq!(
+        as_reciever.get_fingerprint().unwrap(),
+        identity.get_fingerprint().unwrap()
+    );
+}
+
+#[test]
+fn identity_loaded_from_a_file_is_the_same_identity() {
+    let identity = Identity::generate();
+    let pem = identity.dangerously_export_private_key_pem().unwrap();
+    let loaded = Identity::from_private_key_pem(pem.expose_secret(), "").unwrap();
+
+    let (sender, _) = handshake(&loaded, &Identity::generate());
+    assert_eq!(
+        sender.get_other_fingerprint().unwrap(),
+        identity.get_fingerprint().unwrap()
+    );
+}
+
+#[test]
+fn new_builders_without_an_identity_get_fresh_keys() {
+    let first = E2EOfflineBuilder::new_sender();
+    let second = E2EOfflineBuilder::new_sender();
+
+    assert_ne!(
+        first.get_fingerprint().unwrap(),
+        second.get_fingerprint().unwrap()
+    );
+}
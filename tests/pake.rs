@@ -0,0 +1,119 @@
+use e2eoffline::{error::PakeError, E2EOfflinePakeBuilder, Error};
+
+const CODE: &str = "123-456";
+
+#[test]
+fn matching_codes_pair() {
+    let mut sender = E2EOfflinePakeBuilder::new_sender(CODE);
+    let mut reciever = E2EOfflinePakeBuilder::new_reciever(CODE);
+
+    reciever.recieve(&sender.send().unwrap()).unwrap();
+    sender.recieve(&reciever.send().unwrap()).unwrap();
+    reciever.recieve(&sender.send().unwrap()).unwrap();
+
+    let mut sender = sender.build().unwrap();
+    let mut reciever = reciever.build().unwrap();
+
+    let decrypted = reciever.decrypt(&sender.encrypt("hello").unwrap()).unwrap();
+    assert_eq!(decrypted.expose_secret(), "hello");
+}
+
+#[test]
+fn wrong_code_is_caught_by_both_sides() {
+    let mut sender = E2EOfflinePakeBuilder::new_sender(CODE);
+    let mut reciever = E2EOfflinePakeBuilder::new_reciever("123-457");
+
+    reciever.recieve(&sender.send().unwrap()).unwrap();
+    assert!(matches!(
+        sender.recieve(&reciever.send().unwrap()),
+        Err(Error::Pake(PakeError::WrongCode))
+    ));
+
+    // the sender has nothing to confirm with, so the reciever never builds a
+    // session that can't decrypt anything
+    assert!(matches!(
+        sender.send(),
+        Err(Error::Pake(PakeError::WrongCode))
+    ));
+    assert!(matches!(
+        reciever.build(),
+        Err(Error::Pake(PakeError::Unconfirmed))
+    ));
+    assert!(matches!(
+        sender.build(),
+        Err(Error::Pake(PakeError::WrongCode))
+    ));
+}
+
+#[test]
+fn forged_sender_confirmation_is_refused() {
+    let mut sender = E2EOfflinePakeBuilder::new_sender(CODE);
+    let mut reciever = E2EOfflinePakeBuilder::new_reciever(CODE);
+
+    reciever.recieve(&sender.send().unwrap()).unwrap();
+    sender.recieve(&reciever.send().unwrap()).unwrap();
+
+    assert!(matches!(
+        reciever.recieve("cpace.AAAAAAAAAAAAAAAAAAAAAA=="),
+        Err(Error::Pake(PakeError::WrongCode))
+    ));
+    assert!(matches!(
+        reciever.build(),
+        Err(Error::Pake(PakeError::WrongCode))
+    ));
+}
+
+#[test]
+fn no_second_attempt_after_a_wrong_code() {
+    let mut sender = E2EOfflinePakeBuilder::new_sender(CODE);
+    let mut reciever = E2EOfflinePakeBuilder::new_reciever(CODE);
+
+    reciever.recieve(&sender.send().unwrap()).unwrap();
+    reciever.send().unwrap();
+    assert!(matches!(
+        reciever.recieve("cpace.AAAAAAAAAAAAAAAAAAAAAA=="),
+        Err(Error::Pake(PakeError::WrongCode))
+    ));
+
+    // a fresh share under another guess gets nowhere, nor does the real
+    // sender's share
+    let mut guess = E2EOfflinePakeBuilder::new_sender("654-321");
+    assert!(matches!(
+        reciever.recieve(&guess.send().unwrap()),
+        Err(Error::Pake(PakeError::WrongCode))
+    ));
+    assert!(matches!(
+        reciever.recieve(&sender.send().unwrap()),
+        Err(Error::Pake(PakeError::WrongCode))
+    ));
+    assert!(matches!(
+        reciever.send(),
+        Err(Error::Pake(PakeError::WrongCode))
+    ));
+}
+
+#[test]
+fn reciever_cant_build_before_the_sender_confirms() {
+    let mut sender = E2EOfflinePakeBuilder::new_sender(CODE);
+    let mut reciever = E2EOfflinePakeBuilder::new_reciever(CODE);
+
+    reciever.recieve(&sender.send().unwrap()).unwrap();
+    reciever.send().unwrap();
+
+    assert!(matches!(
+        reciever.build(),
+        Err(Error::Pake(PakeError::Unconfirmed))
+    ));
+}
+
+#[test]
+fn generated_code_is_six_digits() {
+    let code = E2EOfflinePakeBuilder::generate_code();
+
+    assert_eq!(code.len(), 7);
+    assert!(code.chars().enumerate().all(|(i, c)| if i == 3 {
+        c == '-'
+    } else {
+        c.is_ascii_digit()
+    }));
+}
@@ -0,0 +1,83 @@
+use e2eoffline::{
+    error::{PassphraseError, RecieveError},
+    E2EOffline, Error,
+};
+
+const PASSPHRASE: &str = "correct horse battery staple";
+
+#[test]
+fn passphrase_alone_agrees_on_the_key() {
+    let mut first = E2EOffline::from_passphrase(PASSPHRASE).unwrap();
+    let mut second = E2EOffline::from_passphrase(PASSPHRASE).unwrap();
+
+    // each side has its own salt, sent along with its ciphertexts
+    let from_first = first.encrypt("hello").unwrap();
+    let from_second = second.encrypt("hello back").unwrap();
+    assert_ne!(from_first.split('.').next(), from_second.split('.').next());
+
+    assert_eq!(
+        second.decrypt(&from_first).unwrap().expose_secret(),
+        "hello"
+    );
+    assert_eq!(
+        first.decrypt(&from_second).unwrap().expose_secret(),
+        "hello back"
+    );
+
+    let mut wrong = E2EOffline::from_passphrase("wrong horse").unwrap();
+    assert!(matches!(
+        wrong.decrypt(&from_first),
+        Err(Error::Recieve(RecieveError::FailedDecryption))
+    ));
+}
+
+#[test]
+fn other_sides_salt_is_pinned() {
+    let mut first = E2EOffline::from_passphrase(PASSPHRASE).unwrap();
+    let mut second = E2EOffline::from_passphrase(PASSPHRASE).unwrap();
+    let mut third = E2EOffline::from_passphrase(PASSPHRASE).unwrap();
+
+    second.decrypt(&first.encrypt("hello").unwrap()).unwrap();
+
+    // a third salt would cost another Argon2id run, so it's refused outright
+    assert!(matches!(
+        second.decrypt(&third.encrypt("hello").unwrap()),
+        Err(Error::Passphrase(PassphraseError::UnexpectedSalt))
+    ));
+
+    // the pinned salt and this side's own still work
+    assert!(second.decrypt(&first.encrypt("again").unwrap()).is_ok());
+    let mine = second.encrypt("mine").unwrap();
+    assert!(second.decrypt(&mine).is_ok());
+}
+
+#[test]
+fn salt_is_only_pinned_by_a_message_that_decrypts() {
+    let mut first = E2EOffline::from_passphrase(PASSPHRASE).unwrap();
+    let mut second = E2EOffline::from_passphrase(PASSPHRASE).unwrap();
+
+    let ciphertext = first.encrypt("hello").unwrap();
+    let (_, rest) = ciphertext.split_once('.').unwrap();
+    let forged = format!("AAAAAAAAAAAAAAAAAAAAAA==.{rest}");
+
+    assert!(matches!(
+        second.decrypt(&forged),
+        Err(Error::Recieve(RecieveError::FailedDecryption))
+    ));
+    assert_eq!(
+        second.decrypt(&ciphertext).unwrap().expose_secret(),
+        "hello"
+    );
+}
+
+#[test]
+fn salted_ciphertext_needs_a_passphrase_session() {
+    let mut first = E2EOffline::from_passphrase(PASSPHRASE).unwrap();
+    let mut raw =
+        E2EOffline::from_key_base64(first.dangerously_export_raw_key().expose_secret()).unwrap();
+
+    assert!(matches!(
+        raw.decrypt(&first.encrypt("hello").unwrap()),
+        Err(Error::Passphrase(PassphraseError::NotPassphrase))
+    ));
+}
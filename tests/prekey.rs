@@ -0,0 +1,106 @@
+use e2eoffline::{error::PrekeyError, E2EOfflineBuilder, Error, HandshakeMode, Identity};
+
+fn reciever(identity: &Identity) -> E2EOfflineBuilder {
+    E2EOfflineBuilder::new_reciever_with_identity(identity, HandshakeMode::Classic)
+}
+
+// the sender's side of a prekey handshake, from the published bundle alone
+fn send(bundle: &str, identity: &Identity) -> (E2EOfflineBuilder, String) {
+    let mut sender = E2EOfflineBuilder::new_sender_with_identity(identity);
+    sender.set_prekey_bundle_encoded(bundle).unwrap();
+    let exchange = sender.send().unwrap();
+
+    (sender, exchange)
+}
+
+#[test]
+fn one_time_prekey_is_consumed() {
+    let identity = Identity::generate();
+    let mut reciever = reciever(&identity);
+    let bundle = reciever.get_prekey_bundle_encoded(1).unwrap();
+    reciever
+        .set_other_public_key_encoded(&identity.get_pubkey_encoded().unwrap())
+        .unwrap();
+
+    let (sender, exchange) = send(&bundle, &identity);
+    reciever.recieve(&exchange).unwrap();
+    assert_eq!(reciever.used_one_time_prekeys(), [0]);
+
+    // replaying the exchange, or another sender picking the same prekey from
+    // the same bundle, is refused
+    assert!(matches!(
+        reciever.recieve(&exchange),
+        Err(Error::Prekey(PrekeyError::OneTimePrekeyUsed))
+    ));
+    let (_, other_exchange) = send(&bundle, &identity);
+    assert!(matches!(
+        reciever.recieve(&other_exchange),
+        Err(Error::Prekey(PrekeyError::OneTimePrekeyUsed))
+    ));
+
+    let mut sender = sender.build().unwrap();
+    let mut reciever = reciever.build().unwrap();
+    let decrypted = reciever.decrypt(&sender.encrypt("hello").unwrap()).unwrap();
+    assert_eq!(decrypted.expose_secret(), "hello");
+}
+
+#[test]
+fn signed_prekey_alone_still_agrees() {
+    let identity = Identity::generate();
+    let mut reciever = reciever(&identity);
+    let bundle = reciever.get_prekey_bundle_encoded(0).unwrap();
+    reciever
+        .set_other_public_key_encoded(&identity.get_pubkey_encoded().unwrap())
+        .unwrap();
+
+    let (sender, exchange) = send(&bundle, &identity);
+    reciever.recieve(&exchange).unwrap();
+    assert!(reciever.used_one_time_prekeys().is_empty());
+
+    let mut sender = sender.build().unwrap();
+    let mut reciever = reciever.build().unwrap();
+    let decrypted = reciever.decrypt(&sender.encrypt("hello").unwrap()).unwrap();
+    assert_eq!(decrypted.expose_secret(), "hello");
+}
+
+#[test]
+fn one_time_prekey_cannot_be_skipped() {
+    let identity = Identity::generate();
+    let mut reciever = reciever(&identity);
+    // a bundle without one-time prekeys, published before any were added
+    let bundle = reciever.get_prekey_bundle_encoded(0).unwrap();
+    reciever.get_prekey_bundle_encoded(1).unwrap();
+    reciever
+        .set_other_public_key_encoded(&identity.get_pubkey_encoded().unwrap())
+        .unwrap();
+
+    let (_, exchange) = send(&bundle, &identity);
+    assert!(matches!(
+        reciever.recieve(&exchange),
+        Err(Error::Prekey(PrekeyError::MissingOneTimePrekey))
+    ));
+    assert!(reciever.used_one_time_prekeys().is_empty());
+}
+
+#[test]
+fn bundle_signature_is_checked() {
+    let identity = Identity::generate();
+    let mut reciever = reciever(&identity);
+    let bundle = reciever.get_prekey_bundle_encoded(2).unwrap();
+
+    // dropping a one-time prekey changes what was signed
+    let mut parts: Vec<&str> = bundle.split('.').collect();
+    let fewer = parts[2].split(',').next().unwrap().to_string();
+    parts[2] = &fewer;
+
+    let mut sender = E2EOfflineBuilder::new_sender_with_identity(&identity);
+    assert!(matches!(
+        sender.set_prekey_bundle_encoded(&parts.join(".")),
+        Err(Error::Prekey(PrekeyError::FailedSignatureCheck))
+    ));
+
+    assert!(matches!(
+        sender.get_prekey_bundle_encoded(1),
+        Err(Error::Prekey(PrekeyError::NotReciever))
+    ));
+}
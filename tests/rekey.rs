@@ -0,0 +1,120 @@
+use e2eoffline::{
+    error::{RecieveError, RekeyError},
+    CipherSuite, E2EOffline, Error, REKEY_GRACE_MESSAGES,
+};
+
+mod common;
+
+use common::{round_trips, Mode, KEY};
+
+#[test]
+fn old_key_lasts_for_the_grace_window() {
+    let mut sender = E2EOffline::from_key_base64(KEY).unwrap();
+    let mut reciever = E2EOffline::from_key_base64(KEY).unwrap();
+
+    let late = sender.encrypt("in flight").unwrap();
+    let rekey = sender.rekey().unwrap();
+    assert!(E2EOffline::is_rekey_message(&rekey));
+    reciever.apply_rekey(&rekey).unwrap();
+    assert_ne!(sender.dangerously_export_raw_key().expose_secret(), KEY);
+
+    // messages that don't decrypt don't count against the old key
+    for _ in 0..REKEY_GRACE_MESSAGES * 2 {
+        assert!(matches!(
+            reciever.decrypt("AAAAAAAAAAAAAAAA.AAAAAAAAAAAAAAAAAAAAAA=="),
+            Err(Error::Recieve(RecieveError::FailedDecryption))
+        ));
+    }
+    for _ in 0..REKEY_GRACE_MESSAGES - 1 {
+        assert!(round_trips(&mut sender, &mut reciever));
+    }
+    assert!(reciever.decrypt(&late).is_ok());
+
+    // the message under the old key counted for nothing, and now it's gone
+    assert!(round_trips(&mut sender, &mut reciever));
+    assert!(reciever.decrypt(&late).is_err());
+}
+
+#[test]
+fn replayed_rekey_is_refused() {
+    let (mut sender, mut reciever) = common::sessions(Mode::Classic, CipherSuite::default());
+
+    let first = sender.rekey().unwrap();
+    let second = sender.rekey().unwrap();
+
+    reciever.apply_rekey(&first).unwrap();
+    assert!(matches!(
+        reciever.apply_rekey(&first),
+        Err(Error::Rekey(RekeyError::Stale))
+    ));
+
+    reciever.apply_rekey(&second).unwrap();
+    assert!(round_trips(&mut sender, &mut reciever));
+
+    // the first rekey still decrypts under a key in its grace window, but
+    // can't roll the session back to it
+    assert!(matches!(
+        reciever.apply_rekey(&first),
+        Err(Error::Rekey(RekeyError::Stale))
+    ));
+    assert!(round_trips(&mut sender, &mut reciever));
+}
+
+#[test]
+fn rekey_signature_is_checked() {
+    let (mut sender, mut reciever) = common::sessions(Mode::Classic, CipherSuite::default());
+
+    // the other side knows the shared key but not the signing key, so it can
+    // only make an unsigned rekey
+    let mut forger =
+        E2EOffline::from_key_base64(sender.dangerously_export_raw_key().expose_secret()).unwrap();
+
+    assert!(matches!(
+        reciever.apply_rekey(&forger.rekey().unwrap()),
+        Err(Error::Rekey(RekeyError::MissingSignature))
+    ));
+    assert!(round_trips(&mut sender, &mut reciever));
+}
+
+#[test]
+fn simultaneous_rekeys_settle_on_one_key() {
+    let (mut sender, mut reciever) = common::sessions(Mode::Classic, CipherSuite::default());
+
+    let from_sender = sender.rekey().unwrap();
+    let from_reciever = reciever.rekey().unwrap();
+    let sender_late = sender.encrypt("late").unwrap();
+
+    sender.apply_rekey(&from_reciever).unwrap();
+    reciever.apply_rekey(&from_sender).unwrap();
+
+    assert_eq!(
+        sender.dangerously_export_raw_key().expose_secret(),
+        reciever.dangerously_export_raw_key().expose_secret()
+    );
+    assert!(round_trips(&mut sender, &mut reciever));
+    assert!(round_trips(&mut reciever, &mut sender));
+    assert!(reciever.decrypt(&sender_late).is_ok());
+
+    // and rekeying carries on from there
+    reciever.apply_rekey(&sender.rekey().unwrap()).unwrap();
+    assert!(round_trips(&mut sender, &mut reciever));
+}
+
+#[test]
+fn rekey_counts_survive_a_session_export() {
+    let (mut sender, mut reciever) = common::sessions(Mode::Classic, CipherSuite::default());
+    let mut vault = E2EOffline::from_key_base64(KEY).unwrap();
+
+    let rekey = sender.rekey().unwrap();
+    reciever.apply_rekey(&rekey).unwrap();
+
+    let export = reciever.export_session_with(&mut vault).unwrap();
+    let mut reciever = E2EOffline::import_session_with(&export, &mut vault).unwrap();
+
+    assert!(matches!(
+        reciever.apply_rekey(&rekey),
+        Err(Error::Rekey(RekeyError::Stale))
+    ));
+    reciever.apply_rekey(&sender.rekey().unwrap()).unwrap();
+    assert!(round_trips(&mut sender, &mut reciever));
+}
@@ -0,0 +1,90 @@
+use e2eoffline::{
+    error::{PassphraseError, SessionError},
+    E2EOffline, Error, SessionExport, REKEY_GRACE_MESSAGES,
+};
+
+mod common;
+
+use common::{round_trips, KEY};
+
+const PASSPHRASE: &str = "correct horse battery staple";
+
+// saves and restores a session through its text form
+fn reimport(session: &E2EOffline) -> E2EOffline {
+    let export = session.export_session("export passphrase").unwrap();
+    let export = SessionExport::from_encoded(&export.to_encoded()).unwrap();
+
+    E2EOffline::import_session(&export, "export passphrase").unwrap()
+}
+
+#[test]
+fn exported_session_carries_on() {
+    let mut sender = E2EOffline::from_key_base64(KEY).unwrap();
+    let mut reciever = reimport(&E2EOffline::from_key_base64(KEY).unwrap());
+
+    assert!(round_trips(&mut sender, &mut reciever));
+    assert!(round_trips(&mut reciever, &mut sender));
+}
+
+#[test]
+fn wrong_passphrase_is_refused() {
+    let export = E2EOffline::from_key_base64(KEY)
+        .unwrap()
+        .export_session("export passphrase")
+        .unwrap();
+
+    assert!(matches!(
+        E2EOffline::import_session(&export, "wrong passphrase"),
+        Err(Error::Session(SessionError::WrongPassphrase))
+    ));
+
+    // an export sealed with a vault has no salt to derive a key from
+    let export = E2EOffline::from_key_base64(KEY)
+        .unwrap()
+        .export_session_with(&mut E2EOffline::from_key_base64(KEY).unwrap())
+        .unwrap();
+    assert!(matches!(
+        E2EOffline::import_session(&export, "export passphrase"),
+        Err(Error::Session(SessionError::WrongPassphrase))
+    ));
+}
+
+#[test]
+fn grace_window_survives_an_export() {
+    let mut sender = E2EOffline::from_key_base64(KEY).unwrap();
+    let mut reciever = E2EOffline::from_key_base64(KEY).unwrap();
+
+    let late = sender.encrypt("in flight").unwrap();
+    reciever.apply_rekey(&sender.rekey().unwrap()).unwrap();
+
+    // the grace window is partly spent before the export and the rest after
+    let before = REKEY_GRACE_MESSAGES / 2;
+    for _ in 0..before {
+        assert!(round_trips(&mut sender, &mut reciever));
+    }
+    let mut reciever = reimport(&reciever);
+    for _ in before..REKEY_GRACE_MESSAGES - 1 {
+        assert!(round_trips(&mut sender, &mut reciever));
+    }
+    assert!(reciever.decrypt(&late).is_ok());
+
+    assert!(round_trips(&mut sender, &mut reciever));
+    assert!(reciever.decrypt(&late).is_err());
+}
+
+#[test]
+fn pinned_salt_survives_an_export() {
+    let mut first = E2EOffline::from_passphrase(PASSPHRASE).unwrap();
+    let mut second = E2EOffline::from_passphrase(PASSPHRASE).unwrap();
+    let mut third = E2EOffline::from_passphrase(PASSPHRASE).unwrap();
+
+    assert!(round_trips(&mut first, &mut second));
+    let mut second = reimport(&second);
+
+    assert!(round_trips(&mut first, &mut second));
+    assert!(round_trips(&mut second, &mut first));
+    assert!(matches!(
+        second.decrypt(&third.encrypt("hello").unwrap()),
+        Err(Error::Passphrase(PassphraseError::UnexpectedSalt))
+    ));
+}
@@ -0,0 +1,150 @@
+use std::{
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use e2eoffline::{
+    error::{IdentityError, SessionError},
+    E2EOffline, E2EOfflineBuilder, Error, HandshakeMode, Identity, SessionExport,
+};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+mod common;
+
+use common::KEY;
+
+const OTHER_KEY: &str = "HxweHRwbGhkYFxYVFBMSERAPDg0MCwoJCAcGBQQDAgE=";
+
+fn vault(key: &str) -> E2EOffline {
+    E2EOffline::from_key_base64(key).unwrap()
+}
+
+#[test]
+fn saved_handshake_carries_on() {
+    for mode in [HandshakeMode::Classic, HandshakeMode::Hybrid] {
+        let mut reciever =
+            E2EOfflineBuilder::new_reciever_with_identity(&Identity::generate(), mode);
+        reciever.set_expiry(4_000_000_000);
+        let hello = reciever.get_hello_encoded().unwrap();
+        let bundle = reciever.get_prekey_bundle_encoded(1).unwrap();
+
+        let state = reciever.export_state_with(&mut vault(KEY)).unwrap();
+        let mut restored = E2EOfflineBuilder::import_state_with(&state, &mut vault(KEY)).unwrap();
+
+        // everything the reciever handed out before saving still works
+        assert_eq!(restored.get_hello_encoded().unwrap(), hello);
+        assert_eq!(
+            restored.get_pubkey_encoded().unwrap(),
+            reciever.get_pubkey_encoded().unwrap()
+        );
+
+        let mut sender = E2EOfflineBuilder::new_sender();
+        sender.set_prekey_bundle_encoded(&bundle).unwrap();
+        restored
+            .set_other_public_key_encoded(&sender.get_pubkey_encoded().unwrap())
+            .unwrap();
+        restored.recieve(&sender.send().unwrap()).unwrap();
+
+        let mut sender = sender.build().unwrap();
+        let mut restored = restored.build().unwrap();
+        let decrypted = restored.decrypt(&sender.encrypt("hello").unwrap()).unwrap();
+        assert_eq!(decrypted.expose_secret(), "hello");
+    }
+}
+
+#[test]
+fn wrong_passphrase_is_refused() {
+    let state = E2EOfflineBuilder::new_reciever()
+        .export_state("correct horse")
+        .unwrap();
+
+    assert!(matches!(
+        E2EOfflineBuilder::import_state(&state, "wrong horse"),
+        Err(Error::Session(SessionError::WrongPassphrase))
+    ));
+    assert!(E2EOfflineBuilder::import_state(&state, "correct horse").is_ok());
+}
+
+#[test]
+fn state_and_session_exports_cant_be_swapped() {
+    let state = E2EOfflineBuilder::new_sender()
+        .export_state_with(&mut vault(KEY))
+        .unwrap();
+    let session = vault(OTHER_KEY)
+        .export_session_with(&mut vault(KEY))
+        .unwrap();
+
+    assert!(matches!(
+        E2EOffline::import_session_with(&state, &mut vault(KEY)),
+        Err(Error::Session(SessionError::WrongKind))
+    ));
+    assert!(matches!(
+        E2EOfflineBuilder::import_state_with(&session, &mut vault(KEY)),
+        Err(Error::Session(SessionError::WrongKind))
+    ));
+}
+
+#[test]
+fn unknown_version_is_refused() {
+    let state = E2EOfflineBuilder::new_sender()
+        .export_state_with(&mut vault(KEY))
+        .unwrap();
+
+    assert!(matches!(
+        E2EOfflineBuilder::import_state_with(
+            &SessionExport::from_encoded(
+                &state.to_encoded().replace("\"version\":1", "\"version\":2")
+            )
+            .unwrap(),
+            &mut vault(KEY)
+        ),
+        Err(Error::Session(SessionError::UnknownVersion(2)))
+    ));
+}
+
+#[test]
+fn key_that_expired_while_saved_is_refused() {
+    let mut sender = E2EOfflineBuilder::new_sender();
+    let mut reciever = E2EOfflineBuilder::new_reciever();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    sender.set_expiry(now + 2);
+    reciever
+        .set_other_public_key_encoded(&sender.get_pubkey_encoded().unwrap())
+        .unwrap();
+    let state = reciever.export_state_with(&mut vault(KEY)).unwrap();
+
+    thread::sleep(Duration::from_secs(3));
+
+    assert!(matches!(
+        E2EOfflineBuilder::import_state_with(&state, &mut vault(KEY)),
+        Err(Error::Identity(IdentityError::Expired))
+    ));
+}
+
+#[test]
+fn restored_handshake_uses_the_given_rng() {
+    let reciever = E2EOfflineBuilder::new_reciever();
+    let mut sender = E2EOfflineBuilder::new_sender();
+    sender
+        .set_other_public_key_encoded(&reciever.get_hello_encoded().unwrap())
+        .unwrap();
+    let state = sender.export_state_with(&mut vault(KEY)).unwrap();
+
+    let send = |seed| {
+        E2EOfflineBuilder::import_state_with_rng(
+            &state,
+            &mut vault(KEY),
+            ChaCha20Rng::seed_from_u64(seed),
+        )
+        .unwrap()
+        .send()
+        .unwrap()
+    };
+
+    assert_eq!(send(1), send(1));
+    assert_ne!(send(1), send(2));
+}
@@ -0,0 +1,389 @@
+//! Checks the published test vectors in `test-vectors/e2eoffline.json`.
+//!
+//! Every vector is made from seeded RNGs, so this implementation has to
+//! reproduce them exactly. Other implementations can check the decoding side:
+//! importing the keys, recieving the exchange texts and decrypting the
+//! ciphertexts. After a deliberate format change, regenerate them with
+//! `cargo test --test vectors -- --ignored`.
+
+use std::{fs, path::PathBuf};
+
+use e2eoffline::{CipherSuite, E2EOffline, E2EOfflineBuilder, HandshakeMode, Identity};
+use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+use serde::{Deserialize, Serialize};
+
+// the private keys in the vectors are encrypted with this
+const PRIVATE_KEY_PASSPHRASE: &str = "test vectors";
+
+const KEY: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+const PASSPHRASE: &str = "correct horse battery staple";
+const PLAINTEXTS: [&str; 3] = ["", "attack at dawn", "ünïcödé 🔑 and a.dot"];
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Vectors {
+    identities: Vec<IdentityVector>,
+    handshakes: Vec<HandshakeVector>,
+    ciphertexts: Vec<CiphertextVector>,
+    passphrase_ciphertexts: Vec<PassphraseVector>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct IdentityVector {
+    seed: u64,
+    private_key_pem: String,
+    pubkey_encoded: String,
+    pubkey_pem: String,
+    pubkey_openssh: String,
+    fingerprint: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct HandshakeVector {
+    /// "classic", "hybrid" or "prekey"
+    mode: String,
+    cipher_suite: String,
+    /// Indexes into `identities`.
+    reciever_identity: usize,
+    sender_identity: usize,
+    reciever_seed: u64,
+    sender_seed: u64,
+    /// The reciever's hello, or its prekey bundle in prekey mode.
+    hello: String,
+    sender_pubkey: String,
+    exchange: String,
+    shared_key: String,
+    plaintext: String,
+    ciphertext: String,
+    rekey: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CiphertextVector {
+    key: String,
+    seed: u64,
+    plaintext: String,
+    ciphertext: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct PassphraseVector {
+    passphrase: String,
+    seed: u64,
+    plaintext: String,
+    ciphertext: String,
+}
+
+fn vectors_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test-vectors/e2eoffline.json")
+}
+
+fn load_vectors() -> Vectors {
+    serde_json::from_str(&fs::read_to_string(vectors_path()).unwrap()).unwrap()
+}
+
+fn identity(seed: u64) -> Identity {
+    Identity::generate_with_rng(&mut ChaCha20Rng::seed_from_u64(seed))
+}
+
+fn identity_vector(seed: u64) -> IdentityVector {
+    let identity = identity(seed);
+
+    IdentityVector {
+        seed,
+        private_key_pem: identity.to_private_key_pem(PRIVATE_KEY_PASSPHRASE).unwrap(),
+        pubkey_encoded: identity.get_pubkey_encoded().unwrap(),
+        pubkey_pem: identity.get_pubkey_pem().unwrap(),
+        pubkey_openssh: identity.get_pubkey_openssh().unwrap(),
+        fingerprint: identity.get_fingerprint().unwrap(),
+    }
+}
+
+// runs the handshake a vector describes, taking everything random from its
+// seeds
+fn handshake_vector(
+    identities: &[IdentityVector],
+    mode: &str,
+    cipher_suite: CipherSuite,
+    reciever_identity: usize,
+    sender_identity: usize,
+    reciever_seed: u64,
+    sender_seed: u64,
+) -> HandshakeVector {
+    let handshake_mode = match mode {
+        "hybrid" => HandshakeMode::Hybrid,
+        _ => HandshakeMode::Classic,
+    };
+
+    let mut reciever = E2EOfflineBuilder::new_reciever_with_rng(
+        &identity(identities[reciever_identity].seed),
+        handshake_mode,
+        ChaCha20Rng::seed_from_u64(reciever_seed),
+    );
+    let mut sender = E2EOfflineBuilder::new_sender_with_rng(
+        &identity(identities[sender_identity].seed),
+        ChaCha20Rng::seed_from_u64(sender_seed),
+    );
+
+    let hello = match mode {
+        "prekey" => {
+            let bundle = reciever.get_prekey_bundle_encoded(2).unwrap();
+            sender.set_prekey_bundle_encoded(&bundle).unwrap();
+            bundle
+        }
+        _ => {
+            let hello = reciever.get_hello_encoded().unwrap();
+            sender.set_other_public_key_encoded(&hello).unwrap();
+            hello
+        }
+    };
+
+    let sender_pubkey = sender.get_pubkey_encoded().unwrap();
+    reciever
+        .set_other_public_key_encoded(&sender_pubkey)
+        .unwrap();
+
+    sender.set_cipher_suite(cipher_suite);
+    let exchange = sender.send().unwrap();
+    reciever.recieve(&exchange).unwrap();
+
+    let mut sender = sender.build().unwrap();
+    let mut reciever = reciever.build().unwrap();
+
+    let plaintext = PLAINTEXTS[1].to_string();
+    let ciphertext = sender.encrypt(&plaintext).unwrap();
+    assert_eq!(
+        reciever.decrypt(&ciphertext).unwrap().expose_secret(),
+        plaintext
+    );
+
+    HandshakeVector {
+        mode: mode.to_string(),
+        cipher_suite: cipher_suite.to_string(),
+        reciever_identity,
+        sender_identity,
+        reciever_seed,
+        sender_seed,
+        hello,
+        sender_pubkey,
+        exchange,
+        shared_key: sender
+            .dangerously_export_raw_key()
+            .expose_secret()
+            .to_string(),
+        plaintext,
+        ciphertext,
+        rekey: sender.rekey().unwrap(),
+    }
+}
+
+fn ciphertext_vector(seed: u64, plaintext: &str) -> CiphertextVector {
+    let mut e2e =
+        E2EOffline::from_key_base64_with_rng(KEY, ChaCha20Rng::seed_from_u64(seed)).unwrap();
+
+    CiphertextVector {
+        key: KEY.to_string(),
+        seed,
+        plaintext: plaintext.to_string(),
+        ciphertext: e2e.encrypt(plaintext).unwrap(),
+    }
+}
+
+fn passphrase_vector(seed: u64, plaintext: &str) -> PassphraseVector {
+    let mut e2e =
+        E2EOffline::from_passphrase_with_rng(PASSPHRASE, ChaCha20Rng::seed_from_u64(seed)).unwrap();
+
+    PassphraseVector {
+        passphrase: PASSPHRASE.to_string(),
+        seed,
+        plaintext: plaintext.to_string(),
+        ciphertext: e2e.encrypt(plaintext).unwrap(),
+    }
+}
+
+fn regenerate_handshake(
+    identities: &[IdentityVector],
+    vector: &HandshakeVector,
+) -> HandshakeVector {
+    handshake_vector(
+        identities,
+        &vector.mode,
+        vector.cipher_suite.parse().unwrap(),
+        vector.reciever_identity,
+        vector.sender_identity,
+        vector.reciever_seed,
+        vector.sender_seed,
+    )
+}
+
+#[test]
+#[ignore = "rewrites test-vectors/e2eoffline.json"]
+fn regenerate_vectors() {
+    let identities = vec![identity_vector(1), identity_vector(2)];
+
+    let handshakes = vec![
+        handshake_vector(&identities, "classic", CipherSuite::Aes256Gcm, 0, 1, 10, 11),
+        handshake_vector(
+            &identities,
+            "classic",
+            CipherSuite::XChaCha20Poly1305,
+            1,
+            0,
+            12,
+            13,
+        ),
+        handshake_vector(
+            &identities,
+            "hybrid",
+            CipherSuite::Aes256GcmSiv,
+            0,
+            1,
+            14,
+            15,
+        ),
+        handshake_vector(
+            &identities,
+            "prekey",
+            CipherSuite::XChaCha20Poly1305,
+            0,
+            1,
+            16,
+            17,
+        ),
+    ];
+
+    let ciphertexts = PLAINTEXTS
+        .iter()
+        .enumerate()
+        .map(|(i, plaintext)| ciphertext_vector(20 + i as u64, plaintext))
+        .collect();
+
+    let vectors = Vectors {
+        identities,
+        handshakes,
+        ciphertexts,
+        passphrase_ciphertexts: vec![passphrase_vector(30, PLAINTEXTS[1])],
+    };
+
+    fs::write(
+        vectors_path(),
+        serde_json::to_string_pretty(&vectors).unwrap() + "\n",
+    )
+    .unwrap();
+}
+
+#[test]
+fn identity_vectors() {
+    for vector in load_vectors().identities {
+        let identity = identity(vector.seed);
+
+        // the PEM's salt is random, so it's checked by loading it instead
+        assert_eq!(
+            Identity::from_private_key_pem(&vector.private_key_pem, PRIVATE_KEY_PASSPHRASE)
+                .unwrap(),
+            identity
+        );
+        assert_eq!(
+            identity.get_pubkey_encoded().unwrap(),
+            vector.pubkey_encoded
+        );
+        assert_eq!(identity.get_pubkey_pem().unwrap(), vector.pubkey_pem);
+        assert_eq!(
+            identity.get_pubkey_openssh().unwrap(),
+            vector.pubkey_openssh
+        );
+        assert_eq!(identity.get_fingerprint().unwrap(), vector.fingerprint);
+    }
+}
+
+#[test]
+fn handshake_vectors() {
+    let vectors = load_vectors();
+
+    for vector in &vectors.handshakes {
+        assert_eq!(&regenerate_handshake(&vectors.identities, vector), vector);
+    }
+}
+
+#[test]
+fn handshake_vectors_are_recieved() {
+    let vectors = load_vectors();
+
+    for vector in &vectors.handshakes {
+        // loading the PEM is checked by identity_vectors, and is slow
+        let identity = identity(vectors.identities[vector.reciever_identity].seed);
+
+        let mode = match vector.mode.as_str() {
+            "hybrid" => HandshakeMode::Hybrid,
+            _ => HandshakeMode::Classic,
+        };
+
+        // the hello nonce and ephemeral keys only live in the reciever's seeded
+        // RNG, so the reciever is rebuilt from it
+        let mut reciever = E2EOfflineBuilder::new_reciever_with_rng(
+            &identity,
+            mode,
+            ChaCha20Rng::seed_from_u64(vector.reciever_seed),
+        );
+        if vector.mode == "prekey" {
+            assert_eq!(reciever.get_prekey_bundle_encoded(2).unwrap(), vector.hello);
+        }
+        reciever
+            .set_other_public_key_encoded(&vector.sender_pubkey)
+            .unwrap();
+        reciever.recieve(&vector.exchange).unwrap();
+
+        let mut reciever = reciever.build().unwrap();
+        assert_eq!(reciever.cipher_suite().to_string(), vector.cipher_suite);
+        assert_eq!(
+            reciever.dangerously_export_raw_key().expose_secret(),
+            vector.shared_key
+        );
+        assert_eq!(
+            reciever
+                .decrypt(&vector.ciphertext)
+                .unwrap()
+                .expose_secret(),
+            vector.plaintext
+        );
+
+        reciever.apply_rekey(&vector.rekey).unwrap();
+    }
+}
+
+#[test]
+fn ciphertext_vectors() {
+    for vector in load_vectors().ciphertexts {
+        assert_eq!(ciphertext_vector(vector.seed, &vector.plaintext), vector);
+
+        let mut e2e = E2EOffline::from_key_base64(&vector.key).unwrap();
+        assert_eq!(
+            e2e.decrypt(&vector.ciphertext).unwrap().expose_secret(),
+            vector.plaintext
+        );
+    }
+}
+
+#[test]
+fn handshake_ciphertexts_decrypt_with_raw_key() {
+    for vector in load_vectors().handshakes {
+        let mut e2e = E2EOffline::from_key_base64(&vector.shared_key).unwrap();
+        e2e.set_cipher_suite(vector.cipher_suite.parse().unwrap());
+        assert_eq!(
+            e2e.decrypt(&vector.ciphertext).unwrap().expose_secret(),
+            vector.plaintext
+        );
+    }
+}
+
+#[test]
+fn passphrase_vectors() {
+    for vector in load_vectors().passphrase_ciphertexts {
+        assert_eq!(passphrase_vector(vector.seed, &vector.plaintext), vector);
+
+        let mut e2e = E2EOffline::from_passphrase(&vector.passphrase).unwrap();
+        assert_eq!(
+            e2e.decrypt(&vector.ciphertext).unwrap().expose_secret(),
+            vector.plaintext
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use e2eoffline::{E2EOffline, E2EOfflineBuilder, E2EOfflinePakeBuilder, Identity, SecretString};
+use zeroize::Zeroize;
+
+mod common;
+
+use common::KEY;
+
+// reads the bytes still allocated behind a zeroized string; zeroizing keeps
+// the allocation, so the pointer is still valid
+fn allocation_after_zeroize(mut secret: SecretString) -> Vec<u8> {
+    let ptr = secret.expose_secret().as_ptr();
+    let len = secret.expose_secret().len();
+
+    secret.zeroize();
+    assert_eq!(secret.expose_secret(), "");
+
+    unsafe { std::slice::from_raw_parts(ptr, len) }.to_vec()
+}
+
+#[test]
+fn secret_string_is_cleared() {
+    let secret = SecretString::from("attack at dawn".to_string());
+
+    assert_eq!(allocation_after_zeroize(secret), vec![0u8; 14]);
+}
+
+#[test]
+fn decrypted_message_is_cleared() {
+    let mut sender = E2EOffline::from_key_base64(KEY).unwrap();
+    let mut reciever = E2EOffline::from_key_base64(KEY).unwrap();
+
+    let decrypted = reciever
+        .decrypt(&sender.encrypt("attack at dawn").unwrap())
+        .unwrap();
+    assert_eq!(decrypted.expose_secret(), "attack at dawn");
+
+    assert_eq!(allocation_after_zeroize(decrypted), vec![0u8; 14]);
+}
+
+#[test]
+fn secret_string_debug_is_redacted() {
+    let secret = SecretString::from("attack at dawn".to_string());
+
+    assert!(!format!("{secret:?}").contains("attack at dawn"));
+}
+
+#[test]
+fn session_debug_is_redacted() {
+    let e2e = E2EOffline::from_key_base64(KEY).unwrap();
+    let debug = format!("{e2e:?}");
+
+    assert!(!debug.contains(KEY.trim_end_matches('=')));
+    assert!(debug.contains("[REDACTED]"));
+
+    let e2e = E2EOffline::from_passphrase("correct horse battery staple").unwrap();
+
+    assert!(!format!("{e2e:?}").contains("correct horse"));
+}
+
+#[test]
+fn builder_debug_is_redacted() {
+    let identity = Identity::generate();
+    let private_key_pem = identity.to_private_key_pem("").unwrap();
+
+    let mut reciever = E2EOfflineBuilder::new_reciever_with_identity(
+        &identity,
+        e2eoffline::HandshakeMode::Classic,
+    );
+    let mut sender = E2EOfflineBuilder::new_sender();
+
+    sender
+        .set_other_public_key_encoded(&reciever.get_hello_encoded().unwrap())
+        .unwrap();
+    reciever
+        .set_other_public_key_encoded(&sender.get_pubkey_encoded().unwrap())
+        .unwrap();
+    reciever.recieve(&sender.send().unwrap()).unwrap();
+
+    let debugs = [format!("{sender:?}"), format!("{reciever:?}")];
+
+    let e2e = reciever.build().unwrap();
+    let shared_key = e2e.dangerously_export_raw_key();
+
+    for debug in debugs {
+        assert!(!debug.contains(shared_key.expose_secret().trim_end_matches('=')));
+        assert!(debug.contains("[REDACTED]"));
+    }
+
+    let private_key_body = private_key_pem.lines().nth(1).unwrap();
+    assert!(!format!("{identity:?}").contains(private_key_body));
+    assert!(format!("{identity:?}").contains(&identity.get_fingerprint().unwrap()));
+}
+
+#[test]
+fn pake_debug_is_redacted() {
+    let sender = E2EOfflinePakeBuilder::new_sender("123-456");
+
+    assert!(!format!("{sender:?}").contains("123-456"));
+}
@@ -0,0 +1,7 @@
+use gloo_worker::Registrable;
+
+use webapp::worker::KeyWorker;
+
+fn main() {
+    KeyWorker::registrar().register();
+}
@@ -0,0 +1,124 @@
+use e2eoffline::{ContactBook, Trust, Verification};
+use yew::prelude::*;
+
+// where contacts were kept before the vault
+const CONTACTS_KEY: &str = "e2eoffline_contacts";
+
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+/// Moves contacts saved before the vault existed, which weren't encrypted,
+/// out of local storage.
+pub fn take_legacy_contacts() -> ContactBook {
+    let Some(storage) = storage() else {
+        return ContactBook::default();
+    };
+
+    let contacts = storage
+        .get_item(CONTACTS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|encoded| ContactBook::from_encoded(&encoded).ok())
+        .unwrap_or_default();
+    let _ = storage.remove_item(CONTACTS_KEY);
+
+    contacts
+}
+
+/// Checks the other user's key against the contact book, remembering them if
+/// they're new. Does nothing if no contact name was given.
+pub fn check_contact(
+    contacts: &UseStateHandle<ContactBook>,
+    name: &str,
+    builder: &e2eoffline::E2EOfflineBuilder,
+) -> anyhow::Result<String> {
+    if name.trim().is_empty() {
+        return Ok("".to_string());
+    }
+
+    let mut book = (**contacts).clone();
+    let trust = book.check(
+        name,
+        &builder.get_other_fingerprint()?,
+        builder.get_other_expiry(),
+    )?;
+
+    contacts.set(book);
+
+    Ok(match trust {
+        Trust::FirstSeen => format!(
+            "First time talking to {}, their key has been remembered",
+            name.trim()
+        ),
+        Trust::Known(verification) => format!("Key matches the one on file ({verification})"),
+    })
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct ContactsProps {
+    pub contacts: UseStateHandle<ContactBook>,
+}
+
+#[function_component(Contacts)]
+pub fn contacts(props: &ContactsProps) -> Html {
+    let ContactsProps { contacts } = props.clone();
+
+    let rows = contacts
+        .contacts()
+        .iter()
+        .map(|contact| {
+            let verify = {
+                let contacts = contacts.clone();
+                let name = contact.name.clone();
+                Callback::from(move |_| {
+                    let mut book = (*contacts).clone();
+                    if book.mark_verified(&name).is_ok() {
+                        contacts.set(book);
+                    }
+                })
+            };
+
+            let forget = {
+                let contacts = contacts.clone();
+                let name = contact.name.clone();
+                Callback::from(move |_| {
+                    let mut book = (*contacts).clone();
+                    book.forget(&name);
+                    contacts.set(book);
+                })
+            };
+
+            html! {
+                <tr>
+                    <td>{contact.name.clone()}</td>
+                    <td><code>{contact.fingerprint.clone()}</code></td>
+                    <td>{contact.verification.to_string()}</td>
+                    <td>
+                        if contact.verification == Verification::Unverified {
+                            <button class="btn btn-secondary btn-sm" onclick={verify}>{"Mark Verified"}</button>
+                        }
+                        <button class="btn btn-danger btn-sm" onclick={forget}>{"Forget"}</button>
+                    </td>
+                </tr>
+            }
+        })
+        .collect::<Html>();
+
+    html! {
+        <>
+            <h2>{"Contacts"}</h2>
+            <table class="table">
+                <thead>
+                    <tr>
+                        <th>{"Name"}</th>
+                        <th>{"Fingerprint"}</th>
+                        <th>{"Status"}</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>{rows}</tbody>
+            </table>
+        </>
+    }
+}
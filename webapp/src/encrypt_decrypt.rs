@@ -1,26 +1,64 @@
+use e2eoffline::{E2EOffline, SessionExport};
 use wasm_bindgen::UnwrapThrowExt;
+use webapp::worker::{Job, JobOutput};
 use yew::prelude::*;
 
-use crate::text_input::{TextAreaInput, TextInput};
+use crate::{
+    job::{hand_over_session, session_carrier, use_job_runner, with_session, JobStatus},
+    print_error_if_happened,
+    session::Session,
+    vault::Vault,
+};
+
+use crate::text_input::{PasswordInput, TextAreaInput, TextInput};
+
+const NO_SESSION: &str =
+    "No session yet: finish a handshake, or use a passphrase, raw key or session export";
 
 #[derive(Clone, PartialEq, Properties)]
 pub struct EncryptDecryptProps {
-    pub shared_key: UseStateHandle<String>,
+    pub session: UseStateHandle<Option<Session>>,
+    pub vault: Vault,
 }
 #[function_component(EncryptDecrypt)]
 pub fn encrypt_decrypt(props: &EncryptDecryptProps) -> Html {
-    let EncryptDecryptProps { shared_key } = props.clone();
-    // it's fine that this isn't a use_state because we don't need changes to it to trigger
+    let EncryptDecryptProps { session, vault } = props.clone();
 
+    let raw_key = use_state(|| "".to_string());
+    let passphrase = use_state(|| "".to_string());
+    let session_text = use_state(|| "".to_string());
+    let session_passphrase = use_state(|| "".to_string());
     let plain_text = use_state(|| "".to_string());
     let cipher_text = use_state(|| "".to_string());
 
     let error_text = use_state(|| "".to_string());
+    let runner = use_job_runner();
+
+    let on_raw_key_change = {
+        let raw_key = raw_key.clone();
+        Callback::from(move |text| {
+            raw_key.set(text);
+        })
+    };
+
+    let on_passphrase_change = {
+        let passphrase = passphrase.clone();
+        Callback::from(move |text| {
+            passphrase.set(text);
+        })
+    };
+
+    let on_session_text_change = {
+        let session_text = session_text.clone();
+        Callback::from(move |text| {
+            session_text.set(text);
+        })
+    };
 
-    let on_key_change = {
-        let shared_key = shared_key.clone();
+    let on_session_passphrase_change = {
+        let session_passphrase = session_passphrase.clone();
         Callback::from(move |text| {
-            shared_key.set(text);
+            session_passphrase.set(text);
         })
     };
 
@@ -38,19 +76,102 @@ pub fn encrypt_decrypt(props: &EncryptDecryptProps) -> Html {
         })
     };
 
+    let set_session = {
+        let session = session.clone();
+        Callback::from(move |new_session| {
+            session.set(Some(new_session));
+        })
+    };
+
+    let use_passphrase = {
+        let passphrase = passphrase.clone();
+        let error_text = error_text.clone();
+        let set_session = set_session.clone();
+        let runner = runner.clone();
+
+        Callback::from(move |_| {
+            error_text.set("".to_string());
+
+            let carrier = session_carrier();
+            runner.run(
+                Job::UsePassphrase {
+                    passphrase: (*passphrase).clone(),
+                    carrier: carrier.expose_secret().to_string(),
+                },
+                with_session(set_session.clone(), carrier, error_text.clone()),
+            );
+        })
+    };
+
+    let use_raw_key = {
+        let raw_key = raw_key.clone();
+        let error_text = error_text.clone();
+        let session = session.clone();
+
+        Callback::from(move |_| {
+            let r = (|| -> anyhow::Result<()> {
+                error_text.set("".to_string());
+
+                let e2e = E2EOffline::from_key_base64(&raw_key)
+                    .inspect_err(|_| error_text.set("Bad AES Key".to_string()))?;
+                session.set(Some(Session::new(e2e)));
+
+                Ok(())
+            })();
+            print_error_if_happened(r);
+        })
+    };
+
+    let show_raw_key = {
+        let raw_key = raw_key.clone();
+        let error_text = error_text.clone();
+        let session = session.clone();
+
+        Callback::from(move |_| match &*session {
+            Some(session) => {
+                error_text.set("".to_string());
+
+                let confirmed = web_sys::window()
+                    .and_then(|window| {
+                        window
+                            .confirm_with_message(
+                                "Show the raw key? Anyone who sees it can read this conversation.",
+                            )
+                            .ok()
+                    })
+                    .unwrap_or(false);
+                if !confirmed {
+                    return;
+                }
+
+                raw_key.set(
+                    session
+                        .borrow_mut()
+                        .dangerously_export_raw_key()
+                        .expose_secret()
+                        .to_string(),
+                );
+            }
+            None => error_text.set(NO_SESSION.to_string()),
+        })
+    };
+
+    // encrypting, decrypting and taking up the other user's new key all move
+    // the session along, so it's saved after each
     let encrypt = {
         let plain_text = plain_text.clone();
         let cipher_text = cipher_text.clone();
         let error_text = error_text.clone();
-        let shared_key = shared_key.clone();
+        let session = session.clone();
+        let vault = vault.clone();
 
-        Callback::from(move |_| {
-            let mut aes = e2eoffline::E2EOffline::from_key_base64(&shared_key)
-                .map_err(|_| error_text.set("Bad AES Key".to_string()))
-                .unwrap_throw();
-
-            error_text.set("".to_string());
-            cipher_text.set(aes.encrypt(&plain_text).unwrap_throw());
+        Callback::from(move |_| match &*session {
+            Some(session) => {
+                error_text.set("".to_string());
+                cipher_text.set(session.borrow_mut().encrypt(&plain_text).unwrap_throw());
+                vault.save_session(Some(session));
+            }
+            None => error_text.set(NO_SESSION.to_string()),
         })
     };
 
@@ -58,30 +179,124 @@ pub fn encrypt_decrypt(props: &EncryptDecryptProps) -> Html {
         let plain_text = plain_text.clone();
         let cipher_text = cipher_text.clone();
         let error_text = error_text.clone();
-        let shared_key = shared_key.clone();
+        let session = session.clone();
+        let vault = vault.clone();
+
+        Callback::from(move |_| match &*session {
+            Some(session) => {
+                error_text.set("".to_string());
+
+                if E2EOffline::is_rekey_message(&cipher_text) {
+                    if let Err(e) = session.borrow_mut().apply_rekey(&cipher_text) {
+                        error_text.set(e.to_string());
+                        return;
+                    }
+                    vault.save_session(Some(session));
+
+                    plain_text.set("The other user switched to a new key".to_string());
+                    return;
+                }
+
+                let text = match session.borrow_mut().decrypt(&cipher_text) {
+                    Ok(text) => text,
+                    Err(_) => {
+                        error_text.set("Bad Ciphertext".to_string());
+                        return;
+                    }
+                };
+                vault.save_session(Some(session));
+
+                plain_text.set(text.expose_secret().to_string())
+            }
+            None => error_text.set(NO_SESSION.to_string()),
+        })
+    };
+
+    let export_session = {
+        let session_text = session_text.clone();
+        let session_passphrase = session_passphrase.clone();
+        let error_text = error_text.clone();
+        let session = session.clone();
+        let runner = runner.clone();
+
+        Callback::from(move |_| {
+            let r = (|| -> anyhow::Result<()> {
+                match &*session {
+                    Some(session) => {
+                        error_text.set("".to_string());
+
+                        let carrier = session_carrier();
+                        let session_text = session_text.clone();
+                        let error_text = error_text.clone();
+
+                        runner.run(
+                            Job::ExportSession {
+                                session: hand_over_session(session, &carrier)?,
+                                carrier: carrier.expose_secret().to_string(),
+                                passphrase: (*session_passphrase).clone(),
+                            },
+                            Callback::from(move |output: JobOutput| {
+                                let r = output
+                                    .map(|export| session_text.set(export))
+                                    .map_err(anyhow::Error::msg)
+                                    .inspect_err(|e| error_text.set(e.to_string()));
+                                print_error_if_happened(r);
+                            }),
+                        );
+                    }
+                    None => error_text.set(NO_SESSION.to_string()),
+                }
+                Ok(())
+            })();
+            print_error_if_happened(r);
+        })
+    };
+
+    let import_session = {
+        let session_text = session_text.clone();
+        let session_passphrase = session_passphrase.clone();
+        let error_text = error_text.clone();
+        let runner = runner.clone();
+
         Callback::from(move |_| {
             error_text.set("".to_string());
-            let mut aes = e2eoffline::E2EOffline::from_key_base64(&shared_key)
-                .map_err(|_| error_text.set("Bad AES Key".to_string()))
-                .unwrap_throw();
 
-            let text = aes
-                .decrypt(&cipher_text)
-                .map_err(|_| error_text.set("Bad Ciphertext".to_string()))
-                .unwrap_throw();
+            if SessionExport::from_encoded(&session_text).is_err() {
+                error_text.set("Invalid session export".to_string());
+                return;
+            }
 
-            plain_text.set(text)
+            let carrier = session_carrier();
+            runner.run(
+                Job::ImportSession {
+                    export: (*session_text).clone(),
+                    passphrase: (*session_passphrase).clone(),
+                    carrier: carrier.expose_secret().to_string(),
+                },
+                with_session(set_session.clone(), carrier, error_text.clone()),
+            );
         })
     };
 
+    let session_status = match &*session {
+        Some(session) => format!(
+            "Session ready, encrypting with {}",
+            session.borrow_mut().cipher_suite()
+        ),
+        None => NO_SESSION.to_string(),
+    };
+
     html! {
         <form onsubmit={Callback::from(|e: SubmitEvent| { e.prevent_default() })}>
             <h2>{ "Encrypt/Decrypt"}</h2>
             <p class="error">{(*error_text).clone()}</p>
+            <p>{session_status}</p>
+            <JobStatus runner={runner.clone()}/>
             <div class="mb-3">
-                <label for="ed_area_key">{"Key"}</label>
-                <TextInput id="ed_area_key" class={"form-control"} on_change={on_key_change} value={(*shared_key).clone()}/>
+                <label for="ed_area_passphrase">{"Passphrase (to use instead of a handshake)"}</label>
+                <PasswordInput id="ed_area_passphrase" class={"form-control"} on_change={on_passphrase_change} value={(*passphrase).clone()}/>
             </div>
+            <button onclick={use_passphrase} class="btn btn-secondary" disabled={runner.is_running()}>{"Use Passphrase"}</button>
             <div class="mb-3">
                 <label for="plaintext">{"Plaintext"}</label>
                 <TextAreaInput class="form-control" id="ciphertext" on_change={on_plain_text_change} value={(*plain_text).clone()}/>
@@ -92,6 +307,22 @@ pub fn encrypt_decrypt(props: &EncryptDecryptProps) -> Html {
                 <TextAreaInput class="form-control" id="ciphertext" on_change={on_cipher_text_change} value={(*cipher_text).clone()}/>
             </div>
             <button onclick={decrypt} class="btn btn-primary">{"Decrypt"}</button><br/>
+            <div class="mb-3">
+                <label for="ed_area_session">{"Session Export (to carry this conversation on elsewhere)"}</label>
+                <TextAreaInput class="form-control" id="ed_area_session" on_change={on_session_text_change} value={(*session_text).clone()}/>
+            </div>
+            <div class="mb-3">
+                <label for="ed_area_session_passphrase">{"Session Export Passphrase"}</label>
+                <PasswordInput id="ed_area_session_passphrase" class={"form-control"} on_change={on_session_passphrase_change} value={(*session_passphrase).clone()}/>
+            </div>
+            <button onclick={export_session} class="btn btn-secondary" disabled={runner.is_running()}>{"Export Session"}</button>
+            <button onclick={import_session} class="btn btn-secondary" disabled={runner.is_running()}>{"Import Session"}</button>
+            <div class="mb-3">
+                <label for="ed_area_raw_key">{"Raw Key (anyone with it can read and forge your messages)"}</label>
+                <TextInput id="ed_area_raw_key" class={"form-control"} on_change={on_raw_key_change} value={(*raw_key).clone()}/>
+            </div>
+            <button onclick={use_raw_key} class="btn btn-secondary">{"Use Raw Key"}</button>
+            <button onclick={show_raw_key} class="btn btn-danger">{"Show Raw Key"}</button>
         </form>
     }
 }
@@ -0,0 +1,151 @@
+use e2eoffline::Identity;
+use webapp::worker::{Job, JobOutput};
+use yew::prelude::*;
+
+use crate::{
+    job::{use_job_runner, with_identity, JobStatus},
+    print_error_if_happened,
+    text_input::{PasswordInput, TextAreaInput},
+};
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct IdentityProps {
+    pub identity: UseStateHandle<Option<Identity>>,
+}
+
+#[function_component(IdentityCard)]
+pub fn identity_card(props: &IdentityProps) -> Html {
+    let IdentityProps { identity } = props.clone();
+
+    let error_text = use_state(|| "".to_string());
+    let pem = use_state(|| "".to_string());
+    let passphrase = use_state(|| "".to_string());
+    let runner = use_job_runner();
+
+    let on_pem_change = {
+        let pem = pem.clone();
+        Callback::from(move |text| {
+            pem.set(text);
+        })
+    };
+
+    let on_passphrase_change = {
+        let passphrase = passphrase.clone();
+        Callback::from(move |text| {
+            passphrase.set(text);
+        })
+    };
+
+    let set_identity = {
+        let identity = identity.clone();
+        Callback::from(move |loaded| {
+            identity.set(Some(loaded));
+        })
+    };
+
+    let load = {
+        let error_text = error_text.clone();
+        let pem = pem.clone();
+        let passphrase = passphrase.clone();
+        let set_identity = set_identity.clone();
+        let runner = runner.clone();
+
+        Callback::from(move |_| {
+            error_text.set("".to_string());
+
+            runner.run(
+                Job::LoadIdentity {
+                    pem: (*pem).clone(),
+                    passphrase: (*passphrase).clone(),
+                },
+                with_identity(set_identity.clone(), error_text.clone()),
+            );
+        })
+    };
+
+    let generate = {
+        let error_text = error_text.clone();
+        let runner = runner.clone();
+
+        Callback::from(move |_| {
+            error_text.set("".to_string());
+            runner.run(
+                Job::GenerateIdentity,
+                with_identity(set_identity.clone(), error_text.clone()),
+            );
+        })
+    };
+
+    let export = {
+        let identity = identity.clone();
+        let error_text = error_text.clone();
+        let pem = pem.clone();
+        let passphrase = passphrase.clone();
+        let runner = runner.clone();
+
+        Callback::from(move |_| {
+            let r = (|| -> anyhow::Result<()> {
+                match &*identity {
+                    Some(identity) => {
+                        error_text.set("".to_string());
+
+                        let unprotected = identity.dangerously_export_private_key_pem()?;
+                        let pem = pem.clone();
+                        let error_text = error_text.clone();
+
+                        runner.run(
+                            Job::ExportIdentity {
+                                pem: unprotected.expose_secret().to_string(),
+                                passphrase: (*passphrase).clone(),
+                            },
+                            Callback::from(move |output: JobOutput| {
+                                let r = output
+                                    .map(|protected| pem.set(protected))
+                                    .map_err(anyhow::Error::msg)
+                                    .inspect_err(|e| error_text.set(e.to_string()));
+                                print_error_if_happened(r);
+                            }),
+                        );
+                    }
+                    None => {
+                        error_text.set("Need to load or generate an identity first".to_string());
+                    }
+                }
+                Ok(())
+            })();
+            print_error_if_happened(r);
+        })
+    };
+
+    let (fingerprint, pubkey_openssh) = match &*identity {
+        Some(identity) => (
+            identity.get_fingerprint().unwrap_or_default(),
+            identity.get_pubkey_openssh().unwrap_or_default(),
+        ),
+        None => (
+            "None, a new key is made for every handshake".to_string(),
+            "".to_string(),
+        ),
+    };
+
+    html! {
+        <form onsubmit={Callback::from(|e: SubmitEvent| { e.prevent_default() })}>
+            <h2>{ "Identity" }</h2>
+            <p class="error">{(*error_text).clone()}</p>
+            <p>{"Fingerprint: "}<code>{fingerprint}</code></p>
+            <p><code>{pubkey_openssh}</code></p>
+            <div class="mb-3">
+                <label for="identity_area_pem" class="form-label">{"Private Key PEM" }</label>
+                <TextAreaInput id="identity_area_pem" class="form-control" on_change={on_pem_change} value={(*pem).clone()}/>
+            </div>
+            <div class="mb-3">
+                <label for="identity_area_passphrase" class="form-label">{"Passphrase" }</label>
+                <PasswordInput id="identity_area_passphrase" class="form-control" on_change={on_passphrase_change} value={(*passphrase).clone()}/>
+            </div>
+            <JobStatus runner={runner.clone()}/>
+            <button onclick={load} class="btn btn-primary" disabled={runner.is_running()}>{ "Load Identity" }</button>
+            <button onclick={generate} class="btn btn-secondary" disabled={runner.is_running()}>{ "Generate Identity" }</button>
+            <button onclick={export} class="btn btn-secondary" disabled={runner.is_running()}>{ "Export Identity" }</button>
+        </form>
+    }
+}
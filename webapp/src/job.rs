@@ -0,0 +1,170 @@
+use std::{cell::RefCell, rc::Rc};
+
+use e2eoffline::{E2EOffline, Identity, SecretString, SessionExport};
+use gloo_worker::{Spawnable, WorkerBridge};
+use webapp::worker::{Job, JobOutput, KeyWorker, WORKER_PATH};
+use yew::prelude::*;
+
+use crate::{print_error_if_happened, session::Session, vault::Vault};
+
+#[derive(Default)]
+struct Slot {
+    bridge: Option<WorkerBridge<KeyWorker>>,
+    on_done: Option<Callback<JobOutput>>,
+}
+
+/// Runs [`Job`]s on a Web Worker, so the page keeps drawing while they run.
+/// Each component gets its own worker, started on its first job.
+#[derive(Clone)]
+pub struct JobRunner {
+    slot: Rc<RefCell<Slot>>,
+    status: UseStateHandle<Option<&'static str>>,
+}
+
+impl JobRunner {
+    /// Starts `job`, calling `on_done` with its output unless it's cancelled.
+    pub fn run(&self, job: Job, on_done: Callback<JobOutput>) {
+        let mut slot = self.slot.borrow_mut();
+        self.status.set(Some(job.description()));
+
+        // a job still running is cancelled, so its output can't reach `on_done`
+        if slot.on_done.is_some() {
+            slot.bridge = None;
+        }
+
+        let bridge = slot.bridge.get_or_insert_with(|| {
+            // weak, as the slot owns the bridge that owns this callback
+            let weak_slot = Rc::downgrade(&self.slot);
+            let status = self.status.clone();
+
+            KeyWorker::spawner()
+                .callback(move |output| {
+                    let on_done = weak_slot
+                        .upgrade()
+                        .and_then(|slot| slot.borrow_mut().on_done.take());
+
+                    if let Some(on_done) = on_done {
+                        status.set(None);
+                        on_done.emit(output);
+                    }
+                })
+                .spawn(WORKER_PATH)
+        });
+
+        bridge.send(job);
+        slot.on_done = Some(on_done);
+    }
+
+    /// Stops waiting for the current job. A worker can't be interrupted, so it
+    /// finishes the job and then exits, and the next job gets a fresh one.
+    pub fn cancel(&self) {
+        let mut slot = self.slot.borrow_mut();
+        slot.on_done = None;
+        slot.bridge = None;
+        self.status.set(None);
+    }
+
+    /// What the current job is doing, if one is running.
+    pub fn status(&self) -> Option<&'static str> {
+        *self.status
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status.is_some()
+    }
+}
+
+impl PartialEq for JobRunner {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.slot, &other.slot) && *self.status == *other.status
+    }
+}
+
+#[hook]
+pub fn use_job_runner() -> JobRunner {
+    JobRunner {
+        slot: use_mut_ref(Slot::default),
+        status: use_state(|| None),
+    }
+}
+
+/// Turns the PEM a job returned back into an identity for `next`, or shows
+/// what went wrong in `error_text`.
+pub fn with_identity(
+    next: Callback<Identity>,
+    error_text: UseStateHandle<String>,
+) -> Callback<JobOutput> {
+    Callback::from(move |output: JobOutput| {
+        let r = (|| -> anyhow::Result<()> {
+            let pem = SecretString::from(output.map_err(anyhow::Error::msg)?);
+
+            next.emit(Identity::from_private_key_pem(pem.expose_secret(), "")?);
+
+            Ok(())
+        })()
+        .inspect_err(|e| error_text.set(e.to_string()));
+        print_error_if_happened(r);
+    })
+}
+
+/// Makes a one-off key for handing a session to or from a job.
+pub fn session_carrier() -> SecretString {
+    Vault::generate_key()
+}
+
+/// Seals `session` under `carrier` for a job to open.
+pub fn hand_over_session(session: &Session, carrier: &SecretString) -> anyhow::Result<String> {
+    Ok(session
+        .borrow_mut()
+        .export_session_with(&mut E2EOffline::from_key_base64(carrier.expose_secret())?)?
+        .to_encoded())
+}
+
+/// Opens the session a job returned under `carrier` for `next`, or shows what
+/// went wrong in `error_text`.
+pub fn with_session(
+    next: Callback<Session>,
+    carrier: SecretString,
+    error_text: UseStateHandle<String>,
+) -> Callback<JobOutput> {
+    Callback::from(move |output: JobOutput| {
+        let r = (|| -> anyhow::Result<()> {
+            let export = SessionExport::from_encoded(&output.map_err(anyhow::Error::msg)?)?;
+            let e2e = E2EOffline::import_session_with(
+                &export,
+                &mut E2EOffline::from_key_base64(carrier.expose_secret())?,
+            )?;
+
+            next.emit(Session::new(e2e));
+
+            Ok(())
+        })()
+        .inspect_err(|e| error_text.set(e.to_string()));
+        print_error_if_happened(r);
+    })
+}
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct JobStatusProps {
+    pub runner: JobRunner,
+}
+
+/// A spinner and a cancel button, shown while a job runs.
+#[function_component(JobStatus)]
+pub fn job_status(props: &JobStatusProps) -> Html {
+    let JobStatusProps { runner } = props.clone();
+
+    let Some(status) = runner.status() else {
+        return html! {};
+    };
+
+    let cancel = Callback::from(move |_| runner.cancel());
+
+    html! {
+        <div class="mb-3 d-flex align-items-center">
+            <span class="spinner-border spinner-border-sm me-2" role="status"></span>
+            <span class="me-2">{status}{"..."}</span>
+            <button class="btn btn-outline-secondary btn-sm" onclick={cancel}>{"Cancel"}</button>
+        </div>
+    }
+}
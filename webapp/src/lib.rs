@@ -0,0 +1,4 @@
+//! The part of the webapp that runs in its Web Worker, shared by the page
+//! (`main.rs`) and the worker itself (`bin/worker.rs`).
+
+pub mod worker;
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use e2eoffline::SecretString;
+use wasm_bindgen_futures::spawn_local;
+use webapp::worker::{Job, JobOutput};
+use yew::prelude::*;
+
+use crate::{
+    job::{use_job_runner, JobStatus},
+    print_error_if_happened,
+    store::Store,
+    text_input::PasswordInput,
+    vault::{Vault, KEY_RECORD},
+};
+
+#[derive(Clone, PartialEq, Properties)]
+pub struct LockScreenProps {
+    pub store: Store,
+    pub on_unlock: Callback<Vault>,
+}
+
+/// Asks for the passphrase, or for a new one the first time, before anything
+/// saved is shown.
+#[function_component(LockScreen)]
+pub fn lock_screen(props: &LockScreenProps) -> Html {
+    let LockScreenProps { store, on_unlock } = props.clone();
+
+    let error_text = use_state(|| "".to_string());
+    let passphrase = use_state(|| "".to_string());
+    let confirmation = use_state(|| "".to_string());
+    let records = use_state(|| None::<HashMap<String, String>>);
+    let runner = use_job_runner();
+
+    {
+        let store = store.clone();
+        let records = records.clone();
+        let error_text = error_text.clone();
+
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move {
+                    match store.load_all().await {
+                        Ok(loaded) => records.set(Some(loaded)),
+                        Err(e) => error_text.set(e),
+                    }
+                });
+            },
+            (),
+        );
+    }
+
+    let on_passphrase_change = {
+        let passphrase = passphrase.clone();
+        Callback::from(move |text| {
+            passphrase.set(text);
+        })
+    };
+
+    let on_confirmation_change = {
+        let confirmation = confirmation.clone();
+        Callback::from(move |text| {
+            confirmation.set(text);
+        })
+    };
+
+    let unlock = {
+        let store = store.clone();
+        let on_unlock = on_unlock.clone();
+        let error_text = error_text.clone();
+        let passphrase = passphrase.clone();
+        let records = records.clone();
+        let runner = runner.clone();
+
+        Callback::from(move |_| {
+            let Some(records) = (*records).clone() else {
+                return;
+            };
+            let Some(wrapped) = records.get(KEY_RECORD).cloned() else {
+                return;
+            };
+            error_text.set("".to_string());
+
+            let store = store.clone();
+            let on_unlock = on_unlock.clone();
+            let error_text = error_text.clone();
+
+            runner.run(
+                Job::UnwrapVaultKey {
+                    wrapped,
+                    passphrase: (*passphrase).clone(),
+                },
+                Callback::from(move |output: JobOutput| {
+                    let r = (|| -> anyhow::Result<()> {
+                        let key = SecretString::from(output.map_err(anyhow::Error::msg)?);
+
+                        on_unlock.emit(Vault::open(
+                            store.clone(),
+                            key.expose_secret(),
+                            records.clone(),
+                        )?);
+
+                        Ok(())
+                    })()
+                    .inspect_err(|e| error_text.set(e.to_string()));
+                    print_error_if_happened(r);
+                }),
+            );
+        })
+    };
+
+    let create = {
+        let store = store.clone();
+        let error_text = error_text.clone();
+        let passphrase = passphrase.clone();
+        let confirmation = confirmation.clone();
+        let runner = runner.clone();
+
+        Callback::from(move |_| {
+            if passphrase.is_empty() {
+                error_text.set("Choose a passphrase".to_string());
+                return;
+            }
+            if *passphrase != *confirmation {
+                error_text.set("The passphrases don't match".to_string());
+                return;
+            }
+            error_text.set("".to_string());
+
+            let key = Vault::generate_key();
+            let store = store.clone();
+            let on_unlock = on_unlock.clone();
+            let error_text = error_text.clone();
+
+            runner.run(
+                Job::WrapVaultKey {
+                    key: key.expose_secret().to_string(),
+                    passphrase: (*passphrase).clone(),
+                },
+                Callback::from(move |output: JobOutput| {
+                    let r = (|| -> anyhow::Result<()> {
+                        let wrapped = output.map_err(anyhow::Error::msg)?;
+
+                        on_unlock.emit(Vault::create(store.clone(), key.expose_secret(), wrapped)?);
+
+                        Ok(())
+                    })()
+                    .inspect_err(|e| error_text.set(e.to_string()));
+                    print_error_if_happened(r);
+                }),
+            );
+        })
+    };
+
+    let forget = {
+        let records = records.clone();
+        let error_text = error_text.clone();
+
+        Callback::from(move |_| {
+            let confirmed = web_sys::window()
+                .and_then(|window| {
+                    window
+                        .confirm_with_message(
+                            "Delete the saved identity, contacts and conversations? This can't be undone.",
+                        )
+                        .ok()
+                })
+                .unwrap_or(false);
+            if !confirmed {
+                return;
+            }
+
+            let store = store.clone();
+            let records = records.clone();
+            let error_text = error_text.clone();
+            spawn_local(async move {
+                match store.clear().await {
+                    Ok(()) => records.set(Some(HashMap::new())),
+                    Err(e) => error_text.set(e),
+                }
+            });
+        })
+    };
+
+    let form = match &*records {
+        None => html! { <p>{"Opening the vault..."}</p> },
+        Some(records) if records.contains_key(KEY_RECORD) => html! {
+            <>
+                <h2>{"Unlock"}</h2>
+                <p class="error">{(*error_text).clone()}</p>
+                <p>{"Your identity, contacts and conversations are saved in this browser, encrypted with your passphrase."}</p>
+                <div class="mb-3">
+                    <label for="lock_area_passphrase" class="form-label">{"Passphrase"}</label>
+                    <PasswordInput id="lock_area_passphrase" class="form-control" on_change={on_passphrase_change} value={(*passphrase).clone()}/>
+                </div>
+                <JobStatus runner={runner.clone()}/>
+                <button onclick={unlock} class="btn btn-primary" disabled={runner.is_running()}>{"Unlock"}</button>
+                <button onclick={forget} class="btn btn-danger" disabled={runner.is_running()}>{"Forget Everything"}</button>
+            </>
+        },
+        Some(_) => html! {
+            <>
+                <h2>{"Choose a Passphrase"}</h2>
+                <p class="error">{(*error_text).clone()}</p>
+                <p>{"Your identity, contacts and conversations will be saved in this browser, encrypted with this passphrase. There is no way to get them back if it's forgotten."}</p>
+                <div class="mb-3">
+                    <label for="lock_area_passphrase" class="form-label">{"Passphrase"}</label>
+                    <PasswordInput id="lock_area_passphrase" class="form-control" on_change={on_passphrase_change} value={(*passphrase).clone()}/>
+                </div>
+                <div class="mb-3">
+                    <label for="lock_area_confirmation" class="form-label">{"Passphrase Again"}</label>
+                    <PasswordInput id="lock_area_confirmation" class="form-control" on_change={on_confirmation_change} value={(*confirmation).clone()}/>
+                </div>
+                <JobStatus runner={runner.clone()}/>
+                <button onclick={create} class="btn btn-primary" disabled={runner.is_running()}>{"Create Vault"}</button>
+            </>
+        },
+    };
+
+    html! {
+        <div class="container">
+            <div class="card">
+                <div class="card-body">
+                    <form onsubmit={Callback::from(|e: SubmitEvent| { e.prevent_default() })}>
+                        {form}
+                    </form>
+                </div>
+            </div>
+        </div>
+    }
+}
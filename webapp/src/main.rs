@@ -1,16 +1,34 @@
+use gloo_timers::callback::Interval;
+use js_sys::Date;
 use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::spawn_local;
 use web_sys::console;
 use yew::prelude::*;
 
+mod contacts;
 mod encrypt_decrypt;
+mod identity;
+mod job;
+mod lock;
 mod receiver;
 mod sender;
+mod session;
+mod store;
+mod vault;
 
 mod text_input;
 
+use contacts::Contacts;
 use encrypt_decrypt::EncryptDecrypt;
+use identity::IdentityCard;
+use lock::LockScreen;
 use receiver::Receiver;
 use sender::Sender;
+use store::Store;
+use vault::Vault;
+
+const AUTO_LOCK_AFTER_MS: f64 = 5.0 * 60.0 * 1000.0;
+const AUTO_LOCK_CHECK_MS: u32 = 10_000;
 
 fn print_error_if_happened<T>(result: anyhow::Result<T>) {
     if let Err(e) = result {
@@ -21,26 +39,154 @@ fn print_error_if_happened<T>(result: anyhow::Result<T>) {
 
 #[function_component(App)]
 fn app() -> Html {
-    let shared_key = use_state(|| "".to_string());
+    let store = use_state(|| None);
+    let vault = use_state(|| None);
+
+    {
+        let store = store.clone();
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move {
+                    store.set(Some(Store::open().await));
+                });
+            },
+            (),
+        );
+    }
+
+    let on_unlock = {
+        let vault = vault.clone();
+        Callback::from(move |unlocked| {
+            vault.set(Some(unlocked));
+        })
+    };
+
+    let on_lock = {
+        let vault = vault.clone();
+        Callback::from(move |_| {
+            vault.set(None);
+        })
+    };
+
+    match (&*store, &*vault) {
+        (None, _) => html! {},
+        (Some(Err(e)), _) => html! {
+            <div class="container">
+                <p class="error">{format!("Can't open the browser's storage: {e}")}</p>
+            </div>
+        },
+        (Some(Ok(store)), None) => html! {
+            <LockScreen store={store.clone()} {on_unlock}/>
+        },
+        (Some(Ok(_)), Some(vault)) => html! {
+            <Unlocked vault={vault.clone()} {on_lock}/>
+        },
+    }
+}
+
+#[derive(Clone, PartialEq, Properties)]
+struct UnlockedProps {
+    vault: Vault,
+    on_lock: Callback<()>,
+}
+
+/// The app itself, which is only ever shown unlocked. Locking drops it, and
+/// with it every key it held.
+#[function_component(Unlocked)]
+fn unlocked(props: &UnlockedProps) -> Html {
+    let UnlockedProps { vault, on_lock } = props.clone();
+
+    let session = use_state(|| vault.session());
+    let contacts = use_state(|| vault.contacts());
+    let identity = use_state(|| vault.identity());
+    let last_activity = use_mut_ref(Date::now);
+
+    // everything is saved as it changes, so locking never loses anything. The
+    // vault won't save over a record it couldn't read, even here on mount
+    {
+        let vault = vault.clone();
+        use_effect_with_deps(
+            move |session: &Option<session::Session>| vault.save_session(session.as_ref()),
+            (*session).clone(),
+        );
+    }
+    {
+        let vault = vault.clone();
+        use_effect_with_deps(
+            move |contacts| vault.save_contacts(contacts),
+            (*contacts).clone(),
+        );
+    }
+    {
+        let vault = vault.clone();
+        use_effect_with_deps(
+            move |identity: &Option<e2eoffline::Identity>| vault.save_identity(identity.as_ref()),
+            (*identity).clone(),
+        );
+    }
+
+    {
+        let last_activity = last_activity.clone();
+        let on_lock = on_lock.clone();
+        use_effect_with_deps(
+            move |_| {
+                let interval = Interval::new(AUTO_LOCK_CHECK_MS, move || {
+                    if Date::now() - *last_activity.borrow() > AUTO_LOCK_AFTER_MS {
+                        on_lock.emit(());
+                    }
+                });
+                move || drop(interval)
+            },
+            (),
+        );
+    }
+
+    let on_activity = Callback::from(move |_| {
+        *last_activity.borrow_mut() = Date::now();
+    });
+
+    // the vault refuses to save over these, so they're shown rather than lost
+    let unreadable = vault.unreadable().into_iter().map(|(name, e)| {
+        html! {
+            <p class="error">{format!("The saved {name} record couldn't be read ({e}). It's been left as it is; Forget Everything on the lock screen starts over.")}</p>
+        }
+    });
 
     html! {
-        <div class="container">
+        <div class="container" onclick={on_activity.reform(|_: MouseEvent| ())} onkeydown={on_activity.reform(|_: KeyboardEvent| ())}>
+            <div class="d-flex justify-content-end align-items-center my-2">
+                <span class="me-2">{"Locks itself after 5 minutes without use"}</span>
+                <button class="btn btn-outline-secondary btn-sm" onclick={on_lock.reform(|_| ())}>{"Lock"}</button>
+            </div>
+            {for unreadable}
             <div class="card-group">
                 <div class="card">
                     <div class="card-body">
-                        <Sender shared_key={shared_key.clone()} />
+                        <Sender session={session.clone()} contacts={contacts.clone()} identity={identity.clone()} vault={vault.clone()} />
+                    </div>
+                </div>
+                <div class="card">
+                    <div class="card-body">
+                        <Receiver session={session.clone()} contacts={contacts.clone()} identity={identity.clone()} vault={vault.clone()} />
                     </div>
                 </div>
+            </div>
+            <div class="card-group">
                 <div class="card">
                     <div class="card-body">
-                        <Receiver shared_key={shared_key.clone()} />
+                        <EncryptDecrypt {session} {vault}/>
                     </div>
                 </div>
             </div>
             <div class="card-group">
                 <div class="card">
                     <div class="card-body">
-                        <EncryptDecrypt {shared_key}/>
+                        <Contacts {contacts}/>
+                    </div>
+                </div>
+                <div class="card">
+                    <div class="card-body">
+                        <IdentityCard {identity}/>
                     </div>
                 </div>
             </div>
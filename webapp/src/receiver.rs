@@ -1,25 +1,51 @@
 use web_sys::SubmitEvent;
 use yew::prelude::*;
 
-use crate::{print_error_if_happened, text_input::TextInput};
+use e2eoffline::{ContactBook, E2EOfflineBuilder, HandshakeMode, Identity};
+use webapp::worker::Job;
+
+use crate::{
+    contacts::check_contact,
+    job::{use_job_runner, with_identity, JobStatus},
+    print_error_if_happened,
+    session::Session,
+    text_input::TextInput,
+    vault::{Vault, RECIEVER_RECORD},
+};
 
 #[derive(Clone, PartialEq, Properties)]
 pub struct ReceiverProps {
-    pub shared_key: UseStateHandle<String>,
+    pub session: UseStateHandle<Option<Session>>,
+    pub contacts: UseStateHandle<ContactBook>,
+    pub identity: UseStateHandle<Option<Identity>>,
+    pub vault: Vault,
 }
 
 #[function_component(Receiver)]
 pub fn receiver(props: &ReceiverProps) -> Html {
-    let ReceiverProps { shared_key } = props.clone();
+    let ReceiverProps {
+        session,
+        contacts,
+        identity,
+        vault,
+    } = props.clone();
 
     let error_text = use_state(|| "".to_string());
-    let receiver_pubkey_text = use_state(|| "".to_string());
+    let contact_text = use_state(|| "".to_string());
+    let contact_name = use_state(|| "".to_string());
+    // a handshake started before the last lock carries on where it was
+    let builder = use_mut_ref(|| vault.builder(RECIEVER_RECORD));
+    let receiver_pubkey_text = use_state(|| {
+        (*builder)
+            .borrow()
+            .as_ref()
+            .and_then(|reciever| reciever.get_hello_encoded().ok())
+            .unwrap_or_default()
+    });
     let sender_pubkey_text = use_state(|| "".to_string());
     let connection_string_text = use_state(|| "".to_string());
 
-    let is_doing_work = use_state(|| false);
-
-    let builder = use_mut_ref(|| None);
+    let keygen = use_job_runner();
 
     let on_sender_pubkey_text_change = {
         let sender_pubkey_text = sender_pubkey_text.clone();
@@ -28,6 +54,13 @@ pub fn receiver(props: &ReceiverProps) -> Html {
         })
     };
 
+    let on_contact_name_change = {
+        let contact_name = contact_name.clone();
+        Callback::from(move |text| {
+            contact_name.set(text);
+        })
+    };
+
     let on_connection_string_text_change = {
         let connection_string_text = connection_string_text.clone();
         Callback::from(move |text| {
@@ -36,57 +69,81 @@ pub fn receiver(props: &ReceiverProps) -> Html {
     };
 
     let generate_reciever = {
-        let receiver_pubkey_text = receiver_pubkey_text.clone();
         let error_text = error_text.clone();
-        let builder = builder.clone();
-        let is_doing_work = is_doing_work.clone();
+        let keygen = keygen.clone();
+
+        // starts the handshake once there's a key to start it with
+        let start = {
+            let receiver_pubkey_text = receiver_pubkey_text.clone();
+            let error_text = error_text.clone();
+            let builder = builder.clone();
+            let vault = vault.clone();
+
+            Callback::from(move |identity: Identity| {
+                let r = (|| -> anyhow::Result<()> {
+                    let reciever = E2EOfflineBuilder::new_reciever_with_identity(
+                        &identity,
+                        HandshakeMode::Classic,
+                    );
+
+                    receiver_pubkey_text.set(reciever.get_hello_encoded().inspect_err(|e| {
+                        error_text.set(e.to_string());
+                    })?);
+
+                    vault.save_builder(RECIEVER_RECORD, Some(&reciever));
+                    builder.replace(Some(reciever));
+                    Ok(())
+                })();
+
+                print_error_if_happened(r);
+            })
+        };
 
         Callback::from(move |_| {
-            is_doing_work.set(true);
-            let r = (|| -> anyhow::Result<()> {
-                let reciever = e2eoffline::E2EOfflineBuilder::new_reciever();
-                error_text.set("".to_string());
-
-                receiver_pubkey_text.set(reciever.get_pubkey_encoded().map_err(|e| {
-                    error_text.set(e.to_string());
-                    e
-                })?);
-
-                builder.replace(Some(reciever));
-                Ok(())
-            })();
-            is_doing_work.set(false);
-
-            print_error_if_happened(r);
+            error_text.set("".to_string());
+
+            match &*identity {
+                Some(identity) => start.emit(identity.clone()),
+                None => keygen.run(
+                    Job::GenerateIdentity,
+                    with_identity(start.clone(), error_text.clone()),
+                ),
+            }
         })
     };
 
-    let generate_shared_key = {
+    let finish_handshake = {
         let sender_pubkey_text = sender_pubkey_text.clone();
         let error_text = error_text.clone();
         let connection_string_text = connection_string_text.clone();
-        let shared_key = shared_key.clone();
-        let is_doing_work = is_doing_work.clone();
+        let session = session.clone();
+        let contact_name = contact_name.clone();
+        let contact_text = contact_text.clone();
         Callback::from(move |_| {
-            is_doing_work.set(true);
             let r = (|| -> anyhow::Result<()> {
-                match (*builder).borrow_mut().as_mut() {
-                    Some(builder) => {
+                let mut builder = (*builder).borrow_mut();
+                match builder.as_mut() {
+                    Some(reciever) => {
                         error_text.set("".to_string());
 
-                        builder
+                        reciever
                             .set_other_public_key_encoded(&sender_pubkey_text)
-                            .map_err(|e| {
+                            .inspect_err(|_| {
                                 error_text.set("Invalid sender public key".to_string());
-                                e
                             })?;
 
-                        builder.recieve(&connection_string_text).map_err(|e| {
+                        contact_text.set(
+                            check_contact(&contacts, &contact_name, reciever)
+                                .inspect_err(|e| error_text.set(e.to_string()))?,
+                        );
+
+                        reciever.recieve(&connection_string_text).inspect_err(|_| {
                             error_text.set("Invalid connection string text".to_string());
-                            e
                         })?;
 
-                        shared_key.set(builder.get_shared_key()?);
+                        let reciever = builder.take().unwrap();
+                        vault.save_builder(RECIEVER_RECORD, None);
+                        session.set(Some(Session::new(reciever.build()?)));
                     }
                     None => {
                         error_text.set("Need to generate a receiver first".to_string());
@@ -94,7 +151,6 @@ pub fn receiver(props: &ReceiverProps) -> Html {
                 }
                 Ok(())
             })();
-            is_doing_work.set(false);
             print_error_if_happened(r);
         })
     };
@@ -104,23 +160,25 @@ pub fn receiver(props: &ReceiverProps) -> Html {
             <h2>{ "Reciever" }</h2>
             <p class="error">{(*error_text).clone()}</p>
             <div class="mb-3">
-                <label for="receiver_area_reciever_pubkey" class="form-label">{"Reciever Public Key"}</label>
+                <label for="receiver_area_reciever_pubkey" class="form-label">{"Reciever Hello"}</label>
                 <input id="receiver_area_reciever_pubkey" class="form-control" disabled={true} value={(*receiver_pubkey_text).clone()}/>
             </div>
-            <button class="btn btn-primary" onclick={generate_reciever} disabled={*is_doing_work}>{ "Generate Reciever" }</button>
+            <button class="btn btn-primary" onclick={generate_reciever} disabled={keygen.is_running()}>{ "Generate Reciever" }</button>
+            <JobStatus runner={keygen.clone()}/>
             <div class="mb-3">
                 <label for="reciever_area_sender_pubkey" class="form-label">{"Sender Public Key" }</label>
                 <TextInput id="reciever_area_sender_pubkey" class="form-control" on_change={on_sender_pubkey_text_change} value={(*sender_pubkey_text).clone()}/>
             </div>
             <div class="mb-3">
-                <label for="reciever_area_connection_string" class="form-label">{"Connection String" }</label>
-                <TextInput id="reciever_area_connection_string" class="form-control" on_change={on_connection_string_text_change} value={(*connection_string_text).clone()}/>
+                <label for="reciever_area_contact_name" class="form-label">{"Sender Contact Name" }</label>
+                <TextInput id="reciever_area_contact_name" class="form-control" on_change={on_contact_name_change} value={(*contact_name).clone()}/>
+                <p>{(*contact_text).clone()}</p>
             </div>
             <div class="mb-3">
-                <label for="reciever_area_shared_key" class="form-label">{"Shared Key" }</label>
-                <input value={(*shared_key).clone()} disabled={true} id="reciever_area_shared_key" class="form-control"/>
+                <label for="reciever_area_connection_string" class="form-label">{"Connection String" }</label>
+                <TextInput id="reciever_area_connection_string" class="form-control" on_change={on_connection_string_text_change} value={(*connection_string_text).clone()}/>
             </div>
-            <button onclick={generate_shared_key} class="btn btn-primary" disabled={*is_doing_work}>{ "Get Shared Key" }</button>
+            <button onclick={finish_handshake} class="btn btn-primary" disabled={keygen.is_running()}>{ "Finish Handshake" }</button>
         </form>
     }
 }
@@ -1,21 +1,50 @@
+use web_sys::HtmlSelectElement;
 use yew::prelude::*;
 
-use crate::{print_error_if_happened, text_input::TextInput};
+use e2eoffline::{CipherSuite, ContactBook, E2EOfflineBuilder, Identity};
+use webapp::worker::Job;
+
+use crate::{
+    contacts::check_contact,
+    job::{use_job_runner, with_identity, JobStatus},
+    print_error_if_happened,
+    session::Session,
+    text_input::TextInput,
+    vault::{Vault, SENDER_RECORD},
+};
 
 #[derive(Clone, PartialEq, Properties)]
 pub struct SenderProps {
-    pub shared_key: UseStateHandle<String>,
+    pub session: UseStateHandle<Option<Session>>,
+    pub contacts: UseStateHandle<ContactBook>,
+    pub identity: UseStateHandle<Option<Identity>>,
+    pub vault: Vault,
 }
 #[function_component(Sender)]
 
 pub fn sender(props: &SenderProps) -> Html {
-    let SenderProps { shared_key } = props.clone();
+    let SenderProps {
+        session,
+        contacts,
+        identity,
+        vault,
+    } = props.clone();
     let error_text = use_state(|| "".to_string());
-    let sender_pubkey = use_state(|| "".to_string());
+    let contact_text = use_state(|| "".to_string());
+    let contact_name = use_state(|| "".to_string());
+    // a handshake started before the last lock carries on where it was
+    let builder = use_mut_ref(|| vault.builder(SENDER_RECORD));
+    let sender_pubkey = use_state(|| {
+        (*builder)
+            .borrow()
+            .as_ref()
+            .and_then(|sender| sender.get_pubkey_encoded().ok())
+            .unwrap_or_default()
+    });
     let receiver_text = use_state(|| "".to_string());
     let connection_string_text = use_state(|| "".to_string());
-    let is_doing_work = use_state(|| false);
-    let builder = use_mut_ref(|| None);
+    let cipher_suite = use_state(CipherSuite::default);
+    let keygen = use_job_runner();
 
     let on_receiver_text_change = {
         let receiver_text = receiver_text.clone();
@@ -24,29 +53,59 @@ pub fn sender(props: &SenderProps) -> Html {
         })
     };
 
+    let on_contact_name_change = {
+        let contact_name = contact_name.clone();
+        Callback::from(move |text| {
+            contact_name.set(text);
+        })
+    };
+
+    let on_cipher_suite_change = {
+        let cipher_suite = cipher_suite.clone();
+        Callback::from(move |e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            cipher_suite.set(select.value().parse().unwrap_or_default());
+        })
+    };
+
     let create_sender = {
-        let sender_pubkey = sender_pubkey.clone();
         let error_text = error_text.clone();
-        let builder = builder.clone();
-        let is_doing_work = is_doing_work.clone();
+        let keygen = keygen.clone();
 
-        Callback::from(move |_| {
-            is_doing_work.set(true);
-            let r = (|| -> anyhow::Result<()> {
-                let sender = e2eoffline::E2EOfflineBuilder::new_sender();
-                error_text.set("".to_string());
+        // starts the handshake once there's a key to start it with
+        let start = {
+            let sender_pubkey = sender_pubkey.clone();
+            let error_text = error_text.clone();
+            let builder = builder.clone();
+            let vault = vault.clone();
 
-                sender_pubkey.set(sender.get_pubkey_encoded().map_err(|e| {
-                    error_text.set(e.to_string());
-                    e
-                })?);
+            Callback::from(move |identity: Identity| {
+                let r = (|| -> anyhow::Result<()> {
+                    let sender = E2EOfflineBuilder::new_sender_with_identity(&identity);
 
-                builder.replace(Some(sender));
+                    sender_pubkey.set(sender.get_pubkey_encoded().inspect_err(|e| {
+                        error_text.set(e.to_string());
+                    })?);
 
-                Ok(())
-            })();
-            is_doing_work.set(false);
-            print_error_if_happened(r);
+                    vault.save_builder(SENDER_RECORD, Some(&sender));
+                    builder.replace(Some(sender));
+
+                    Ok(())
+                })();
+                print_error_if_happened(r);
+            })
+        };
+
+        Callback::from(move |_| {
+            error_text.set("".to_string());
+
+            match &*identity {
+                Some(identity) => start.emit(identity.clone()),
+                None => keygen.run(
+                    Job::GenerateIdentity,
+                    with_identity(start.clone(), error_text.clone()),
+                ),
+            }
         })
     };
 
@@ -54,25 +113,34 @@ pub fn sender(props: &SenderProps) -> Html {
         let receiver_text = receiver_text.clone();
         let error_text = error_text.clone();
         let connection_string_text = connection_string_text.clone();
-        let shared_key = shared_key.clone();
+        let session = session.clone();
+        let contact_name = contact_name.clone();
+        let contact_text = contact_text.clone();
 
         Callback::from(move |_| {
-            is_doing_work.set(true);
             let r = (|| -> anyhow::Result<()> {
-                match (*builder).borrow_mut().as_mut() {
-                    Some(builder) => {
+                let mut builder = (*builder).borrow_mut();
+                match builder.as_mut() {
+                    Some(sender) => {
                         error_text.set("".to_string());
 
-                        builder
+                        sender
                             .set_other_public_key_encoded(&receiver_text)
-                            .map_err(|e| {
-                                error_text.set("Invalid reciever public key".to_string());
-                                e
+                            .inspect_err(|_| {
+                                error_text.set("Invalid reciever hello".to_string());
                             })?;
 
-                        shared_key.set(builder.get_shared_key()?);
+                        contact_text.set(
+                            check_contact(&contacts, &contact_name, sender)
+                                .inspect_err(|e| error_text.set(e.to_string()))?,
+                        );
+
+                        sender.set_cipher_suite(*cipher_suite);
+                        connection_string_text.set(sender.send()?);
 
-                        connection_string_text.set(builder.send()?);
+                        let sender = builder.take().unwrap();
+                        vault.save_builder(SENDER_RECORD, None);
+                        session.set(Some(Session::new(sender.build()?)));
                     }
                     None => {
                         error_text.set("Need to generate a sender first".to_string());
@@ -80,8 +148,6 @@ pub fn sender(props: &SenderProps) -> Html {
                 }
                 Ok(())
             })();
-
-            is_doing_work.set(false);
             print_error_if_happened(r);
         })
     };
@@ -94,20 +160,30 @@ pub fn sender(props: &SenderProps) -> Html {
             <label for="sender_area_sender_pubkey" class="form-label">{"Sender Public Key" }</label>
             <input id="sender_area_sender_pubkey" class="form-control" disabled={true} value={(*sender_pubkey).clone()}/>
         </div>
-        <button class="btn btn-primary" onclick={create_sender}>{ "Generate Sender" }</button>
+        <button class="btn btn-primary" onclick={create_sender} disabled={keygen.is_running()}>{ "Generate Sender" }</button>
+        <JobStatus runner={keygen.clone()}/>
         <div class="mb-3">
-            <label for="sender_area_reciever_pubkey" class="form-label">{"Receiver Public Key" }</label>
+            <label for="sender_area_reciever_pubkey" class="form-label">{"Receiver Hello" }</label>
             <TextInput class="form-control" id="sender_area_reciever_pubkey" value={(*receiver_text).clone()} on_change={on_receiver_text_change}/>
         </div>
         <div class="mb-3">
-            <label for="sender_area_connection_string" class="form-label">{"Connection String"}</label>
-            <input id="sender_area_connection_string" class="form-control" disabled={true} value = {(*connection_string_text).clone()}/>
+            <label for="sender_area_contact_name" class="form-label">{"Receiver Contact Name" }</label>
+            <TextInput class="form-control" id="sender_area_contact_name" value={(*contact_name).clone()} on_change={on_contact_name_change}/>
+            <p>{(*contact_text).clone()}</p>
+        </div>
+        <div class="mb-3">
+            <label for="sender_area_cipher_suite" class="form-label">{"Cipher"}</label>
+            <select id="sender_area_cipher_suite" class="form-select" onchange={on_cipher_suite_change}>
+                <option value="aes256gcm" selected={true}>{"AES-256-GCM"}</option>
+                <option value="xchacha20poly1305">{"XChaCha20-Poly1305 (faster in the browser)"}</option>
+                <option value="aes256gcmsiv">{"AES-256-GCM-SIV (safe if a nonce repeats)"}</option>
+            </select>
         </div>
         <div class="mb-3">
-            <label for="sender_area_shared_key" class="form-label">{"Shared Key"}</label>
-            <input class="form-control" id="sender_area_shared_key" disabled={true} value = {(*shared_key).clone()}/>
+            <label for="sender_area_connection_string" class="form-label">{"Connection String"}</label>
+            <input id="sender_area_connection_string" class="form-control" disabled={true} value = {(*connection_string_text).clone()}/>
         </div>
-        <button class="btn btn-primary" onclick={generate_connection_string}>{ "Generate Shared Key" }</button>
+        <button class="btn btn-primary" onclick={generate_connection_string} disabled={keygen.is_running()}>{ "Finish Handshake" }</button>
         </form>
     }
 }
@@ -0,0 +1,29 @@
+use std::{
+    cell::{RefCell, RefMut},
+    rc::Rc,
+};
+
+use e2eoffline::E2EOffline;
+
+/// The conversation a finished handshake hands over to the encrypt/decrypt
+/// card. The key stays inside it rather than being shown on the page.
+#[derive(Clone)]
+pub struct Session(Rc<RefCell<E2EOffline>>);
+
+impl Session {
+    pub fn new(e2e: E2EOffline) -> Self {
+        Self(Rc::new(RefCell::new(e2e)))
+    }
+
+    pub fn borrow_mut(&self) -> RefMut<'_, E2EOffline> {
+        self.0.borrow_mut()
+    }
+}
+
+// sessions are compared by identity, so a new one re-renders but encrypting
+// with the current one doesn't
+impl PartialEq for Session {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
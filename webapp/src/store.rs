@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use js_sys::{Array, Promise};
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{IdbDatabase, IdbObjectStore, IdbRequest, IdbTransactionMode};
+
+const DB_NAME: &str = "e2eoffline";
+const DB_VERSION: u32 = 1;
+const STORE_NAME: &str = "vault";
+
+/// The page's IndexedDB database: one object store of text records, which
+/// are all encrypted apart from their names (see `vault.rs`).
+///
+/// One connection is shared by everything, so writes land in the order they
+/// were made.
+#[derive(Clone, PartialEq)]
+pub struct Store {
+    db: IdbDatabase,
+}
+
+// waits for an IndexedDB request, returning its result
+async fn finished(request: &IdbRequest) -> Result<JsValue, String> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+
+    match JsFuture::from(promise).await {
+        Ok(_) => request.result().map_err(describe),
+        Err(_) => Err(match request.error() {
+            Ok(Some(e)) => e.message(),
+            _ => "IndexedDB request failed".to_string(),
+        }),
+    }
+}
+
+fn describe(e: JsValue) -> String {
+    e.as_string().unwrap_or_else(|| format!("{e:?}"))
+}
+
+impl Store {
+    pub async fn open() -> Result<Self, String> {
+        let factory = web_sys::window()
+            .and_then(|window| window.indexed_db().ok().flatten())
+            .ok_or("This browser doesn't offer IndexedDB")?;
+        let request = factory
+            .open_with_u32(DB_NAME, DB_VERSION)
+            .map_err(describe)?;
+
+        let on_upgrade = {
+            let request = request.clone();
+            Closure::once_into_js(move || {
+                if let Ok(db) = request.result() {
+                    let _ = db
+                        .unchecked_into::<IdbDatabase>()
+                        .create_object_store(STORE_NAME);
+                }
+            })
+        };
+        request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+
+        Ok(Self {
+            db: finished(&request).await?.unchecked_into(),
+        })
+    }
+
+    fn object_store(&self, mode: IdbTransactionMode) -> Result<IdbObjectStore, String> {
+        self.db
+            .transaction_with_str_and_mode(STORE_NAME, mode)
+            .and_then(|transaction| transaction.object_store(STORE_NAME))
+            .map_err(describe)
+    }
+
+    /// Reads every record, by name.
+    pub async fn load_all(&self) -> Result<HashMap<String, String>, String> {
+        let store = self.object_store(IdbTransactionMode::Readonly)?;
+        let names = store.get_all_keys().map_err(describe)?;
+        let values = store.get_all().map_err(describe)?;
+
+        let names: Array = finished(&names).await?.unchecked_into();
+        let values: Array = finished(&values).await?.unchecked_into();
+
+        Ok(names
+            .iter()
+            .zip(values.iter())
+            .filter_map(|(name, value)| Some((name.as_string()?, value.as_string()?)))
+            .collect())
+    }
+
+    pub async fn put(&self, name: &str, value: &str) -> Result<(), String> {
+        let request = self
+            .object_store(IdbTransactionMode::Readwrite)?
+            .put_with_key(&JsValue::from_str(value), &JsValue::from_str(name))
+            .map_err(describe)?;
+
+        finished(&request).await.map(|_| ())
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<(), String> {
+        let request = self
+            .object_store(IdbTransactionMode::Readwrite)?
+            .delete(&JsValue::from_str(name))
+            .map_err(describe)?;
+
+        finished(&request).await.map(|_| ())
+    }
+
+    /// Deletes every record, for when the passphrase is forgotten.
+    pub async fn clear(&self) -> Result<(), String> {
+        let request = self
+            .object_store(IdbTransactionMode::Readwrite)?
+            .clear()
+            .map_err(describe)?;
+
+        finished(&request).await.map(|_| ())
+    }
+}
@@ -28,6 +28,24 @@ pub fn text_input(props: &TextInputProps) -> Html {
     }
 }
 
+#[function_component(PasswordInput)]
+pub fn password_input(props: &TextInputProps) -> Html {
+    let TextInputProps {
+        on_change,
+        id,
+        class,
+        value,
+    } = props.clone();
+
+    let oninput = Callback::from(move |input_event: InputEvent| {
+        on_change.emit(get_value_from_input_event(input_event));
+    });
+
+    html! {
+        <input type="password"  {oninput} {value} {class} {id}/>
+    }
+}
+
 #[function_component(TextAreaInput)]
 pub fn text_area_input(props: &TextInputProps) -> Html {
     let TextInputProps {
@@ -0,0 +1,281 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use base64::Engine;
+use e2eoffline::{
+    ContactBook, E2EOffline, E2EOfflineBuilder, Identity, SecretString, SessionExport,
+};
+use wasm_bindgen_futures::spawn_local;
+
+use crate::{
+    contacts::take_legacy_contacts, print_error_if_happened, session::Session, store::Store,
+};
+
+/// The record holding the vault key, encrypted with the user's passphrase.
+pub const KEY_RECORD: &str = "key";
+pub const SENDER_RECORD: &str = "sender";
+pub const RECIEVER_RECORD: &str = "reciever";
+const IDENTITY_RECORD: &str = "identity";
+const CONTACTS_RECORD: &str = "contacts";
+const SESSION_RECORD: &str = "session";
+
+/// The unlocked vault: the records read from the [`Store`] when it was
+/// unlocked, and the key they're encrypted under. The key is random, and only
+/// ever stored wrapped with the passphrase, so saving doesn't have to
+/// stretch the passphrase again.
+#[derive(Clone)]
+pub struct Vault(Rc<VaultInner>);
+
+struct VaultInner {
+    store: Store,
+    key: RefCell<E2EOffline>,
+    records: RefCell<HashMap<String, String>>,
+    // records that couldn't be read, and why
+    unreadable: RefCell<HashMap<String, String>>,
+}
+
+impl Vault {
+    /// Makes a key for a new vault, as base64.
+    pub fn generate_key() -> SecretString {
+        let mut key = [0u8; 32];
+        getrandom::getrandom(&mut key).expect("the browser always has a random source");
+
+        base64::engine::general_purpose::URL_SAFE.encode(key).into()
+    }
+
+    pub fn open(store: Store, key: &str, records: HashMap<String, String>) -> anyhow::Result<Self> {
+        Ok(Self(Rc::new(VaultInner {
+            store,
+            key: RefCell::new(E2EOffline::from_key_base64(key)?),
+            records: RefCell::new(records),
+            unreadable: RefCell::new(HashMap::new()),
+        })))
+    }
+
+    /// Starts a new vault, with the contacts saved before there was one.
+    pub fn create(store: Store, key: &str, wrapped_key: String) -> anyhow::Result<Self> {
+        let vault = Self::open(store, key, HashMap::new())?;
+
+        vault.write(KEY_RECORD, Some(wrapped_key));
+        vault.save_contacts(&take_legacy_contacts());
+
+        Ok(vault)
+    }
+
+    fn read(&self, name: &str) -> Option<String> {
+        self.0.records.borrow().get(name).cloned()
+    }
+
+    // keeps the record for the next unlock in this page and writes it to the
+    // store in the background, deleting it if there's no value. A record that
+    // couldn't be read is left alone
+    fn write(&self, name: &'static str, value: Option<String>) {
+        if self.0.unreadable.borrow().contains_key(name) {
+            print_error_if_happened::<()>(Err(anyhow::anyhow!(
+                "not saving over the {name} record, which couldn't be read"
+            )));
+            return;
+        }
+
+        let store = self.0.store.clone();
+        let mut records = self.0.records.borrow_mut();
+
+        match value {
+            Some(value) => {
+                records.insert(name.to_string(), value.clone());
+                spawn_local(async move {
+                    print_error_if_happened(
+                        store.put(name, &value).await.map_err(anyhow::Error::msg),
+                    );
+                });
+            }
+            None => {
+                if records.remove(name).is_some() {
+                    spawn_local(async move {
+                        print_error_if_happened(
+                            store.delete(name).await.map_err(anyhow::Error::msg),
+                        );
+                    });
+                }
+            }
+        }
+    }
+
+    // each record is sealed together with its name, so one can't be swapped
+    // for another in the store
+    fn decrypt(&self, name: &str) -> anyhow::Result<Option<SecretString>> {
+        let Some(ciphertext) = self.read(name) else {
+            return Ok(None);
+        };
+        let sealed = self.0.key.borrow_mut().decrypt(&ciphertext)?;
+
+        match sealed
+            .expose_secret()
+            .strip_prefix(name)
+            .and_then(|value| value.strip_prefix('\n'))
+        {
+            Some(value) => Ok(Some(value.to_string().into())),
+            None => anyhow::bail!("it holds another record"),
+        }
+    }
+
+    fn encrypt(&self, name: &'static str, plaintext: Option<&str>) {
+        let r = (|| -> anyhow::Result<()> {
+            let ciphertext = match plaintext {
+                Some(plaintext) => {
+                    let sealed = SecretString::from(format!("{name}\n{plaintext}"));
+                    Some(self.0.key.borrow_mut().encrypt(sealed.expose_secret())?)
+                }
+                None => None,
+            };
+            self.write(name, ciphertext);
+            Ok(())
+        })();
+        print_error_if_happened(r);
+    }
+
+    pub fn identity(&self) -> Option<Identity> {
+        self.readable(IDENTITY_RECORD, || {
+            let Some(pem) = self.decrypt(IDENTITY_RECORD)? else {
+                return Ok(None);
+            };
+
+            Ok(Some(Identity::from_private_key_pem(
+                pem.expose_secret(),
+                "",
+            )?))
+        })
+    }
+
+    pub fn save_identity(&self, identity: Option<&Identity>) {
+        let r = (|| -> anyhow::Result<()> {
+            let pem = match identity {
+                Some(identity) => Some(identity.dangerously_export_private_key_pem()?),
+                None => None,
+            };
+            self.encrypt(
+                IDENTITY_RECORD,
+                pem.as_ref().map(SecretString::expose_secret),
+            );
+            Ok(())
+        })();
+        print_error_if_happened(r);
+    }
+
+    pub fn contacts(&self) -> ContactBook {
+        self.readable(CONTACTS_RECORD, || {
+            let Some(encoded) = self.decrypt(CONTACTS_RECORD)? else {
+                return Ok(None);
+            };
+
+            Ok(Some(ContactBook::from_encoded(encoded.expose_secret())?))
+        })
+        .unwrap_or_default()
+    }
+
+    pub fn save_contacts(&self, contacts: &ContactBook) {
+        self.encrypt(CONTACTS_RECORD, Some(&contacts.to_encoded()));
+    }
+
+    // sessions and handshakes are encrypted by the library too, but are still
+    // sealed with their names like everything else
+
+    pub fn session(&self) -> Option<Session> {
+        self.readable(SESSION_RECORD, || {
+            let Some(encoded) = self.decrypt(SESSION_RECORD)? else {
+                return Ok(None);
+            };
+            let export = SessionExport::from_encoded(encoded.expose_secret())?;
+            let e2e = E2EOffline::import_session_with(&export, &mut *self.0.key.borrow_mut())?;
+
+            Ok(Some(Session::new(e2e)))
+        })
+    }
+
+    pub fn save_session(&self, session: Option<&Session>) {
+        let r = (|| -> anyhow::Result<()> {
+            let export = match session {
+                Some(session) => Some(
+                    session
+                        .borrow_mut()
+                        .export_session_with(&mut *self.0.key.borrow_mut())?
+                        .to_encoded(),
+                ),
+                None => None,
+            };
+            self.encrypt(SESSION_RECORD, export.as_deref());
+            Ok(())
+        })();
+        print_error_if_happened(r);
+    }
+
+    /// The unfinished handshake saved under `name`, [`SENDER_RECORD`] or
+    /// [`RECIEVER_RECORD`].
+    pub fn builder(&self, name: &str) -> Option<E2EOfflineBuilder> {
+        self.readable(name, || {
+            let Some(state) = self.decrypt(name)? else {
+                return Ok(None);
+            };
+            let state = SessionExport::from_encoded(state.expose_secret())?;
+
+            Ok(Some(E2EOfflineBuilder::import_state_with(
+                &state,
+                &mut *self.0.key.borrow_mut(),
+            )?))
+        })
+    }
+
+    pub fn save_builder(&self, name: &'static str, builder: Option<&E2EOfflineBuilder>) {
+        let r = (|| -> anyhow::Result<()> {
+            let state = match builder {
+                Some(builder) => Some(
+                    builder
+                        .export_state_with(&mut *self.0.key.borrow_mut())?
+                        .to_encoded(),
+                ),
+                None => None,
+            };
+            self.encrypt(name, state.as_deref());
+            Ok(())
+        })();
+        print_error_if_happened(r);
+    }
+
+    // a record that can't be read is left out, rather than keeping the whole
+    // vault shut. It's remembered so it's never saved over, which would lose
+    // it for good
+    fn readable<T>(
+        &self,
+        name: &str,
+        read: impl FnOnce() -> anyhow::Result<Option<T>>,
+    ) -> Option<T> {
+        read().unwrap_or_else(|e| {
+            print_error_if_happened::<()>(Err(anyhow::anyhow!("the {name} record: {e}")));
+            self.0
+                .unreadable
+                .borrow_mut()
+                .insert(name.to_string(), e.to_string());
+            None
+        })
+    }
+
+    /// Why each record that couldn't be read was left out.
+    pub fn unreadable(&self) -> Vec<(String, String)> {
+        let mut unreadable: Vec<_> = self
+            .0
+            .unreadable
+            .borrow()
+            .iter()
+            .map(|(name, e)| (name.clone(), e.clone()))
+            .collect();
+        unreadable.sort();
+
+        unreadable
+    }
+}
+
+// vaults are compared by identity, like sessions
+impl PartialEq for Vault {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
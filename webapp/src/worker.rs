@@ -0,0 +1,142 @@
+use e2eoffline::{E2EOffline, Identity, SessionExport};
+use gloo_worker::{HandlerId, Worker, WorkerScope};
+use serde::{Deserialize, Serialize};
+
+/// Where Trunk puts the worker, relative to the page.
+pub const WORKER_PATH: &str = "worker.js";
+
+/// Work too slow for the page's thread. Keys cross between the page and the
+/// worker unprotected, since stretching a passphrase on both sides would
+/// bring the wait straight back. Sessions cross as session exports under a
+/// one-off `carrier` key, the only form they have outside an [`E2EOffline`].
+#[derive(Serialize, Deserialize)]
+pub enum Job {
+    /// Generates an RSA key, returning it as unprotected PEM.
+    GenerateIdentity,
+    /// Unlocks a key file, returning the key as unprotected PEM.
+    LoadIdentity { pem: String, passphrase: String },
+    /// Protects an unprotected PEM key with a passphrase.
+    ExportIdentity { pem: String, passphrase: String },
+    /// Encrypts the vault's raw key with the user's passphrase.
+    WrapVaultKey { key: String, passphrase: String },
+    /// Decrypts the vault's raw key, failing if the passphrase is wrong.
+    UnwrapVaultKey { wrapped: String, passphrase: String },
+    /// Starts a session from a passphrase both users share.
+    UsePassphrase { passphrase: String, carrier: String },
+    /// Protects a session with a passphrase, returning the export to show.
+    ExportSession {
+        session: String,
+        carrier: String,
+        passphrase: String,
+    },
+    /// Opens a session export protected with a passphrase.
+    ImportSession {
+        export: String,
+        passphrase: String,
+        carrier: String,
+    },
+}
+
+/// The text a [`Job`] produced, or what went wrong.
+pub type JobOutput = Result<String, String>;
+
+impl Job {
+    /// What the page shows while the job runs.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Job::GenerateIdentity => "Generating a key",
+            Job::LoadIdentity { .. } => "Unlocking the key file",
+            Job::ExportIdentity { .. } => "Protecting the key file",
+            Job::WrapVaultKey { .. } => "Protecting the vault",
+            Job::UnwrapVaultKey { .. } => "Unlocking the vault",
+            Job::UsePassphrase { .. } => "Stretching the passphrase",
+            Job::ExportSession { .. } => "Protecting the session export",
+            Job::ImportSession { .. } => "Unlocking the session export",
+        }
+    }
+
+    fn run(self) -> anyhow::Result<String> {
+        let identity = match self {
+            Job::GenerateIdentity => Identity::generate(),
+            Job::LoadIdentity { pem, passphrase } => {
+                Identity::from_private_key_pem(&pem, &passphrase)?
+            }
+            Job::ExportIdentity { pem, passphrase } => {
+                return Ok(
+                    Identity::from_private_key_pem(&pem, "")?.to_private_key_pem(&passphrase)?
+                );
+            }
+            Job::WrapVaultKey { key, passphrase } => {
+                return Ok(E2EOffline::from_passphrase(&passphrase)?.encrypt(&key)?);
+            }
+            Job::UnwrapVaultKey {
+                wrapped,
+                passphrase,
+            } => {
+                let key = E2EOffline::from_passphrase(&passphrase)?
+                    .decrypt(&wrapped)
+                    .map_err(|_| anyhow::anyhow!("Wrong passphrase"))?;
+
+                return Ok(key.expose_secret().to_string());
+            }
+            Job::UsePassphrase {
+                passphrase,
+                carrier,
+            } => {
+                return Ok(E2EOffline::from_passphrase(&passphrase)?
+                    .export_session_with(&mut E2EOffline::from_key_base64(&carrier)?)?
+                    .to_encoded());
+            }
+            Job::ExportSession {
+                session,
+                carrier,
+                passphrase,
+            } => {
+                let session = E2EOffline::import_session_with(
+                    &SessionExport::from_encoded(&session)?,
+                    &mut E2EOffline::from_key_base64(&carrier)?,
+                )?;
+
+                return Ok(session.export_session(&passphrase)?.to_encoded());
+            }
+            Job::ImportSession {
+                export,
+                passphrase,
+                carrier,
+            } => {
+                let session = E2EOffline::import_session(
+                    &SessionExport::from_encoded(&export)?,
+                    &passphrase,
+                )?;
+
+                return Ok(session
+                    .export_session_with(&mut E2EOffline::from_key_base64(&carrier)?)?
+                    .to_encoded());
+            }
+        };
+
+        Ok(identity
+            .dangerously_export_private_key_pem()?
+            .expose_secret()
+            .to_string())
+    }
+}
+
+/// Runs [`Job`]s one at a time, answering each with a [`JobOutput`].
+pub struct KeyWorker;
+
+impl Worker for KeyWorker {
+    type Message = ();
+    type Input = Job;
+    type Output = JobOutput;
+
+    fn create(_scope: &WorkerScope<Self>) -> Self {
+        Self
+    }
+
+    fn update(&mut self, _scope: &WorkerScope<Self>, _msg: Self::Message) {}
+
+    fn received(&mut self, scope: &WorkerScope<Self>, job: Self::Input, id: HandlerId) {
+        scope.respond(id, job.run().map_err(|e| e.to_string()));
+    }
+}